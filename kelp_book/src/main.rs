@@ -0,0 +1,77 @@
+use kelp_engine::kelp::book::builder::BookBuilder;
+use kelp_engine::kelp::pgn::PgnReader;
+use std::fs::File;
+use std::io::BufReader;
+
+fn print_usage() {
+    println!("Usage: kelp_book <out.bin> <file.pgn>... [--max-ply <n>] [--min-count <n>] [--min-rating <n>]");
+    println!(
+        "builds a polyglot book from every game with a result, moves are weighted by 2 per win"
+    );
+    println!("and 1 per draw. defaults to --max-ply 40 --min-count 1 --min-rating 0");
+}
+
+struct Args {
+    out: String,
+    pgns: Vec<String>,
+    builder: BookBuilder,
+}
+
+fn parse_args() -> Option<Args> {
+    let mut iter = std::env::args().skip(1);
+    let mut args = Args {
+        out: iter.next()?,
+        pgns: Vec::new(),
+        builder: BookBuilder::default(),
+    };
+
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--max-ply" => args.builder.max_ply = iter.next()?.parse().ok()?,
+            "--min-count" => args.builder.min_count = iter.next()?.parse().ok()?,
+            "--min-rating" => args.builder.min_rating = iter.next()?.parse().ok()?,
+            _ if arg.starts_with("--") => return None,
+            _ => args.pgns.push(arg),
+        }
+    }
+
+    if args.pgns.is_empty() {
+        return None;
+    }
+    Some(args)
+}
+
+fn main() {
+    let Some(mut args) = parse_args() else {
+        print_usage();
+        std::process::exit(1);
+    };
+
+    for path in &args.pgns {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) => {
+                eprintln!("{}: {}", path, e);
+                std::process::exit(1);
+            }
+        };
+
+        for game in PgnReader::new(BufReader::new(file)) {
+            match game {
+                Ok(game) => {
+                    args.builder.add_game(&game);
+                }
+                Err(e) => eprintln!("{}: {}", path, e),
+            }
+        }
+    }
+
+    let book = args.builder.build();
+    if let Err(e) = std::fs::write(&args.out, book.to_bytes()) {
+        eprintln!("{}: {}", args.out, e);
+        std::process::exit(1);
+    }
+
+    println!("games   : {}", args.builder.games());
+    println!("entries : {}", book.len());
+}
@@ -0,0 +1,211 @@
+use super::{Bitbase, BitbaseError, Bitbases, Ending};
+use crate::kelp::board::board::Board;
+use crate::kelp::board::fen::{Fen, FenParse};
+use crate::kelp::board::moves::MoveList;
+use crate::kelp::board::piece::{
+    BoardPiece::{self, *},
+    Color,
+};
+use crate::kelp::kelp_core::bitboard::BitBoard;
+use crate::kelp::kelp_core::lookup_table::LookupTable;
+use crate::kelp::mov_gen::generator::MovGen;
+use crate::kelp::syzygy::Wdl;
+use crate::kelp::Squares;
+use strum::IntoEnumIterator;
+
+pub(super) const EMPTY: &str = "8/8/8/8/8/8/8/8 w - - 0 1";
+
+// results of a position for the side to move, the low two bits of its state
+const UNKNOWN: u8 = 0;
+const WIN: u8 = 1;
+const LOSS: u8 = 2;
+const DRAW: u8 = 3;
+// a capture or promotion reaches a draw, the position can't be lost
+const DRAWN_EXIT: u8 = 4;
+
+// the long diagonals, the only squares a mirror of the board keeps in place
+const DIAGONALS: u64 = 0x8142_2418_1824_4281;
+
+/// Retrograde analysis of `ending`. every position starts with a count of the positions its moves
+/// that stay in the ending lead to, positions decided by their own moves are queued and a queued
+/// position decides its predecessors: a loss makes them wins, a win takes one from their count
+/// and the last one makes them losses. whatever is left undecided is a draw
+pub(super) fn generate(
+    ending: &Ending,
+    known: &Bitbases,
+    gen: &MovGen,
+) -> Result<Bitbase, BitbaseError> {
+    let size = ending.size();
+    let empty = Board::parse(Fen(EMPTY.to_string())).unwrap();
+    let mut state = vec![UNKNOWN; size];
+    let mut remaining = vec![0u8; size];
+    let mut queue = Vec::new();
+
+    let mut moves = MoveList::new();
+    let mut found = Vec::new();
+    for idx in 0..size {
+        let Some(board) = ending.board(idx, &empty, gen) else {
+            continue;
+        };
+        moves.clear();
+        gen.generate_legal_moves(&board, &mut moves);
+
+        // two moves can only lead to positions mirroring each other if the mirror keeps the king
+        // that doesn't move in place, they have the same index and count once
+        let waiting = board.get_king_square(!board.get_side_to_move()) as u8;
+        let symmetric = !ending.pawns && DIAGONALS >> waiting & 1 == 1;
+
+        found.clear();
+        let mut quiet = 0;
+        let mut won = false;
+        let mut drawn = false;
+        for &mov in moves.iter() {
+            let mut next = board;
+            if !mov.is_capture() && !mov.is_promotion() {
+                if symmetric {
+                    next.make_move(mov, false);
+                    found.push(ending.index(&next, false));
+                }
+                quiet += 1;
+                continue;
+            }
+
+            next.make_move(mov, false);
+            match known.probe(&next) {
+                Some(Wdl::Loss) => won = true,
+                Some(Wdl::Win) => {}
+                Some(_) => drawn = true,
+                None => {
+                    let white = super::side_name(&next, Color::White);
+                    let black = super::side_name(&next, Color::Black);
+                    return Err(BitbaseError::MissingEnding(white + &black));
+                }
+            }
+        }
+
+        if symmetric {
+            found.sort_unstable();
+            found.dedup();
+            quiet = found.len();
+        }
+
+        state[idx] = if moves.is_empty() {
+            if board.is_check(gen) {
+                LOSS
+            } else {
+                DRAW
+            }
+        } else if won {
+            WIN
+        } else if quiet == 0 {
+            if drawn {
+                DRAW
+            } else {
+                LOSS
+            }
+        } else {
+            remaining[idx] = quiet as u8;
+            if drawn {
+                DRAWN_EXIT
+            } else {
+                UNKNOWN
+            }
+        };
+        if state[idx] == WIN || state[idx] == LOSS {
+            queue.push(idx as u32);
+        }
+    }
+
+    while let Some(idx) = queue.pop() {
+        let lost = state[idx as usize] == LOSS;
+        let Some(board) = ending.board(idx as usize, &empty, gen) else {
+            continue;
+        };
+
+        predecessors(ending, &board, gen, &mut found);
+        for &prev in found.iter() {
+            if state[prev] & 3 != UNKNOWN {
+                continue;
+            }
+            if lost {
+                state[prev] = WIN;
+                queue.push(prev as u32);
+                continue;
+            }
+
+            remaining[prev] -= 1;
+            if remaining[prev] == 0 {
+                if state[prev] & DRAWN_EXIT != 0 {
+                    state[prev] = DRAW;
+                } else {
+                    state[prev] = LOSS;
+                    queue.push(prev as u32);
+                }
+            }
+        }
+    }
+
+    let mut wins = vec![0; size.div_ceil(64)];
+    let mut losses = vec![0; size.div_ceil(64)];
+    for (idx, &result) in state.iter().enumerate() {
+        match result {
+            WIN => wins[idx / 64] |= 1 << (idx % 64),
+            LOSS => losses[idx / 64] |= 1 << (idx % 64),
+            _ => {}
+        }
+    }
+
+    log::info!("Generated bitbase {}", ending.name());
+    Ok(Bitbase { wins, losses })
+}
+
+// the indices of the positions one move before `board` that stay in the ending, each only once
+fn predecessors(ending: &Ending, board: &Board, gen: &MovGen, found: &mut Vec<usize>) {
+    found.clear();
+    let table = LookupTable::global();
+    let mover = !board.get_side_to_move();
+    let occupancy = board.get_occ();
+
+    for piece in BoardPiece::iter().filter(|p| p.get_color() == mover) {
+        for sq in board.get_bitboard(piece) {
+            let origins = match piece {
+                WhitePawn | BlackPawn => pawn_origins(sq, mover, occupancy),
+                WhiteKnight | BlackKnight => table.get_knight_attacks(sq),
+                WhiteBishop | BlackBishop => table.get_bishop_attacks(sq, occupancy),
+                WhiteRook | BlackRook => table.get_rook_attacks(sq, occupancy),
+                WhiteQueen | BlackQueen => table.get_queen_attacks(sq, occupancy),
+                WhiteKing | BlackKing => table.get_king_attacks(sq),
+            } & !occupancy;
+
+            for origin in origins {
+                let mut prev = *board;
+                prev.remove_from_bb(piece, Squares::from_repr(sq).unwrap());
+                prev.add_to_bb(piece, Squares::from_repr(origin).unwrap());
+                prev.toggle_turn();
+                if !prev.is_check_opp(gen) {
+                    found.push(ending.index(&prev, false));
+                }
+            }
+        }
+    }
+    found.sort_unstable();
+    found.dedup();
+}
+
+// squares a pawn on `sq` could have been pushed from, never its first rank
+fn pawn_origins(sq: u8, color: Color, occupancy: BitBoard) -> BitBoard {
+    let (rank, back): (u8, i8) = match color {
+        Color::White => (sq / 8, -8),
+        Color::Black => (7 - sq / 8, 8),
+    };
+    let single = sq.wrapping_add_signed(back);
+
+    let mut origins = BitBoard::empty();
+    if rank >= 2 && !occupancy.get_bit(single) {
+        origins.set_bit(single);
+        if rank == 3 {
+            origins.set_bit(single.wrapping_add_signed(back));
+        }
+    }
+    origins
+}
@@ -0,0 +1,533 @@
+//! Win/draw/loss bitbases of small endings, generated by retrograde analysis
+
+mod generate;
+
+use crate::kelp::board::board::Board;
+use crate::kelp::board::piece::{
+    BoardPiece::{self, *},
+    Color,
+};
+use crate::kelp::mov_gen::generator::MovGen;
+use crate::kelp::syzygy::Wdl;
+use crate::kelp::Squares;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+use std::sync::OnceLock;
+
+/// most men, kings included, of an ending a bitbase can be generated for
+pub const MAX_MEN: usize = 4;
+
+/// endings generated on first use, KBK and KNK are draws by insufficient material
+const BUILTIN: [&str; 3] = ["KQK", "KRK", "KPK"];
+/// endings generated the first time they are probed, along with the ones they convert into
+const BUILTIN_FOUR_MEN: [&str; 9] = [
+    "KQKQ", "KQKR", "KQKB", "KQKN", "KRKR", "KRKB", "KRKN", "KQKP", "KRKP",
+];
+const BUILTIN_MEN: u8 = 4;
+
+// pieces besides the king by color, in the order they are named
+const PIECE_LETTERS: &str = "QRBNP";
+const PIECES: [[BoardPiece; 5]; 2] = [
+    [WhiteQueen, WhiteRook, WhiteBishop, WhiteKnight, WhitePawn],
+    [BlackQueen, BlackRook, BlackBishop, BlackKnight, BlackPawn],
+];
+
+// the squares the white king is moved to by mirroring the board, a1 to d4 on and below the long
+// diagonal. with pawns the ranks can't be mirrored and it only goes to the a to d files
+const KING_TRIANGLE: [u8; 10] = [0, 1, 2, 3, 9, 10, 11, 18, 19, 27];
+
+static BITBASES: OnceLock<Bitbases> = OnceLock::new();
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BitbaseError {
+    /// not a name like `KPK` or `KRKP` with at most `MAX_MEN` men
+    InvalidEnding(String),
+    /// a capture or promotion leads to an ending that wasn't generated before
+    MissingEnding(String),
+}
+
+impl Display for BitbaseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BitbaseError::InvalidEnding(name) => write!(f, "invalid ending {}", name),
+            BitbaseError::MissingEnding(name) => {
+                write!(f, "ending {} must be generated first", name)
+            }
+        }
+    }
+}
+
+/// Material of an ending, the side named first plays white
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ending {
+    name: String,
+    // the key of the ending's bitbase, see `material`
+    material: u64,
+    // color and kind, an index into `PIECES`, of every man besides the kings. identical pieces
+    // are next to each other
+    pieces: Vec<(Color, usize)>,
+    pawns: bool,
+}
+
+impl Ending {
+    pub fn parse(name: &str) -> Result<Ending, BitbaseError> {
+        let invalid = || BitbaseError::InvalidEnding(name.to_string());
+        let (white, black) = name
+            .strip_prefix('K')
+            .and_then(|name| name.split_once('K'))
+            .ok_or_else(invalid)?;
+
+        let mut pieces = Vec::new();
+        for (color, side) in [(Color::White, white), (Color::Black, black)] {
+            let mut kinds = side
+                .chars()
+                .map(|c| PIECE_LETTERS.find(c))
+                .collect::<Option<Vec<usize>>>()
+                .ok_or_else(invalid)?;
+            kinds.sort_unstable();
+            pieces.extend(kinds.into_iter().map(|kind| (color, kind)));
+        }
+        if pieces.len() + 2 > MAX_MEN {
+            return Err(invalid());
+        }
+
+        let side = |color: Color| -> String {
+            let letters = pieces.iter().filter(|&&(c, _)| c == color);
+            let letters = letters.map(|&(_, kind)| &PIECE_LETTERS[kind..=kind]);
+            format!("K{}", letters.collect::<String>())
+        };
+        let name = format!("{}{}", side(Color::White), side(Color::Black));
+        let material = pieces
+            .iter()
+            .map(|&(color, kind)| 1 << material_shift(color as usize, kind))
+            .sum();
+        let pawns = pieces.iter().any(|&(_, kind)| PIECES[0][kind] == WhitePawn);
+        Ok(Ending {
+            name,
+            material,
+            pieces,
+            pawns,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn men(&self) -> usize {
+        self.pieces.len() + 2
+    }
+
+    fn size(&self) -> usize {
+        let king_squares = if self.pawns { 32 } else { KING_TRIANGLE.len() };
+        king_squares << self.shift(0)
+    }
+
+    // one bit for the side to move, six bits for every man and the white king's square last
+    fn shift(&self, slot: usize) -> usize {
+        match slot {
+            0 => 1 + 6 * (self.men() - 1),
+            _ => 1 + 6 * (slot - 1),
+        }
+    }
+
+    fn piece(&self, slot: usize) -> BoardPiece {
+        match slot {
+            0 => WhiteKing,
+            1 => BlackKing,
+            _ => {
+                let (color, kind) = self.pieces[slot - 2];
+                PIECES[color as usize][kind]
+            }
+        }
+    }
+
+    /// index of `board`, which has this material. `flipped` swaps the colors and mirrors the ranks
+    /// for positions where black has the white pieces of the ending. the board is mirrored until
+    /// the white king is on one of the squares the index has room for
+    fn index(&self, board: &Board, flipped: bool) -> usize {
+        let white = if flipped { Color::Black } else { Color::White };
+        let white_king = board.get_king_square(white) as u8;
+
+        let mut mirror = if flipped { 56 } else { 0 };
+        if (white_king ^ mirror) & 7 > 3 {
+            mirror ^= 7;
+        }
+        if !self.pawns && (white_king ^ mirror) >> 3 > 3 {
+            mirror ^= 56;
+        }
+
+        if self.pawns {
+            return self.mirrored_index(board, flipped, mirror, false);
+        }
+        let king = white_king ^ mirror;
+        match (king >> 3).cmp(&(king & 7)) {
+            Ordering::Less => self.mirrored_index(board, flipped, mirror, false),
+            Ordering::Greater => self.mirrored_index(board, flipped, mirror, true),
+            // a king on the long diagonal stays there either way, the smaller index is used
+            Ordering::Equal => self
+                .mirrored_index(board, flipped, mirror, false)
+                .min(self.mirrored_index(board, flipped, mirror, true)),
+        }
+    }
+
+    // `index` with the squares xored with `mirror` and then mirrored along the long diagonal
+    // if `transpose` is set
+    fn mirrored_index(&self, board: &Board, flipped: bool, mirror: u8, transpose: bool) -> usize {
+        let relative = |color: Color| if flipped { !color } else { color };
+        let square = |sq: u8| {
+            let sq = sq ^ mirror;
+            (if transpose {
+                (sq & 7) << 3 | sq >> 3
+            } else {
+                sq
+            }) as usize
+        };
+
+        let king = square(board.get_king_square(relative(Color::White)) as u8);
+        let king = if self.pawns {
+            king / 8 * 4 + king % 8
+        } else {
+            KING_TRIANGLE
+                .iter()
+                .position(|&sq| sq as usize == king)
+                .unwrap()
+        };
+        let mut idx = (relative(board.get_side_to_move()) == Color::Black) as usize;
+        idx |= king << self.shift(0);
+        idx |= square(board.get_king_square(relative(Color::Black)) as u8) << self.shift(1);
+
+        let mut slot = 2;
+        for (i, &(color, kind)) in self.pieces.iter().enumerate() {
+            if i > 0 && self.pieces[i - 1] == (color, kind) {
+                continue;
+            }
+            // identical pieces are stored lowest square first
+            let mut squares = [0; MAX_MEN];
+            let mut count = 0;
+            for sq in board.get_bitboard(PIECES[relative(color) as usize][kind]) {
+                squares[count] = square(sq);
+                count += 1;
+            }
+            squares[..count].sort_unstable();
+            for &sq in squares[..count].iter() {
+                idx |= sq << self.shift(slot);
+                slot += 1;
+            }
+        }
+        idx
+    }
+
+    /// the position at `idx` built on `empty`, `None` if it can't occur in a game
+    fn board(&self, idx: usize, empty: &Board, gen: &MovGen) -> Option<Board> {
+        let square = |slot: usize| {
+            let sq = (idx >> self.shift(slot)) & 63;
+            (match slot {
+                0 if self.pawns => sq / 4 * 8 + sq % 4,
+                0 => KING_TRIANGLE[sq] as usize,
+                _ => sq,
+            }) as u8
+        };
+
+        let mut board = *empty;
+        let mut occupied = 0u64;
+        for slot in 0..self.men() {
+            let sq = square(slot);
+            let piece = self.piece(slot);
+            if occupied & (1 << sq) != 0 {
+                return None;
+            }
+            if matches!(piece, WhitePawn | BlackPawn) && !(8..56).contains(&sq) {
+                return None;
+            }
+            if slot > 2 && self.piece(slot - 1) == piece && square(slot - 1) > sq {
+                return None;
+            }
+            occupied |= 1 << sq;
+            board.add_to_bb(piece, Squares::from_repr(sq).unwrap());
+        }
+        if idx & 1 == 1 {
+            board.toggle_turn();
+        }
+
+        // with the white king on the long diagonal a position has two indices, the smaller is used
+        let king = square(0);
+        if !self.pawns && king >> 3 == king & 7 && self.index(&board, false) != idx {
+            return None;
+        }
+        // kings next to each other are caught here too
+        (!board.is_check_opp(gen)).then_some(board)
+    }
+}
+
+/// Win and loss bits of every position of an ending, the other positions are draws
+pub struct Bitbase {
+    wins: Vec<u64>,
+    losses: Vec<u64>,
+}
+
+impl Bitbase {
+    /// solves `ending`, the endings its captures and promotions lead to must be in `known`
+    pub fn generate(
+        ending: &Ending,
+        known: &Bitbases,
+        gen: &MovGen,
+    ) -> Result<Bitbase, BitbaseError> {
+        generate::generate(ending, known, gen)
+    }
+
+    fn get(&self, idx: usize) -> Wdl {
+        let bit = 1 << (idx % 64);
+        if self.wins[idx / 64] & bit != 0 {
+            Wdl::Win
+        } else if self.losses[idx / 64] & bit != 0 {
+            Wdl::Loss
+        } else {
+            Wdl::Draw
+        }
+    }
+}
+
+// an ending and its bitbase, which is generated on first use if it wasn't up front
+struct Table {
+    ending: Ending,
+    bitbase: OnceLock<Bitbase>,
+}
+
+impl Table {
+    fn bitbase(&self, known: &Bitbases) -> &Bitbase {
+        self.bitbase.get_or_init(|| {
+            Bitbase::generate(&self.ending, known, &MovGen::new())
+                .expect("built in endings only convert into built in endings")
+        })
+    }
+}
+
+/// Bitbases by the material of their ending
+#[derive(Default)]
+pub struct Bitbases {
+    tables: HashMap<u64, Table>,
+}
+
+impl Bitbases {
+    /// generates `endings` in order, an ending must come after the ones it converts into
+    pub fn generate(endings: &[&str], gen: &MovGen) -> Result<Bitbases, BitbaseError> {
+        let mut bitbases = Bitbases::default();
+        for name in endings {
+            let ending = Ending::parse(name)?;
+            let bitbase = Bitbase::generate(&ending, &bitbases, gen)?;
+            let table = Table {
+                ending,
+                bitbase: OnceLock::from(bitbase),
+            };
+            bitbases.tables.insert(table.ending.material, table);
+        }
+        Ok(bitbases)
+    }
+
+    /// the built in bitbases, the three men endings are generated on the first call. the others
+    /// take a while and are generated the first time they are probed or by `fill`
+    pub fn global() -> &'static Bitbases {
+        BITBASES.get_or_init(|| {
+            log::info!("Generating bitbases");
+            let mut bitbases = Bitbases::generate(&BUILTIN, &MovGen::new()).unwrap();
+            for name in BUILTIN_FOUR_MEN {
+                let table = Table {
+                    ending: Ending::parse(name).unwrap(),
+                    bitbase: OnceLock::new(),
+                };
+                bitbases.tables.insert(table.ending.material, table);
+            }
+            bitbases
+        })
+    }
+
+    /// generates every bitbase that wasn't generated yet
+    pub fn fill(&self) {
+        log::info!("Generating four men bitbases");
+        for table in self.tables.values() {
+            table.bitbase(self);
+        }
+    }
+
+    /// WDL of `board` for the side to move, `None` if there is no bitbase of its ending. the
+    /// bitbase is generated if it wasn't yet. positions without mating material are draws.
+    /// en passant is ignored
+    pub fn probe(&self, board: &Board) -> Option<Wdl> {
+        self.probe_with(board, |table| Some(table.bitbase(self)))
+    }
+
+    /// `probe` that doesn't wait for a bitbase to be generated, `None` until it is
+    fn probe_generated(&self, board: &Board) -> Option<Wdl> {
+        self.probe_with(board, |table| table.bitbase.get())
+    }
+
+    fn probe_with<'a>(
+        &'a self,
+        board: &Board,
+        bitbase: impl FnOnce(&'a Table) -> Option<&'a Bitbase>,
+    ) -> Option<Wdl> {
+        if board.info.castle.0 != 0 {
+            return None;
+        }
+        if board.is_insufficient_material() {
+            return Some(Wdl::Draw);
+        }
+
+        let (table, flipped) = match self.tables.get(&material(board, Color::White)) {
+            Some(table) => (table, false),
+            None => (self.tables.get(&material(board, Color::Black))?, true),
+        };
+        Some(bitbase(table)?.get(table.ending.index(board, flipped)))
+    }
+}
+
+/// WDL of `board` for the side to move from the built in bitbases, `None` for bigger endings and
+/// for four men endings until they are generated, the search can't wait for them
+pub fn probe(board: &Board) -> Option<Wdl> {
+    if board.get_occ().count_bits() > BUILTIN_MEN {
+        return None;
+    }
+    Bitbases::global().probe_generated(board)
+}
+
+// the piece counts of `board` in four bits per kind, `first`'s pieces in the low bits like the
+// pieces of the side named first in an ending
+fn material(board: &Board, first: Color) -> u64 {
+    let mut material = 0;
+    for (side, color) in [first, !first].into_iter().enumerate() {
+        for (kind, piece) in PIECES[color as usize].iter().enumerate() {
+            let count = board.get_bitboard(*piece).count_bits() as u64;
+            material |= count << material_shift(side, kind);
+        }
+    }
+    material
+}
+
+fn material_shift(side: usize, kind: usize) -> usize {
+    4 * (PIECE_LETTERS.len() * side + kind)
+}
+
+// `color`'s pieces as they are named in an ending
+fn side_name(board: &Board, color: Color) -> String {
+    let mut name = String::from("K");
+    for (kind, piece) in PIECES[color as usize].iter().enumerate() {
+        for _ in 0..board.get_bitboard(*piece).count_bits() {
+            name.push_str(&PIECE_LETTERS[kind..=kind]);
+        }
+    }
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kelp::board::fen::{Fen, FenParse};
+
+    fn wdl(fen: &str) -> Option<Wdl> {
+        let board = Board::parse(Fen(fen.to_string())).unwrap();
+        probe(&board)
+    }
+
+    #[test]
+    fn ending_test() {
+        let ending = Ending::parse("KPK").unwrap();
+        assert_eq!(ending.name(), "KPK");
+        assert_eq!(ending.men(), 3);
+        assert_eq!(Ending::parse("KPRK").unwrap().name(), "KRPK");
+        assert_eq!(Ending::parse("KKQ").unwrap().name(), "KKQ");
+
+        for name in ["", "KPP", "KQRKR", "KXK", "PKK", "KPKK"] {
+            let err = BitbaseError::InvalidEnding(name.to_string());
+            assert_eq!(Ending::parse(name), Err(err));
+        }
+    }
+
+    #[test]
+    fn index_test() {
+        let gen = MovGen::new();
+        let empty = Board::parse(Fen(generate::EMPTY.to_string())).unwrap();
+        let ending = Ending::parse("KNNK").unwrap();
+
+        // the white king is mirrored to the queen side
+        let board = Board::parse(Fen("8/8/2k5/8/8/8/8/N3K1N1 b - - 0 1".to_string())).unwrap();
+        let idx = ending.index(&board, false);
+        assert_eq!(
+            ending.board(idx, &empty, &gen).unwrap().to_fen(),
+            "8/8/5k2/8/8/8/8/1N1K3N b - - 0 1"
+        );
+
+        // the same position with the colors swapped, then mirrored along the long diagonal
+        for (fen, flipped) in [
+            ("n3k1n1/8/8/8/8/2K5/8/8 w - - 0 1", true),
+            ("N7/8/5k2/8/K7/8/N7/8 b - - 0 1", false),
+        ] {
+            let board = Board::parse(Fen(fen.to_string())).unwrap();
+            assert_eq!(ending.index(&board, flipped), idx, "{}", fen);
+        }
+
+        // the knights in the wrong order, then the kings next to each other
+        let swapped = (idx & !(0xfff << 7)) | (6 << 7);
+        assert!(ending.board(swapped, &empty, &gen).is_none());
+        let kings = (idx & !(63 << 1)) | (11 << 1);
+        assert!(ending.board(kings, &empty, &gen).is_none());
+    }
+
+    #[test]
+    fn four_men_test() {
+        let gen = MovGen::new();
+        let board = |fen: &str| Board::parse(Fen(fen.to_string())).unwrap();
+
+        // the rook taking the queen leads to an ending that wasn't generated
+        let err = Bitbases::generate(&["KQK", "KQKR"], &gen).err();
+        assert_eq!(err, Some(BitbaseError::MissingEnding("KKR".to_string())));
+
+        // the knights can't force mate, but they can mate in one
+        let bitbases = Bitbases::generate(&["KNNK"], &gen).unwrap();
+        let fen = "k7/8/1KN5/3N4/8/8/8/8 w - - 0 1";
+        assert_eq!(bitbases.probe(&board(fen)), Some(Wdl::Win));
+        let fen = "8/8/8/8/3n4/1kn5/8/K7 b - - 0 1";
+        assert_eq!(bitbases.probe(&board(fen)), Some(Wdl::Win));
+        let fen = "8/8/8/4k3/8/8/8/1N2K1N1 w - - 0 1";
+        assert_eq!(bitbases.probe(&board(fen)), Some(Wdl::Draw));
+        assert_eq!(
+            bitbases.probe(&board("8/8/8/4k3/8/8/8/R3K3 b - - 0 1")),
+            None
+        );
+    }
+
+    #[test]
+    fn builtin_test() {
+        // the king in front of its pawn on the sixth rank wins whoever moves
+        assert_eq!(wdl("4k3/8/4K3/4P3/8/8/8/8 w - - 0 1"), Some(Wdl::Win));
+        assert_eq!(wdl("4k3/8/4K3/4P3/8/8/8/8 b - - 0 1"), Some(Wdl::Loss));
+        // a rook pawn with the defending king in the corner
+        assert_eq!(wdl("k7/8/8/8/8/8/P7/K7 w - - 0 1"), Some(Wdl::Draw));
+        // stalemate
+        assert_eq!(wdl("k7/P7/1K6/8/8/8/8/8 b - - 0 1"), Some(Wdl::Draw));
+        // the pawn can't be caught
+        assert_eq!(wdl("7k/8/8/8/8/8/P7/K7 b - - 0 1"), Some(Wdl::Loss));
+
+        assert_eq!(wdl("8/8/8/4k3/8/8/8/R3K3 b - - 0 1"), Some(Wdl::Loss));
+        assert_eq!(wdl("8/8/8/4K3/8/8/8/r3k3 w - - 0 1"), Some(Wdl::Loss));
+        assert_eq!(wdl("8/8/8/4K3/8/8/8/r3k3 b - - 0 1"), Some(Wdl::Win));
+        // the undefended queen is taken
+        assert_eq!(wdl("8/8/8/8/8/8/2kQ4/7K b - - 0 1"), Some(Wdl::Draw));
+
+        // the rook wins against a bishop only with the defending king cornered. four men
+        // bitbases are generated when they are first probed through `Bitbases`
+        let builtin = |fen: &str| {
+            let board = Board::parse(Fen(fen.to_string())).unwrap();
+            Bitbases::global().probe(&board)
+        };
+        assert_eq!(builtin("k7/8/1K6/8/8/8/2b5/7R w - - 0 1"), Some(Wdl::Win));
+        assert_eq!(builtin("7r/2B5/8/8/8/1k6/8/K7 b - - 0 1"), Some(Wdl::Win));
+        assert_eq!(wdl("7r/2B5/8/8/8/1k6/8/K7 b - - 0 1"), Some(Wdl::Win));
+        assert_eq!(builtin("8/8/8/4k3/8/3b4/8/R3K3 w - - 0 1"), Some(Wdl::Draw));
+        assert_eq!(builtin("8/8/8/4k3/8/8/2PP4/2B1K3 w - - 0 1"), None);
+
+        assert_eq!(wdl("8/8/8/4k3/8/8/8/2B1K3 w - - 0 1"), Some(Wdl::Draw));
+        assert_eq!(wdl("8/8/8/4k3/8/8/3P4/2B1K3 w - - 0 1"), None);
+    }
+}
@@ -6,8 +6,9 @@ use super::piece::{
     Color,
 };
 use super::zobrist::Zobrist;
-use crate::kelp::board::moves::{Move, MoveArray, MoveHistory, MoveType};
+use crate::kelp::board::moves::{Move, MoveHistory, MoveType};
 use crate::kelp::board::piece::Color::*;
+use crate::kelp::kelp_core::lookup_table::LookupTable;
 use crate::kelp::mov_gen::generator::MovGen;
 use crate::kelp::Squares::{self, *};
 use crate::kelp::{kelp_core::bitboard::BitBoard, BitBoardArray, BoardInfo, GamePhase, GameState};
@@ -19,15 +20,17 @@ use strum::IntoEnumIterator;
 
 const STARTPOS: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
 
-#[derive(Clone, Eq, PartialEq)]
+/// a position without its game history, cheap to copy
+#[derive(Clone, Copy, Eq, PartialEq)]
 pub struct Board {
-    pub bitboards: BitBoardArray,
+    bitboards: BitBoardArray,
+    // piece on every square and the occupancy of each color, kept in sync with `bitboards`
+    mailbox: [Option<BoardPiece>; 64],
+    occupancy: [BitBoard; 2],
     pub hash: u64,
     pub state: GameState,
     pub phase: GamePhase,
     pub info: BoardInfo,
-    pub move_history: MoveArray,
-    zobrist: Zobrist,
 }
 
 impl Default for Board {
@@ -41,17 +44,33 @@ impl Board {
     #[inline(always)]
     pub fn add_to_bb(&mut self, piece: BoardPiece, square: Squares) {
         self.bitboards[piece as usize].set_bit(square as u8);
+        self.occupancy[piece.get_color() as usize].set_bit(square as u8);
+        self.mailbox[square as usize] = Some(piece);
     }
 
     #[inline(always)]
     pub fn remove_from_bb(&mut self, piece: BoardPiece, square: Squares) {
         self.bitboards[piece as usize].clear_bit(square as u8);
+        self.occupancy[piece.get_color() as usize].clear_bit(square as u8);
+        self.mailbox[square as usize] = None;
+    }
+
+    // replaces every piece, the mailbox and occupancies are rebuilt from `bitboards`
+    fn set_bitboards(&mut self, bitboards: BitBoardArray) {
+        self.bitboards = [BitBoard::empty(); 12];
+        self.mailbox = [None; 64];
+        self.occupancy = [BitBoard::empty(); 2];
+        for piece in BoardPiece::iter() {
+            for sq in bitboards[piece as usize] {
+                self.add_to_bb(piece, Squares::from_repr(sq).unwrap());
+            }
+        }
     }
 
     #[allow(dead_code)]
     pub fn replace_piece(&mut self, piece: BoardPiece, square: Squares) {
-        for p in BoardPiece::iter() {
-            self.remove_piece(p, square);
+        if let Some(old) = self.get_piece(square) {
+            self.remove_piece(old, square);
         }
         self.add_piece(piece, square);
     }
@@ -59,13 +78,13 @@ impl Board {
     #[inline(always)]
     pub fn add_piece(&mut self, piece: BoardPiece, square: Squares) {
         self.add_to_bb(piece, square);
-        self.hash ^= self.zobrist.get_piece_key(piece, square);
+        self.hash ^= Zobrist::global().get_piece_key(piece, square);
     }
 
     #[inline(always)]
     pub fn remove_piece(&mut self, piece: BoardPiece, square: Squares) {
         self.remove_from_bb(piece, square);
-        self.hash ^= self.zobrist.get_piece_key(piece, square);
+        self.hash ^= Zobrist::global().get_piece_key(piece, square);
     }
 
     #[inline(always)]
@@ -76,7 +95,7 @@ impl Board {
 
     #[inline(always)]
     pub fn get_piece(&self, square: Squares) -> Option<BoardPiece> {
-        BoardPiece::iter().find(|&piece| self.bitboards[piece as usize].get_bit(square as u8))
+        self.mailbox[square as usize]
     }
 
     #[inline(always)]
@@ -106,31 +125,17 @@ impl Board {
 
     #[inline(always)]
     pub fn get_white_occ(&self) -> BitBoard {
-        let mut out = BitBoard::empty();
-        out = self.bitboards[WhitePawn as usize]
-            | self.bitboards[WhiteKnight as usize]
-            | self.bitboards[WhiteBishop as usize]
-            | self.bitboards[WhiteRook as usize]
-            | self.bitboards[WhiteQueen as usize]
-            | self.bitboards[WhiteKing as usize];
-        out
+        self.occupancy[White as usize]
     }
 
     #[inline(always)]
     pub fn get_black_occ(&self) -> BitBoard {
-        let mut out = BitBoard::empty();
-        out = self.bitboards[BlackPawn as usize]
-            | self.bitboards[BlackKnight as usize]
-            | self.bitboards[BlackBishop as usize]
-            | self.bitboards[BlackRook as usize]
-            | self.bitboards[BlackQueen as usize]
-            | self.bitboards[BlackKing as usize];
-        out
+        self.occupancy[Black as usize]
     }
 
     #[inline(always)]
     pub fn get_occ(&self) -> BitBoard {
-        self.get_white_occ() | self.get_black_occ()
+        self.occupancy[White as usize] | self.occupancy[Black as usize]
     }
 
     #[inline(always)]
@@ -140,25 +145,38 @@ impl Board {
 
     #[inline(always)]
     pub fn set_en_passant(&mut self, square: Squares) {
-        if self.info.en_passant.is_some() {
-            self.hash ^= self
-                .zobrist
-                .get_en_passant_key(self.info.en_passant.unwrap());
-        }
+        self.clear_en_passant();
         self.info.en_passant = Some(square);
-        self.hash ^= self.zobrist.get_en_passant_key(square);
+        self.hash ^= self.en_passant_key();
     }
 
     #[inline(always)]
     pub fn clear_en_passant(&mut self) {
-        if self.info.en_passant.is_some() {
-            self.hash ^= self
-                .zobrist
-                .get_en_passant_key(self.info.en_passant.unwrap());
-        }
+        self.hash ^= self.en_passant_key();
         self.info.en_passant = None;
     }
 
+    // the en passant square is only part of the hash if a pawn can capture onto it, so the
+    // position after a double push without such a pawn repeats the same position later on
+    #[inline(always)]
+    pub(crate) fn en_passant_key(&self) -> u64 {
+        let Some(square) = self.info.en_passant else {
+            return 0;
+        };
+        // a square on the third rank is captured onto by black
+        let (capturer, pawn) = match square as u8 {
+            0..=31 => (Black, BlackPawn),
+            _ => (White, WhitePawn),
+        };
+        let attackers = LookupTable::global().get_pawn_attacks(!capturer, square as u8)
+            & self.get_bitboard(pawn);
+        if attackers.is_empty() {
+            0
+        } else {
+            Zobrist::global().get_en_passant_key(square)
+        }
+    }
+
     pub fn to_fen(&self) -> String {
         let fen = Fen::parse(self);
         if fen.is_err() {
@@ -169,7 +187,7 @@ impl Board {
 
     pub fn toggle_turn(&mut self) {
         self.info.turn = !self.info.turn;
-        self.hash ^= self.zobrist.get_side_key();
+        self.hash ^= Zobrist::global().get_side_key();
     }
 
     #[inline(always)]
@@ -194,62 +212,90 @@ impl Board {
 
     #[inline(always)]
     pub fn update_hash(&mut self) {
-        self.hash = self.zobrist.get_key(self);
+        self.hash = Zobrist::global().get_key(self);
     }
 
     #[allow(dead_code)]
     pub fn generated_hash(&self) -> u64 {
-        self.zobrist.get_key(self)
+        Zobrist::global().get_key(self)
     }
 
     pub fn is_fifty_move_draw(&self) -> bool {
         self.info.halfmove_clock >= 100
     }
 
+    /// true if no sequence of moves can end in mate: bare kings, a single minor piece or only
+    /// bishops that all stand on squares of one colour
+    pub fn is_insufficient_material(&self) -> bool {
+        const LIGHT_SQUARES: u64 = 0x55aa_55aa_55aa_55aa;
+
+        // a pawn, rook or queen can always mate with some help
+        let pieces = [
+            WhitePawn, BlackPawn, WhiteRook, BlackRook, WhiteQueen, BlackQueen,
+        ];
+        if pieces.iter().any(|&p| !self.get_bitboard(p).is_empty()) {
+            return false;
+        }
+
+        let knights = self.get_bitboard(WhiteKnight) | self.get_bitboard(BlackKnight);
+        let bishops = self.get_bitboard(WhiteBishop) | self.get_bitboard(BlackBishop);
+        match knights.count_bits() {
+            0 => bishops.0 & LIGHT_SQUARES == 0 || bishops.0 & !LIGHT_SQUARES == 0,
+            1 => bishops.is_empty(),
+            _ => false,
+        }
+    }
+
 }
 
 // Make / Unmake move and helper functions
 impl Board {
     #[inline(always)]
-    fn make_normal(&mut self, mov: Move) {
-        if mov.capture.is_some() {
-            self.remove_piece(mov.capture.unwrap(), mov.to);
+    fn make_normal(&mut self, mov: Move, piece: BoardPiece, capture: Option<BoardPiece>) {
+        if let Some(capture) = capture {
+            self.remove_piece(capture, mov.to());
         }
-        self.move_piece(mov.piece, mov.from, mov.to);
+        self.move_piece(piece, mov.from(), mov.to());
     }
 
     #[inline(always)]
-    fn make_double_pawn(&mut self, mov: Move) {
-        self.move_piece(mov.piece, mov.from, mov.to);
-        let color = mov.piece.get_color();
+    fn make_double_pawn(&mut self, mov: Move, piece: BoardPiece) {
+        self.move_piece(piece, mov.from(), mov.to());
+        let color = piece.get_color();
         let en_passant = match color {
-            White => mov.to - 8,
-            Black => mov.to + 8,
+            White => mov.to() - 8,
+            Black => mov.to() + 8,
         };
         self.set_en_passant(en_passant);
     }
 
     #[inline(always)]
-    fn make_en_passant(&mut self, mov: Move) {
-        self.move_piece(mov.piece, mov.from, mov.to);
-        let capture = match mov.piece.get_color() {
-            White => mov.to - 8,
-            Black => mov.to + 8,
+    fn make_en_passant(&mut self, mov: Move, piece: BoardPiece, capture: BoardPiece) {
+        self.move_piece(piece, mov.from(), mov.to());
+        let captured = match piece.get_color() {
+            White => mov.to() - 8,
+            Black => mov.to() + 8,
         };
-        self.remove_piece(mov.capture.unwrap(), capture);
-    }
-    #[inline(always)]
-    fn make_promotion(&mut self, mov: Move, promoted_to: BoardPiece) {
-        if mov.capture.is_some() {
-            self.remove_piece(mov.capture.unwrap(), mov.to);
+        self.remove_piece(capture, captured);
+    }
+    #[inline(always)]
+    fn make_promotion(
+        &mut self,
+        mov: Move,
+        piece: BoardPiece,
+        capture: Option<BoardPiece>,
+        promoted_to: BoardPiece,
+    ) {
+        if let Some(capture) = capture {
+            self.remove_piece(capture, mov.to());
         }
-        self.remove_piece(mov.piece, mov.from);
-        self.add_piece(promoted_to, mov.to);
+        self.remove_piece(piece, mov.from());
+        self.add_piece(promoted_to, mov.to());
     }
 
     #[inline(always)]
-    fn make_castle(&mut self, mov: Move, castle: CastlingRights) {
-        let color = mov.piece.get_color();
+    fn make_castle(&mut self, piece: BoardPiece, castle: CastlingRights) {
+        let color = piece.get_color();
         let (king_from, king_to, rook_from, rook_to) = match castle {
             CastlingRights::WhiteKingSide => (E1, G1, H1, F1),
             CastlingRights::WhiteQueenSide => (E1, C1, A1, D1),
@@ -260,46 +306,52 @@ impl Board {
             White => WhiteRook,
             Black => BlackRook,
         };
-        self.move_piece(mov.piece, king_from, king_to);
+        self.move_piece(piece, king_from, king_to);
         self.move_piece(rook, rook_from, rook_to);
         self.info.castle.remove(castle);
     }
 
     #[inline(always)]
-    fn unmake_normal(&mut self, mov: Move) {
-        self.move_piece(mov.piece, mov.to, mov.from);
-        if mov.capture.is_some() {
-            self.add_piece(mov.capture.unwrap(), mov.to);
+    fn unmake_normal(&mut self, mov: Move, piece: BoardPiece, capture: Option<BoardPiece>) {
+        self.move_piece(piece, mov.to(), mov.from());
+        if let Some(capture) = capture {
+            self.add_piece(capture, mov.to());
         }
     }
 
     #[inline(always)]
-    fn unmake_double_pawn(&mut self, mov: Move) {
-        self.move_piece(mov.piece, mov.to, mov.from);
+    fn unmake_double_pawn(&mut self, mov: Move, piece: BoardPiece) {
+        self.move_piece(piece, mov.to(), mov.from());
     }
 
     #[inline(always)]
-    fn unmake_en_passant(&mut self, mov: Move) {
-        self.move_piece(mov.piece, mov.to, mov.from);
-        let capture = match mov.piece.get_color() {
-            White => mov.to - 8,
-            Black => mov.to + 8,
+    fn unmake_en_passant(&mut self, mov: Move, piece: BoardPiece, capture: BoardPiece) {
+        self.move_piece(piece, mov.to(), mov.from());
+        let captured = match piece.get_color() {
+            White => mov.to() - 8,
+            Black => mov.to() + 8,
         };
-        self.add_piece(mov.capture.unwrap(), capture);
+        self.add_piece(capture, captured);
     }
 
     #[inline(always)]
-    fn unmake_promotion(&mut self, mov: Move, promoted_to: BoardPiece) {
-        self.add_piece(mov.piece, mov.from);
-        self.remove_piece(promoted_to, mov.to);
-        if mov.capture.is_some() {
-            self.add_piece(mov.capture.unwrap(), mov.to);
+    fn unmake_promotion(
+        &mut self,
+        mov: Move,
+        piece: BoardPiece,
+        capture: Option<BoardPiece>,
+        promoted_to: BoardPiece,
+    ) {
+        self.add_piece(piece, mov.from());
+        self.remove_piece(promoted_to, mov.to());
+        if let Some(capture) = capture {
+            self.add_piece(capture, mov.to());
         }
     }
 
     #[inline(always)]
-    fn unmake_castle(&mut self, mov: Move, castle: CastlingRights) {
-        let color = mov.piece.get_color();
+    fn unmake_castle(&mut self, piece: BoardPiece, castle: CastlingRights) {
+        let color = piece.get_color();
         let (king_from, king_to, rook_from, rook_to) = match castle {
             CastlingRights::WhiteKingSide => (E1, G1, H1, F1),
             CastlingRights::WhiteQueenSide => (E1, C1, A1, D1),
@@ -310,37 +362,18 @@ impl Board {
             White => WhiteRook,
             Black => BlackRook,
         };
-        self.move_piece(mov.piece, king_to, king_from);
+        self.move_piece(piece, king_to, king_from);
         self.move_piece(rook, rook_to, rook_from);
         self.info.castle.add(castle);
     }
 
-    // makes move and pushes it to history also logs it, shouldn't be used for search or perft testing because of performance overhead by logging
-    #[inline(always)]
-    pub fn make(&mut self, mov: Move) {
-        let history = self.make_move(mov, false);
-        if history.is_some() {
-            // log::info!("pushing {:?} to history", history.unwrap());
-            self.move_history.push(history.unwrap());
-        }
-    }
-
-    // unmake move from history
-    #[inline(always)]
-    pub fn unmake(&mut self) {
-        let mov = self.move_history.pop();
-        if mov.is_none() {
-            return;
-        }
-        // log::info!("unmaking {:?} from history", mov.unwrap());
-        self.unmake_move(mov.unwrap());
-    }
-
     #[inline(always)]
     pub fn make_move(&mut self, mov: Move, only_captures: bool) -> Option<MoveHistory> {
-        if only_captures && mov.capture.is_none() {
+        if only_captures && !mov.is_capture() {
             return None;
         }
+        let piece = mov.piece(self);
+        let capture = mov.capture(self);
 
         use BoardPiece::*;
         use CastlingRights::*;
@@ -352,24 +385,27 @@ impl Board {
         let old_half_move_clock = self.info.halfmove_clock;
         let old_hash = self.hash;
 
-        match mov.move_type {
+        // cleared before any piece moves, the hash of the square depends on the pawns around it
+        self.clear_en_passant();
+
+        match mov.move_type() {
             Normal => {
-                self.make_normal(mov);
+                self.make_normal(mov, piece, capture);
             }
             DoublePawnPush => {
-                self.make_double_pawn(mov);
+                self.make_double_pawn(mov, piece);
             }
 
             EnPassant => {
-                self.make_en_passant(mov);
+                self.make_en_passant(mov, piece, capture.unwrap());
             }
 
             Promotion(promoted_to) => {
-                self.make_promotion(mov, promoted_to.unwrap());
+                self.make_promotion(mov, piece, capture, promoted_to.unwrap());
             }
 
             Castle(castle) => {
-                self.make_castle(mov, castle);
+                self.make_castle(piece, castle);
             }
         };
 
@@ -384,40 +420,39 @@ impl Board {
         self.toggle_turn();
 
         // Update halfmove clock
-        if mov.capture.is_some() || mov.piece == WhitePawn || mov.piece == BlackPawn {
+        if capture.is_some() || piece == WhitePawn || piece == BlackPawn {
             self.info.halfmove_clock = 0;
         } else {
             self.info.halfmove_clock += 1;
         }
 
-        // Update en passant
-        if mov.move_type != DoublePawnPush {
-            self.clear_en_passant();
-        }
-
-        // Update castling rights
-        if mov.from == A1 || mov.to == A1 {
-            self.info.castle.remove(WhiteQueenSide);
-        } else if mov.from == H1 || mov.to == H1 {
-            self.info.castle.remove(WhiteKingSide);
-        } else if mov.from == A8 || mov.to == A8 {
-            self.info.castle.remove(BlackQueenSide);
-        } else if mov.from == H8 || mov.to == H8 {
-            self.info.castle.remove(BlackKingSide);
-        } else if mov.from == E1 || mov.to == E1 {
-            self.info.castle.remove(WhiteKingSide);
-            self.info.castle.remove(WhiteQueenSide);
-        } else if mov.from == E8 || mov.to == E8 {
-            self.info.castle.remove(BlackKingSide);
-            self.info.castle.remove(BlackQueenSide);
+        // Update castling rights, both squares count since a rook can capture a rook on its home square
+        for sq in [mov.from(), mov.to()] {
+            match sq {
+                A1 => self.info.castle.remove(WhiteQueenSide),
+                H1 => self.info.castle.remove(WhiteKingSide),
+                A8 => self.info.castle.remove(BlackQueenSide),
+                H8 => self.info.castle.remove(BlackKingSide),
+                E1 => {
+                    self.info.castle.remove(WhiteKingSide);
+                    self.info.castle.remove(WhiteQueenSide);
+                }
+                E8 => {
+                    self.info.castle.remove(BlackKingSide);
+                    self.info.castle.remove(BlackQueenSide);
+                }
+                _ => {}
+            }
         }
 
         // Update Castle hash
-        self.hash ^= self.zobrist.get_castle_key(old_castle.0);
-        self.hash ^= self.zobrist.get_castle_key(self.info.castle.0);
+        self.hash ^= Zobrist::global().get_castle_key(old_castle.0);
+        self.hash ^= Zobrist::global().get_castle_key(self.info.castle.0);
 
         Some(MoveHistory {
-            mov: mov,
+            mov,
+            piece,
+            capture,
             castle_rights: old_castle,
             en_passant: old_en_passant,
             hash: old_hash,
@@ -427,25 +462,30 @@ impl Board {
 
     #[inline(always)]
     pub fn unmake_move(&mut self, history: MoveHistory) {
-        let color = history.mov.piece.get_color();
-        let mov = history.mov;
+        let MoveHistory {
+            mov,
+            piece,
+            capture,
+            ..
+        } = history;
+        let color = piece.get_color();
 
         use MoveType::*;
-        match mov.move_type {
+        match mov.move_type() {
             Normal => {
-                self.unmake_normal(mov);
+                self.unmake_normal(mov, piece, capture);
             }
             DoublePawnPush => {
-                self.unmake_double_pawn(mov);
+                self.unmake_double_pawn(mov, piece);
             }
             EnPassant => {
-                self.unmake_en_passant(mov);
+                self.unmake_en_passant(mov, piece, capture.unwrap());
             }
             Promotion(promoted_to) => {
-                self.unmake_promotion(mov, promoted_to.unwrap());
+                self.unmake_promotion(mov, piece, capture, promoted_to.unwrap());
             }
             Castle(castle) => {
-                self.unmake_castle(mov, castle);
+                self.unmake_castle(piece, castle);
             }
         };
 
@@ -568,7 +608,7 @@ impl FenParse<Fen, Board, FenParseError> for Board {
         let mut fullmove_clock = 0;
 
         if parts.len() > 4 {
-            halfmove_clock = parts[4].parse::<u8>().unwrap();
+            halfmove_clock = parts[4].parse::<u16>().unwrap();
             fullmove_clock = parts[5].parse::<u16>().unwrap();
         }
 
@@ -595,11 +635,12 @@ impl FenParse<Fen, Board, FenParseError> for Board {
         };
 
         let mut board = Board {
-            bitboards,
+            bitboards: [BitBoard::empty(); 12],
+            mailbox: [None; 64],
+            occupancy: [BitBoard::empty(); 2],
             hash: 0, // TODO
             state: game_state,
             phase: game_phase,
-            move_history: MoveArray::new(),
             info: BoardInfo {
                 turn,
                 castle: castling_rights,
@@ -607,9 +648,9 @@ impl FenParse<Fen, Board, FenParseError> for Board {
                 halfmove_clock,
                 fullmove_clock,
             },
-            zobrist: Zobrist::new(),
         };
 
+        board.set_bitboards(bitboards);
         board.update_hash();
 
         Ok(board)
@@ -622,13 +663,7 @@ impl Display for Board {
         board.push_str("+---+---+---+---+---+---+---+---+\n");
         for rank in (0..8).rev() {
             for file in 0..8 {
-                let mut piece: Option<BoardPiece> = None;
-                for i in 0..12 {
-                    if self.bitboards[i].get_bit(rank * 8 + file) {
-                        piece = Some(BoardPiece::from(i as u8));
-                        break;
-                    }
-                }
+                let piece = self.get_piece(Squares::from_rank_file(rank, file));
                 board.push_str(&format!(
                     "| {} ",
                     match piece {
@@ -653,13 +688,7 @@ impl Debug for Board {
         board.push_str("+---+---+---+---+---+---+---+---+\n");
         for rank in (0..8).rev() {
             for file in 0..8 {
-                let mut piece: Option<BoardPiece> = None;
-                for i in 0..12 {
-                    if self.bitboards[i].get_bit(rank * 8 + file) {
-                        piece = Some(BoardPiece::from(i as u8));
-                        break;
-                    }
-                }
+                let piece = self.get_piece(Squares::from_rank_file(rank, file));
                 board.push_str(&format!(
                     "| {} ",
                     match piece {
@@ -687,7 +716,7 @@ impl Debug for Board {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::kelp::kelp_core::lookup_table::LookupTable;
+    use crate::kelp::board::moves::MoveList;
 
     #[test]
     fn fen_to_board() {
@@ -710,18 +739,20 @@ mod tests {
         let mut board = Board::default();
         let test_fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
 
-        board.bitboards[0] = BitBoard(34628232960);
-        board.bitboards[1] = BitBoard(68719738880);
-        board.bitboards[2] = BitBoard(6144);
-        board.bitboards[3] = BitBoard(129);
-        board.bitboards[4] = BitBoard(2097152);
-        board.bitboards[5] = BitBoard(16);
-        board.bitboards[6] = BitBoard(12754334924144640);
-        board.bitboards[7] = BitBoard(37383395344384);
-        board.bitboards[8] = BitBoard(18015498021109760);
-        board.bitboards[9] = BitBoard(9295429630892703744);
-        board.bitboards[10] = BitBoard(4503599627370496);
-        board.bitboards[11] = BitBoard(1152921504606846976);
+        board.set_bitboards([
+            BitBoard(34628232960),
+            BitBoard(68719738880),
+            BitBoard(6144),
+            BitBoard(129),
+            BitBoard(2097152),
+            BitBoard(16),
+            BitBoard(12754334924144640),
+            BitBoard(37383395344384),
+            BitBoard(18015498021109760),
+            BitBoard(9295429630892703744),
+            BitBoard(4503599627370496),
+            BitBoard(1152921504606846976),
+        ]);
 
         let fen = board.to_fen();
         assert_eq!(fen, test_fen);
@@ -731,12 +762,26 @@ mod tests {
         assert_eq!(fen.unwrap().to_string(), test_fen);
     }
 
+    // the mailbox and occupancies match what the piece bitboards say
+    fn assert_in_sync(board: &Board) {
+        let mut occupancy = [BitBoard::empty(); 2];
+        for sq in Squares::iter() {
+            let piece = BoardPiece::iter().find(|&p| board.get_piece_occ(p).get_bit(sq as u8));
+            assert_eq!(board.get_piece(sq), piece, "{}", sq);
+            if let Some(piece) = piece {
+                occupancy[piece.get_color() as usize].set_bit(sq as u8);
+            }
+        }
+        assert_eq!(board.get_white_occ(), occupancy[0]);
+        assert_eq!(board.get_black_occ(), occupancy[1]);
+    }
+
     fn incremental_zobrist_test_driver(depth: u16, board: &mut Board, gen: &mut MovGen) {
         if depth == 0 {
             return;
         }
-        gen.generate_moves(board);
-        let move_list = gen.get_move_list();
+        let mut move_list = MoveList::new();
+        gen.generate_moves(board, &mut move_list);
 
         for mov in move_list.iter() {
             let history = board.make_move(*mov, false);
@@ -745,10 +790,12 @@ mod tests {
             }
             let hash = board.generated_hash();
             assert_eq!(hash, board.hash);
+            assert_in_sync(board);
             incremental_zobrist_test_driver(depth - 1, board, gen);
             board.unmake_move(history.unwrap());
             let hash = board.generated_hash();
             assert_eq!(hash, board.hash);
+            assert_in_sync(board);
         }
     }
 
@@ -759,9 +806,7 @@ mod tests {
         let promotion_fen =
             "r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1 ".to_string();
 
-        let mut table = LookupTable::new();
-        table.populate();
-        let mut gen = MovGen::new(&table);
+        let mut gen = MovGen::new();
 
         let mut board = Board::parse(Fen(tricky_fen)).unwrap();
         incremental_zobrist_test_driver(3, &mut board, &mut gen);
@@ -769,4 +814,22 @@ mod tests {
         let mut board = Board::parse(Fen(promotion_fen)).unwrap();
         incremental_zobrist_test_driver(3, &mut board, &mut gen);
     }
+
+    #[test]
+    fn copy_board_test() {
+        assert!(std::mem::size_of::<Board>() <= 256);
+
+        let gen = MovGen::new();
+        let board = Board::default();
+        let mut copy = board;
+        let mut moves = MoveList::new();
+        gen.generate_legal_moves(&copy, &mut moves);
+        copy.make_move(moves.get(0), false).unwrap();
+
+        assert_ne!(copy, board);
+        assert_eq!(board, Board::default());
+        // every board hashes with the same keys
+        assert_eq!(board.hash, Board::default().hash);
+        assert_eq!(copy.hash, copy.generated_hash());
+    }
 }
@@ -168,23 +168,23 @@ impl Fen {
         }
 
         if parts.len() > 4 {
-            match parts[4].parse::<u8>() {
+            match parts[4].parse::<u16>() {
                 Ok(_) => {}
                 Err(_) => {
                     return Err(FenParseError::InvalidHalfMoveClock(format!(
                         "Invalid halfmove clock: {}, \
-            must be a number that can be parsed as a u8",
+            must be a number that can be parsed as a u16",
                         parts[4]
                     )))
                 }
             }
 
-            match parts[5].parse::<u8>() {
+            match parts[5].parse::<u16>() {
                 Ok(_) => {}
                 Err(_) => {
                     return Err(FenParseError::InvalidFullMoveClock(format!(
                         "Invalid fullmove number: {}, \
-            must be a number that can be parsed as a u8",
+            must be a number that can be parsed as a u16",
                         parts[5]
                     )))
                 }
@@ -2,4 +2,5 @@ pub mod board;
 pub mod fen;
 pub mod moves;
 pub mod piece;
+pub mod san;
 mod zobrist;
@@ -1,4 +1,5 @@
-use super::piece::{BoardPiece, Color};
+use super::board::Board;
+use super::piece::{BoardPiece, BoardPiece::*, Color};
 use crate::kelp::{Squares, MAX_SIZE_MOVES_ARR};
 use std::fmt::{Debug, Display};
 use strum_macros::{Display, EnumIter, EnumString};
@@ -87,51 +88,59 @@ pub enum GenType {
     Capture,
 }
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
-pub struct Move {
-    pub from: Squares,
-    pub to: Squares,
-    pub piece: BoardPiece,
-    pub capture: Option<BoardPiece>,
-    pub move_type: MoveType,
-    pub gen_type: GenType,
-}
+/// A move packed into 16 bits, from square in bits 0..6, to square in 6..12 and flags in 12..16.
+/// the moving and captured pieces aren't stored, they are read from the board the move is made on
+#[derive(Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Move(u16);
 
 impl Display for Move {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self.move_type {
-            // MoveType::EnPassant => write!(f, "{}{}e.p.", self.from, self.to),
-            MoveType::Promotion(Some(promotion)) => {
-                write!(
-                    f,
-                    "{}{}{}",
-                    self.from,
-                    self.to,
-                    promotion.to_string().to_lowercase()
-                )
-            }
-            _ => write!(f, "{}{}", self.from, self.to),
+        match self.get_promotion() {
+            Some(promotion) => write!(
+                f,
+                "{}{}{}",
+                self.from(),
+                self.to(),
+                promotion.to_string().to_lowercase()
+            ),
+            None => write!(f, "{}{}", self.from(), self.to()),
         }
     }
 }
 
+impl Debug for Move {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Move({}, {:?})", self, self.move_type())
+    }
+}
+
 impl Move {
+    // quiet moves have no flags
+    const DOUBLE_PAWN_PUSH: u16 = 1;
+    const CASTLE: u16 = 2;
+    const CAPTURE: u16 = 4;
+    const EN_PASSANT: u16 = 5;
+    // the low two bits pick knight, bishop, rook or queen, combines with `CAPTURE`
+    const PROMOTION: u16 = 8;
+
+    /// a1a1, never a real move
+    pub const NULL: Move = Move(0);
+
+    /// packs a move, the pieces themselves aren't stored, the board the move is made on knows them
     pub fn new(
         from: Squares,
         to: Squares,
-        piece: BoardPiece,
+        _piece: BoardPiece,
         capture: Option<BoardPiece>,
         move_type: MoveType,
-        gen_type: GenType,
+        _gen_type: GenType,
     ) -> Self {
-        Move {
-            from,
-            to,
-            piece,
-            capture,
-            move_type,
-            gen_type,
+        let mut mov = Move(from as u16 | (to as u16) << 6);
+        if capture.is_some() {
+            mov.0 |= Self::CAPTURE << 12;
         }
+        mov.set_type(move_type);
+        mov
     }
 
     pub fn new_promotion(
@@ -143,156 +152,404 @@ impl Move {
         gen_type: GenType,
     ) -> Self {
         let move_type = MoveType::Promotion(promotion);
-        Move {
-            from,
-            to,
-            piece,
-            capture,
-            move_type,
-            gen_type,
-        }
+        Move::new(from, to, piece, capture, move_type, gen_type)
     }
 
+    /// changes the kind of move, keeps whether it captures
     pub fn set_type(&mut self, move_type: MoveType) {
-        self.move_type = move_type;
+        let capture = self.flags() & Self::CAPTURE;
+        let flags = match move_type {
+            MoveType::Normal => capture,
+            MoveType::DoublePawnPush => Self::DOUBLE_PAWN_PUSH,
+            MoveType::EnPassant => Self::EN_PASSANT,
+            MoveType::Castle(_) => Self::CASTLE,
+            MoveType::Promotion(promotion) => {
+                let piece = match promotion {
+                    Some(WhiteKnight | BlackKnight) => 0,
+                    Some(WhiteBishop | BlackBishop) => 1,
+                    Some(WhiteRook | BlackRook) => 2,
+                    _ => 3,
+                };
+                Self::PROMOTION | capture | piece
+            }
+        };
+        self.0 = (self.0 & 0xfff) | flags << 12;
     }
 
-    pub fn is_capture(&self) -> bool {
-        self.capture.is_some()
+    #[inline(always)]
+    fn flags(&self) -> u16 {
+        self.0 >> 12
     }
 
-    pub fn is_promotion(&self) -> bool {
-        matches!(self.move_type, MoveType::Promotion(_))
+    #[inline(always)]
+    pub fn from(&self) -> Squares {
+        Squares::from_repr((self.0 & 0x3f) as u8).unwrap()
     }
 
-    pub fn get_promotion(&self) -> Option<BoardPiece> {
-        match self.move_type {
-            MoveType::Promotion(promotion) => promotion,
+    #[inline(always)]
+    pub fn to(&self) -> Squares {
+        Squares::from_repr((self.0 >> 6 & 0x3f) as u8).unwrap()
+    }
+
+    pub fn move_type(&self) -> MoveType {
+        match self.flags() {
+            Self::DOUBLE_PAWN_PUSH => MoveType::DoublePawnPush,
+            Self::EN_PASSANT => MoveType::EnPassant,
+            Self::CASTLE => MoveType::Castle(match self.to() {
+                Squares::G1 => CastlingRights::WhiteKingSide,
+                Squares::C1 => CastlingRights::WhiteQueenSide,
+                Squares::G8 => CastlingRights::BlackKingSide,
+                _ => CastlingRights::BlackQueenSide,
+            }),
+            _ if self.is_promotion() => MoveType::Promotion(self.get_promotion()),
+            _ => MoveType::Normal,
+        }
+    }
+
+    pub fn gen_type(&self) -> GenType {
+        if self.is_capture() {
+            GenType::Capture
+        } else {
+            GenType::Quiet
+        }
+    }
+
+    /// the moving piece, `board` is the position before the move
+    #[inline(always)]
+    pub fn piece(&self, board: &Board) -> BoardPiece {
+        board.get_piece(self.from()).unwrap()
+    }
+
+    /// the captured piece, `board` is the position before the move
+    #[inline(always)]
+    pub fn capture(&self, board: &Board) -> Option<BoardPiece> {
+        match self.flags() {
+            Self::EN_PASSANT => Some(match self.piece(board).get_color() {
+                Color::White => BlackPawn,
+                Color::Black => WhitePawn,
+            }),
+            flags if flags & Self::CAPTURE != 0 => board.get_piece(self.to()),
             _ => None,
         }
     }
 
+    #[inline(always)]
+    pub fn is_capture(&self) -> bool {
+        self.flags() & Self::CAPTURE != 0
+    }
+
+    #[inline(always)]
+    pub fn is_promotion(&self) -> bool {
+        self.flags() & Self::PROMOTION != 0
+    }
+
+    /// the piece a pawn promotes to, its color follows from the rank the pawn lands on
+    pub fn get_promotion(&self) -> Option<BoardPiece> {
+        if !self.is_promotion() {
+            return None;
+        }
+        let white = self.to().rank() == 7;
+        Some(match (self.flags() & 3, white) {
+            (0, true) => WhiteKnight,
+            (1, true) => WhiteBishop,
+            (2, true) => WhiteRook,
+            (_, true) => WhiteQueen,
+            (0, false) => BlackKnight,
+            (1, false) => BlackBishop,
+            (2, false) => BlackRook,
+            (_, false) => BlackQueen,
+        })
+    }
+
+    #[inline(always)]
     pub fn is_en_passant(&self) -> bool {
-        matches!(self.move_type, MoveType::EnPassant)
+        self.flags() == Self::EN_PASSANT
     }
 
+    #[inline(always)]
     pub fn is_castle(&self) -> bool {
-        matches!(self.move_type, MoveType::Castle(_))
+        self.flags() == Self::CASTLE
+    }
+
+    #[inline(always)]
+    pub fn is_double_pawn_push(&self) -> bool {
+        self.flags() == Self::DOUBLE_PAWN_PUSH
+    }
+
+    /// the 16 bits of the move, used to store moves in the transposition table
+    pub fn pack(&self) -> u16 {
+        self.0
+    }
+
+    /// rebuilds a packed move, returns None if it can't be a move of the side to move on `board`
+    pub fn unpack(data: u16, board: &Board) -> Option<Move> {
+        let mov = Move(data);
+        let side = board.get_side_to_move();
+        let piece = board.get_piece(mov.from()).filter(|p| p.get_color() == side)?;
+        let capture = board.get_piece(mov.to());
+
+        let valid = match capture {
+            Some(capture) => capture.get_color() != side && mov.is_capture() && !mov.is_en_passant(),
+            None => !mov.is_capture() || mov.is_en_passant(),
+        };
+        let pawn = matches!(piece, WhitePawn | BlackPawn);
+        let pawn_move = mov.is_promotion() || mov.is_en_passant() || mov.is_double_pawn_push();
+        if mov == Move::NULL || !valid || (pawn_move && !pawn) {
+            return None;
+        }
+        Some(mov)
     }
 }
 
+/// Fixed capacity move list living on the stack, every move carries a score for move ordering.
+/// `N` only needs changing for lists known to stay small, like the moves of a single piece
 #[derive(Debug, Clone)]
-pub struct MoveList(pub Vec<Move>);
+pub struct MoveList<const N: usize = MAX_SIZE_MOVES_ARR> {
+    moves: [Move; N],
+    scores: [i32; N],
+    len: usize,
+}
 
-impl MoveList {
-    pub fn new() -> Self {
-        MoveList(Vec::with_capacity(MAX_SIZE_MOVES_ARR))
+impl<const N: usize> Default for MoveList<N> {
+    fn default() -> Self {
+        Self::new()
     }
+}
 
-    pub fn with_capacity(capacity: usize) -> Self {
-        MoveList(Vec::with_capacity(capacity))
+impl<const N: usize> MoveList<N> {
+    pub fn new() -> Self {
+        MoveList {
+            moves: [Move::NULL; N],
+            scores: [0; N],
+            len: 0,
+        }
     }
 
+    /// adds `m` with a score of 0
     pub fn push(&mut self, m: Move) {
-        self.0.push(m);
+        self.moves[self.len] = m;
+        self.scores[self.len] = 0;
+        self.len += 1;
     }
 
     pub fn len(&self) -> usize {
-        self.0.len()
+        self.len
     }
 
     pub fn is_empty(&self) -> bool {
-        self.0.is_empty()
+        self.len == 0
     }
 
     pub fn clear(&mut self) {
-        self.0.clear();
+        self.len = 0;
+    }
+
+    pub fn get(&self, index: usize) -> Move {
+        self.as_slice()[index]
+    }
+
+    pub fn score(&self, index: usize) -> i32 {
+        self.scores[..self.len][index]
     }
 
-    pub fn pop(&mut self) -> Option<Move> {
-        self.0.pop()
+    pub fn set_score(&mut self, index: usize, score: i32) {
+        self.scores[..self.len][index] = score;
     }
 
-    pub fn last(&self) -> Option<&Move> {
-        self.0.last()
+    pub fn swap(&mut self, a: usize, b: usize) {
+        self.moves[..self.len].swap(a, b);
+        self.scores[..self.len].swap(a, b);
     }
 
-    pub fn iter(&self) -> std::slice::Iter<Move> {
-        self.0.iter()
+    pub fn as_slice(&self) -> &[Move] {
+        &self.moves[..self.len]
     }
 
-    pub fn extend(&mut self, other: &mut MoveList) {
-        self.0.append(&mut other.0);
+    pub fn iter(&self) -> std::slice::Iter<'_, Move> {
+        self.as_slice().iter()
+    }
+
+    /// selection sort step, swaps the highest scored move at or after `index` into `index`
+    /// and returns it, the first one of them on ties
+    pub fn pick(&mut self, index: usize) -> Option<Move> {
+        if index >= self.len {
+            return None;
+        }
+        let mut best = index;
+        for i in index + 1..self.len {
+            if self.scores[i] > self.scores[best] {
+                best = i;
+            }
+        }
+        self.swap(index, best);
+        Some(self.moves[index])
     }
+}
+
+impl<'a, const N: usize> IntoIterator for &'a MoveList<N> {
+    type Item = &'a Move;
+    type IntoIter = std::slice::Iter<'a, Move>;
 
-    pub fn sort_by(&mut self, f: impl Fn(&Move, &Move) -> std::cmp::Ordering) {
-        self.0.sort_by(f);
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
     }
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Copy)]
 pub struct MoveHistory {
     pub mov: Move,
+    pub piece: BoardPiece,
+    pub capture: Option<BoardPiece>,
     pub castle_rights: Castle,
     pub en_passant: Option<Squares>,
     pub hash: u64,
-    pub half_move_clock: u8,
+    pub half_move_clock: u16,
 }
-#[derive(Debug, Clone, Eq, PartialEq)]
+/// moves played in a game, grows with the game so there is no length limit
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
 pub struct MoveArray {
-    pub moves: [Option<MoveHistory>; MAX_SIZE_MOVES_ARR],
-    pub count: usize,
+    moves: Vec<MoveHistory>,
 }
 
 impl MoveArray {
     pub fn new() -> Self {
-        MoveArray {
-            moves: [None; MAX_SIZE_MOVES_ARR],
-            count: 0,
-        }
+        MoveArray { moves: Vec::new() }
     }
 
     pub fn clear(&mut self) {
-        self.count = 0;
+        self.moves.clear();
     }
 
     pub fn push(&mut self, m: MoveHistory) {
-        self.moves[self.count] = Some(m);
-        self.count += 1;
+        self.moves.push(m);
     }
 
     pub fn len(&self) -> usize {
-        self.count
+        self.moves.len()
     }
 
     pub fn is_empty(&self) -> bool {
-        self.count == 0
+        self.moves.is_empty()
     }
 
     pub fn pop(&mut self) -> Option<MoveHistory> {
-        if self.count == 0 {
-            None
-        } else {
-            self.count -= 1;
-            self.moves[self.count]
-        }
+        self.moves.pop()
     }
 
     pub fn last(&self) -> Option<&MoveHistory> {
-        if self.count == 0 {
-            None
-        } else {
-            self.moves[self.count - 1].as_ref()
-        }
+        self.moves.last()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, MoveHistory> {
+        self.moves.iter()
     }
 
-    pub fn iter(&self) -> std::slice::Iter<Option<MoveHistory>> {
-        self.moves[..self.count].iter()
+    pub fn extend(&mut self, other: &MoveArray) {
+        self.moves.extend_from_slice(&other.moves);
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kelp::board::fen::{Fen, FenParse};
+    use crate::kelp::mov_gen::generator::MovGen;
+
+    #[test]
+    fn packed_move_test() {
+        assert_eq!(std::mem::size_of::<Move>(), 2);
+
+        let fens = [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+        ];
+        for fen in fens {
+            let board = Board::parse(Fen(fen.to_string())).unwrap();
+            let mut moves = MoveList::new();
+            MovGen::new().generate_moves(&board, &mut moves);
+
+            for &mov in moves.iter() {
+                let piece = mov.piece(&board);
+                let capture = mov.capture(&board);
+                let rebuilt = Move::new(
+                    mov.from(),
+                    mov.to(),
+                    piece,
+                    capture,
+                    mov.move_type(),
+                    mov.gen_type(),
+                );
+                assert_eq!(rebuilt, mov);
+                assert_eq!(Move::unpack(mov.pack(), &board), Some(mov));
+                assert_eq!(capture.is_some(), mov.is_capture());
+                assert_eq!(board.get_piece(mov.from()), Some(piece));
+            }
+        }
+
+        let board = Board::parse(Fen(fens[2].to_string())).unwrap();
+        let en_passant = Move::new(
+            Squares::E5,
+            Squares::F6,
+            WhitePawn,
+            Some(BlackPawn),
+            MoveType::EnPassant,
+            GenType::Capture,
+        );
+        assert_eq!(en_passant.capture(&board), Some(BlackPawn));
+        assert_eq!(en_passant.to_string(), "e5f6");
+
+        let promotion = Move::new_promotion(
+            Squares::B2,
+            Squares::A1,
+            BlackPawn,
+            Some(WhiteRook),
+            Some(BlackQueen),
+            GenType::Capture,
+        );
+        assert_eq!(promotion.get_promotion(), Some(BlackQueen));
+        assert!(promotion.is_capture());
+        assert_eq!(promotion.to_string(), "b2a1q");
+        // a knight can't double push
+        let double_push = Move::new(
+            Squares::C3,
+            Squares::C5,
+            WhiteKnight,
+            None,
+            MoveType::DoublePawnPush,
+            GenType::Quiet,
+        );
+        let board = Board::parse(Fen(fens[0].to_string())).unwrap();
+        assert_eq!(Move::unpack(double_push.pack(), &board), None);
+        assert_eq!(Move::unpack(Move::NULL.pack(), &board), None);
+    }
+
+    #[test]
+    fn move_list_pick_test() {
+        let mut list: MoveList = MoveList::new();
+        for (i, to) in [Squares::A3, Squares::B3, Squares::C3, Squares::D3]
+            .into_iter()
+            .enumerate()
+        {
+            let mov = Move::new(
+                Squares::A2,
+                to,
+                WhitePawn,
+                None,
+                MoveType::Normal,
+                GenType::Quiet,
+            );
+            list.push(mov);
+            list.set_score(i, [5, 20, 5, 10][i]);
+        }
 
-    pub fn extend(&mut self, other: &mut MoveArray) {
-        for m in other.iter() {
-            self.push(m.unwrap());
+        let mut picked = Vec::new();
+        let mut i = 0;
+        while let Some(mov) = list.pick(i) {
+            picked.push(mov.to());
+            i += 1;
         }
+        assert_eq!(picked, [Squares::B3, Squares::D3, Squares::C3, Squares::A3]);
+        assert_eq!(list.len(), 4);
     }
 }
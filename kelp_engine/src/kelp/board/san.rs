@@ -0,0 +1,309 @@
+use crate::kelp::board::board::Board;
+use crate::kelp::board::moves::{Move, MoveList};
+use crate::kelp::board::piece::{BoardPiece, Color};
+use crate::kelp::mov_gen::generator::MovGen;
+use crate::kelp::Squares;
+use std::fmt::Display;
+use std::str::FromStr;
+
+#[allow(clippy::enum_variant_names)]
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub enum SanError {
+    InvalidSan(String),
+    IllegalMove(String),
+    AmbiguousMove(String),
+}
+
+impl Display for SanError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SanError::InvalidSan(s) => write!(f, "InvalidSan: {}", s),
+            SanError::IllegalMove(s) => write!(f, "IllegalMove: {}", s),
+            SanError::AmbiguousMove(s) => write!(f, "AmbiguousMove: {}", s),
+        }
+    }
+}
+
+// letter of a piece in san, empty for pawns
+fn piece_letter(piece: BoardPiece) -> String {
+    match piece.to_string().to_uppercase().as_str() {
+        "P" => String::new(),
+        letter => letter.to_string(),
+    }
+}
+
+fn legal_moves(board: &Board, gen: &MovGen) -> MoveList {
+    let mut moves = MoveList::new();
+    gen.generate_legal_moves(board, &mut moves);
+    moves
+}
+
+/// standard algebraic notation of `mov`, which has to be legal in `board`
+pub fn to_san(board: &Board, mov: Move, gen: &MovGen) -> String {
+    let mut san = if mov.is_castle() {
+        if mov.to().file() == 6 {
+            String::from("O-O")
+        } else {
+            String::from("O-O-O")
+        }
+    } else {
+        let piece = mov.piece(board);
+        let mut san = piece_letter(piece);
+
+        if san.is_empty() {
+            if mov.is_capture() {
+                san.push_str(&mov.from().to_string()[..1]);
+            }
+        } else {
+            // other pieces of the same kind that can go to the same square
+            let others: Vec<Squares> = legal_moves(board, gen)
+                .iter()
+                .filter(|m| m.to() == mov.to() && m.from() != mov.from())
+                .filter(|m| m.piece(board) == piece)
+                .map(|m| m.from())
+                .collect();
+            let from = mov.from().to_string();
+            if !others.is_empty() {
+                if others.iter().all(|sq| sq.file() != mov.from().file()) {
+                    san.push_str(&from[..1]);
+                } else if others.iter().all(|sq| sq.rank() != mov.from().rank()) {
+                    san.push_str(&from[1..]);
+                } else {
+                    san.push_str(&from);
+                }
+            }
+        }
+
+        if mov.is_capture() {
+            san.push('x');
+        }
+        san.push_str(&mov.to().to_string());
+
+        if let Some(promotion) = mov.get_promotion() {
+            san.push('=');
+            san.push_str(&piece_letter(promotion));
+        }
+        san
+    };
+
+    let mut after = *board;
+    after.make_move(mov, false);
+    if after.is_check(gen) {
+        san.push(if legal_moves(&after, gen).is_empty() {
+            '#'
+        } else {
+            '+'
+        });
+    }
+    san
+}
+
+/// finds the legal move written as `san` in `board`. lenient about check marks, annotations,
+/// `0-0` for castling, missing `=` or `x` and over-specified origins, so `e2e4` and `e7e8q`
+/// are read too
+pub fn parse_san(board: &Board, san: &str, gen: &MovGen) -> Result<Move, SanError> {
+    let invalid = || SanError::InvalidSan(san.to_string());
+    let side = board.get_side_to_move();
+    let moves = legal_moves(board, gen);
+
+    let mut text: String = san
+        .trim()
+        .trim_end_matches(['+', '#', '!', '?'])
+        .replace("e.p.", "")
+        .chars()
+        .filter(|c| !matches!(c, 'x' | ':' | '=' | '(' | ')' | ' '))
+        .collect();
+
+    let castle = text.replace('0', "O").to_uppercase();
+    if castle == "O-O" || castle == "O-O-O" {
+        let file = if castle == "O-O" { 6 } else { 2 };
+        return moves
+            .iter()
+            .copied()
+            .find(|m| m.is_castle() && m.to().file() == file)
+            .ok_or_else(|| SanError::IllegalMove(san.to_string()));
+    }
+    text.retain(|c| c != '-');
+
+    let mut chars: Vec<char> = text.chars().collect();
+
+    // a piece letter after the target square is a promotion, lowercase is fine here
+    let mut promotion = None;
+    if chars.len() > 2 && chars[chars.len() - 1].is_ascii_alphabetic() {
+        let letter = chars.pop().unwrap().to_ascii_uppercase();
+        if !"NBRQ".contains(letter) {
+            return Err(invalid());
+        }
+        promotion = Some(letter);
+    }
+
+    // bishops need an uppercase letter, a lowercase b is a file
+    let mut piece = 'P';
+    if chars
+        .first()
+        .is_some_and(|&c| "NBRQK".contains(c) || "nrqk".contains(c))
+    {
+        piece = chars.remove(0).to_ascii_uppercase();
+    }
+
+    if chars.len() < 2 {
+        return Err(invalid());
+    }
+    let target: String = chars.split_off(chars.len() - 2).into_iter().collect();
+    let target = Squares::from_str(&target).map_err(|_| invalid())?;
+
+    // whatever is left narrows down the origin
+    let mut file = None;
+    let mut rank = None;
+    for c in chars {
+        match c {
+            'a'..='h' if file.is_none() => file = Some(c as u8 - b'a'),
+            '1'..='8' if rank.is_none() => rank = Some(c as u8 - b'1'),
+            _ => return Err(invalid()),
+        }
+    }
+
+    let letter = match side {
+        Color::White => piece.to_string(),
+        Color::Black => piece.to_ascii_lowercase().to_string(),
+    };
+    let piece = BoardPiece::from_str(&letter).map_err(|_| invalid())?;
+
+    let mut found = moves.iter().copied().filter(|m| {
+        m.to() == target
+            && !m.is_castle()
+            && m.piece(board) == piece
+            && file.is_none_or(|f| m.from().file() == f)
+            && rank.is_none_or(|r| m.from().rank() == r)
+            && m.get_promotion().map(piece_letter) == promotion.map(String::from)
+    });
+
+    match (found.next(), found.next()) {
+        (Some(mov), None) => Ok(mov),
+        (None, _) => Err(SanError::IllegalMove(san.to_string())),
+        (Some(_), Some(_)) => Err(SanError::AmbiguousMove(san.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kelp::board::fen::{Fen, FenParse};
+
+    fn board(fen: &str) -> Board {
+        Board::parse(Fen(fen.to_string())).unwrap()
+    }
+
+    #[test]
+    fn to_san_test() {
+        let gen = MovGen::new();
+        let san = |fen: &str, uci: &str| {
+            let board = board(fen);
+            let moves = legal_moves(&board, &gen);
+            let mov = moves.iter().find(|m| m.to_string() == uci).unwrap();
+            to_san(&board, *mov, &gen)
+        };
+
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert_eq!(san(start, "e2e4"), "e4");
+        assert_eq!(san(start, "g1f3"), "Nf3");
+
+        let kiwipete = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        assert_eq!(san(kiwipete, "e1g1"), "O-O");
+        assert_eq!(san(kiwipete, "e1c1"), "O-O-O");
+        assert_eq!(san(kiwipete, "d5e6"), "dxe6");
+        assert_eq!(san(kiwipete, "e2a6"), "Bxa6");
+        assert_eq!(san(kiwipete, "e5f7"), "Nxf7");
+        assert_eq!(san(kiwipete, "f3f6"), "Qxf6");
+
+        // knights on b1 and f3 can both reach d2, rooks on a1 and a5 both reach a3
+        let fen = "4k3/8/8/R7/8/8/8/RN2KN2 w - - 0 1";
+        assert_eq!(san(fen, "b1d2"), "Nbd2");
+        assert_eq!(san(fen, "a1a3"), "R1a3");
+        assert_eq!(san(fen, "a5a3"), "R5a3");
+        // queens on a1, a3 and c1 all reach b2
+        let fen = "4k3/8/8/8/8/Q7/8/Q1Q1K3 w - - 0 1";
+        assert_eq!(san(fen, "a1b2"), "Qa1b2");
+        assert_eq!(san(fen, "a3b2"), "Q3b2");
+        assert_eq!(san(fen, "c1b2"), "Qcb2");
+
+        let fen = "3r2k1/4P3/6K1/8/8/8/8/8 w - - 0 1";
+        assert_eq!(san(fen, "e7d8q"), "exd8=Q#");
+        assert_eq!(san(fen, "e7e8r"), "e8=R+");
+        assert_eq!(san(fen, "e7e8n"), "e8=N");
+
+        assert_eq!(san("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", "a1a8"), "Ra8#");
+        assert_eq!(
+            san(
+                "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+                "e5f6"
+            ),
+            "exf6"
+        );
+    }
+
+    #[test]
+    fn parse_san_test() {
+        let gen = MovGen::new();
+        let parse = |fen: &str, san: &str| parse_san(&board(fen), san, &gen).map(|m| m.to_string());
+
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert_eq!(parse(start, "e4").unwrap(), "e2e4");
+        assert_eq!(parse(start, "e2e4").unwrap(), "e2e4");
+        assert_eq!(parse(start, "Nf3!?").unwrap(), "g1f3");
+        assert_eq!(parse(start, "Ng1-f3").unwrap(), "g1f3");
+        assert!(matches!(parse(start, "e5"), Err(SanError::IllegalMove(_))));
+        assert!(matches!(parse(start, "Zf3"), Err(SanError::InvalidSan(_))));
+        assert!(matches!(parse(start, "x"), Err(SanError::InvalidSan(_))));
+
+        let black = "r1bqkb1r/pppp1ppp/2n2n2/4p3/4P3/2N2N2/PPPP1PPP/R1BQKB1R b KQkq - 4 4";
+        assert_eq!(parse(black, "Bc5").unwrap(), "f8c5");
+        assert_eq!(parse(black, "Nxe4").unwrap(), "f6e4");
+        assert_eq!(parse(black, "nxe4").unwrap(), "f6e4");
+
+        let kiwipete = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        assert_eq!(parse(kiwipete, "O-O").unwrap(), "e1g1");
+        assert_eq!(parse(kiwipete, "0-0-0").unwrap(), "e1c1");
+        assert_eq!(
+            parse(kiwipete, "exd6"),
+            Err(SanError::IllegalMove("exd6".into()))
+        );
+        assert_eq!(parse(kiwipete, "dxe6").unwrap(), "d5e6");
+        assert_eq!(parse(kiwipete, "de6").unwrap(), "d5e6");
+        assert_eq!(parse(kiwipete, "Qxf6").unwrap(), "f3f6");
+
+        let fen = "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3";
+        assert_eq!(parse(fen, "exf6 e.p.").unwrap(), "e5f6");
+
+        let fen = "4k3/8/8/R7/8/8/8/RN2KN2 w - - 0 1";
+        assert_eq!(
+            parse(fen, "Nbd7"),
+            Err(SanError::IllegalMove("Nbd7".into()))
+        );
+        assert_eq!(parse(fen, "Nbd2").unwrap(), "b1d2");
+        assert_eq!(parse(fen, "Nfd2").unwrap(), "f1d2");
+        assert_eq!(
+            parse(fen, "Nd2"),
+            Err(SanError::AmbiguousMove("Nd2".into()))
+        );
+        assert_eq!(parse(fen, "R1a3").unwrap(), "a1a3");
+
+        let fen = "3r2k1/4P3/6K1/8/8/8/8/8 w - - 0 1";
+        assert_eq!(parse(fen, "e8=Q+").unwrap(), "e7e8q");
+        assert_eq!(parse(fen, "exd8=N").unwrap(), "e7d8n");
+        assert_eq!(parse(fen, "e8q").unwrap(), "e7e8q");
+        assert_eq!(parse(fen, "e7e8r").unwrap(), "e7e8r");
+        assert_eq!(parse(fen, "e8(B)").unwrap(), "e7e8b");
+        assert!(parse(fen, "e8").is_err());
+        assert!(parse(fen, "e8=K").is_err());
+
+        // every san written for a move reads back as that move
+        for fen in [start, black, kiwipete] {
+            let board = board(fen);
+            for mov in legal_moves(&board, &gen).iter() {
+                let san = to_san(&board, *mov, &gen);
+                assert_eq!(parse_san(&board, &san, &gen), Ok(*mov), "{}", san);
+            }
+        }
+    }
+}
@@ -2,9 +2,11 @@ use super::piece::{BoardPiece, Color};
 use crate::kelp::board::board::Board;
 use crate::kelp::Squares;
 use crate::kelp::ZobristKey;
-use rand_chacha::rand_core::{RngCore, SeedableRng};
 use strum::IntoEnumIterator;
 
+const SEED: u64 = 15 * 8 * 1947;
+
+/// Zobrist keys, generated at compile time and shared by every board
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct Zobrist {
     pub piece_keys: [[ZobristKey; 64]; 12],
@@ -13,30 +15,46 @@ pub struct Zobrist {
     pub en_passant_keys: [ZobristKey; 64],
 }
 
+static ZOBRIST: Zobrist = Zobrist::new();
+
+// splitmix64, advances `state` and returns the next key
+const fn next_key(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e3779b97f4a7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
 impl Zobrist {
-    pub fn new() -> Self {
-        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(15 * 8 * 1947);
+    pub const fn new() -> Self {
+        let mut state = SEED;
 
         let mut piece_keys = [[0; 64]; 12];
-
-        for piece in BoardPiece::iter() {
-            for sq in 0..64 {
-                piece_keys[piece as usize][sq] = rng.next_u64();
+        let mut piece = 0;
+        while piece < 12 {
+            let mut sq = 0;
+            while sq < 64 {
+                piece_keys[piece][sq] = next_key(&mut state);
+                sq += 1;
             }
+            piece += 1;
         }
 
-        let side_key = rng.next_u64();
+        let side_key = next_key(&mut state);
 
         let mut castle_keys = [0; 16];
-
-        for i in 0..16 {
-            castle_keys[i] = rng.next_u64();
+        let mut i = 0;
+        while i < 16 {
+            castle_keys[i] = next_key(&mut state);
+            i += 1;
         }
 
         let mut en_passant_keys = [0; 64];
-
-        for i in 0..64 {
-            en_passant_keys[i] = rng.next_u64();
+        let mut i = 0;
+        while i < 64 {
+            en_passant_keys[i] = next_key(&mut state);
+            i += 1;
         }
 
         Self {
@@ -47,18 +65,28 @@ impl Zobrist {
         }
     }
 
+    /// keys shared by every board
+    #[inline(always)]
+    pub fn global() -> &'static Zobrist {
+        &ZOBRIST
+    }
+
+    #[inline(always)]
     pub fn get_piece_key(&self, piece: BoardPiece, sq: Squares) -> ZobristKey {
         self.piece_keys[piece as usize][sq as usize]
     }
 
+    #[inline(always)]
     pub fn get_side_key(&self) -> ZobristKey {
         self.side_key
     }
 
+    #[inline(always)]
     pub fn get_castle_key(&self, castle: u8) -> ZobristKey {
         self.castle_keys[castle as usize]
     }
 
+    #[inline(always)]
     pub fn get_en_passant_key(&self, sq: Squares) -> ZobristKey {
         self.en_passant_keys[sq as usize]
     }
@@ -78,10 +106,40 @@ impl Zobrist {
 
         key ^= self.get_castle_key(board.info.castle.0);
 
-        if let Some(sq) = board.info.en_passant {
-            key ^= self.get_en_passant_key(sq);
-        }
+        key ^= board.en_passant_key();
 
         key
     }
 }
+
+impl Default for Zobrist {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn unique_keys_test() {
+        let keys = Zobrist::global();
+        assert_eq!(*keys, Zobrist::new());
+
+        let mut seen = HashSet::new();
+        let all = keys
+            .piece_keys
+            .iter()
+            .flatten()
+            .chain(std::iter::once(&keys.side_key))
+            .chain(keys.castle_keys.iter())
+            .chain(keys.en_passant_keys.iter());
+        for &key in all {
+            assert_ne!(key, 0);
+            assert!(seen.insert(key), "duplicate key {:#x}", key);
+        }
+        assert_eq!(seen.len(), 12 * 64 + 1 + 16 + 64);
+    }
+}
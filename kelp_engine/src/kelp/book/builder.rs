@@ -0,0 +1,233 @@
+use super::polyglot::{encode_move, polyglot_key, Book, BookEntry};
+use crate::kelp::board::piece::Color;
+use crate::kelp::pgn::PgnGame;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, Default)]
+struct MoveStats {
+    games: u32,
+    // 2 for a win, 1 for a draw from the side that played the move
+    score: u32,
+}
+
+/// Collects the moves of many games into a Polyglot book
+#[derive(Debug, Clone)]
+pub struct BookBuilder {
+    /// only the first `max_ply` moves of every game are added
+    pub max_ply: usize,
+    /// moves played in fewer games are left out of the book
+    pub min_count: u32,
+    /// moves of players rated lower, or without a rating, are skipped
+    pub min_rating: u32,
+    moves: HashMap<(u64, u16), MoveStats>,
+    games: usize,
+}
+
+impl Default for BookBuilder {
+    fn default() -> Self {
+        Self::new(40, 1, 0)
+    }
+}
+
+impl BookBuilder {
+    pub fn new(max_ply: usize, min_count: u32, min_rating: u32) -> Self {
+        BookBuilder {
+            max_ply,
+            min_count,
+            min_rating,
+            moves: HashMap::new(),
+            games: 0,
+        }
+    }
+
+    /// number of games added so far
+    pub fn games(&self) -> usize {
+        self.games
+    }
+
+    /// adds the moves of `game` passing the filters, returns how many were added. games without
+    /// a result tell nothing about how good their moves are and are skipped
+    pub fn add_game(&mut self, game: &PgnGame) -> usize {
+        let winner = match game.result.as_str() {
+            "1-0" => Some(Color::White),
+            "0-1" => Some(Color::Black),
+            "1/2-1/2" => None,
+            _ => return 0,
+        };
+        let rating = |tag| {
+            game.tag(tag)
+                .and_then(|r| r.parse::<u32>().ok())
+                .unwrap_or(0)
+        };
+        let white_rated = rating("WhiteElo") >= self.min_rating;
+        let black_rated = rating("BlackElo") >= self.min_rating;
+
+        let mut board = game.start;
+        let mut added = 0;
+        for pgn_move in game.moves.iter().take(self.max_ply) {
+            let side = board.get_side_to_move();
+            let rated = match side {
+                Color::White => white_rated,
+                Color::Black => black_rated,
+            };
+
+            if rated {
+                let stats = self
+                    .moves
+                    .entry((polyglot_key(&board), encode_move(pgn_move.mov)))
+                    .or_default();
+                stats.games += 1;
+                stats.score += match winner {
+                    Some(color) if color == side => 2,
+                    Some(_) => 0,
+                    None => 1,
+                };
+                added += 1;
+            }
+            board.make_move(pgn_move.mov, false);
+        }
+
+        self.games += 1;
+        added
+    }
+
+    /// the book of every move played in at least `min_count` games that scored something,
+    /// weighted by 2 per win and 1 per draw. the moves of a position are listed heaviest first
+    pub fn build(&self) -> Book {
+        let mut moves: Vec<(u64, u16, u32)> = self
+            .moves
+            .iter()
+            .filter(|(_, stats)| stats.games >= self.min_count && stats.score > 0)
+            .map(|(&(key, mov), stats)| (key, mov, stats.score))
+            .collect();
+        moves.sort_by_key(|&(key, mov, score)| (key, Reverse(score), mov));
+
+        // weights have to fit 16 bits, the moves of a position are scaled together
+        let mut entries = Vec::with_capacity(moves.len());
+        for position in moves.chunk_by(|a, b| a.0 == b.0) {
+            let max = position[0].2 as u64;
+            for &(key, mov, score) in position {
+                let weight = if max > u16::MAX as u64 {
+                    (score as u64 * u16::MAX as u64 / max).max(1)
+                } else {
+                    score as u64
+                };
+                entries.push(BookEntry {
+                    key,
+                    mov,
+                    weight: weight as u16,
+                    learn: 0,
+                });
+            }
+        }
+
+        Book::from_entries(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kelp::game::Game;
+    use crate::kelp::mov_gen::generator::MovGen;
+    use crate::kelp::pgn::PgnReader;
+
+    const PGN: &str = r#"[White "a"]
+[Black "b"]
+[WhiteElo "2400"]
+[BlackElo "2000"]
+[Result "1-0"]
+
+1. e4 e5 2. Nf3 Nc6 1-0
+
+[White "c"]
+[Black "d"]
+[WhiteElo "2500"]
+[BlackElo "2600"]
+[Result "1/2-1/2"]
+
+1. e4 c5 2. Nf3 1/2-1/2
+
+[White "e"]
+[Black "f"]
+[Result "0-1"]
+
+1. d4 d5 0-1
+
+[White "g"]
+[Black "h"]
+[Result "*"]
+
+1. c4 *
+"#;
+
+    fn build(builder: &mut BookBuilder) -> Book {
+        for game in PgnReader::new(PGN.as_bytes()) {
+            builder.add_game(&game.unwrap());
+        }
+        builder.build()
+    }
+
+    // the book moves after `moves` from the start position
+    fn book_moves(book: &Book, moves: &str) -> Vec<String> {
+        let mut game = Game::default();
+        for text in moves.split_whitespace() {
+            assert!(game.make_move(game.find_move(text).unwrap()));
+        }
+        let moves = book.moves(game.board(), &MovGen::new());
+        moves.iter().map(|(m, w)| format!("{} {}", m, w)).collect()
+    }
+
+    #[test]
+    fn book_builder_test() {
+        let mut builder = BookBuilder::default();
+        let book = build(&mut builder);
+        assert_eq!(builder.games(), 3);
+
+        // a win and a draw, d4 only lost and c4 is from a game without a result
+        assert_eq!(book_moves(&book, ""), ["e2e4 3"]);
+        assert_eq!(book_moves(&book, "e4"), ["c7c5 1"]);
+        assert_eq!(book_moves(&book, "e4 e5"), ["g1f3 2"]);
+        assert_eq!(book_moves(&book, "d4"), ["d7d5 2"]);
+
+        let book = build(&mut BookBuilder::new(1, 1, 0));
+        assert!(book_moves(&book, "e4").is_empty());
+
+        let book = build(&mut BookBuilder::new(40, 2, 0));
+        assert_eq!(book_moves(&book, ""), ["e2e4 3"]);
+        assert_eq!(book.len(), 1);
+
+        // only the game between the 2500 and the 2600 is left
+        let book = build(&mut BookBuilder::new(40, 1, 2450));
+        assert_eq!(book_moves(&book, ""), ["e2e4 1"]);
+        assert_eq!(book_moves(&book, "e4 c5"), ["g1f3 1"]);
+        assert_eq!(book.len(), 3);
+    }
+
+    #[test]
+    fn book_builder_bytes_test() {
+        let bytes = build(&mut BookBuilder::default()).to_bytes();
+        let record = |key: u64| {
+            bytes
+                .chunks(BookEntry::SIZE)
+                .find(|record| record[..8] == key.to_be_bytes())
+                .map(|record| record.to_vec())
+        };
+
+        // published keys of the start position and of 1. e4, moves are encoded to | from << 6
+        // followed by the weight and an empty learn field
+        assert_eq!(
+            record(0x463b96181691fc9c).unwrap()[8..],
+            [0x03, 0x1c, 0x00, 0x03, 0, 0, 0, 0]
+        );
+        assert_eq!(
+            record(0x823c9b50fd114196).unwrap()[8..],
+            [0x0c, 0xa2, 0x00, 0x01, 0, 0, 0, 0]
+        );
+        assert_eq!(
+            Book::from_bytes(&bytes).len(),
+            bytes.len() / BookEntry::SIZE
+        );
+    }
+}
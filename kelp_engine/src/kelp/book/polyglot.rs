@@ -0,0 +1,289 @@
+use super::random::{CASTLE, EN_PASSANT, RANDOM64, TURN};
+use crate::kelp::board::board::Board;
+use crate::kelp::board::moves::{CastlingRights, Move, MoveList};
+use crate::kelp::board::piece::{BoardPiece, Color};
+use crate::kelp::mov_gen::generator::MovGen;
+use crate::kelp::Squares;
+use rand::Rng;
+use std::io;
+use std::path::Path;
+use strum::IntoEnumIterator;
+
+/// The Polyglot hash of a position, unlike `Board::hash` it is the same in every program
+pub fn polyglot_key(board: &Board) -> u64 {
+    let mut key = 0;
+
+    for piece in BoardPiece::iter() {
+        // black pawn, white pawn, black knight, ...
+        let kind = 2 * (piece as usize % 6) + (piece.get_color() == Color::White) as usize;
+        for sq in board.get_bitboard(piece) {
+            key ^= RANDOM64[64 * kind + sq as usize];
+        }
+    }
+
+    let rights = [
+        CastlingRights::WhiteKingSide,
+        CastlingRights::WhiteQueenSide,
+        CastlingRights::BlackKingSide,
+        CastlingRights::BlackQueenSide,
+    ];
+    for (i, right) in rights.into_iter().enumerate() {
+        if board.info.castle.can_castle(right) {
+            key ^= RANDOM64[CASTLE + i];
+        }
+    }
+
+    // like kelp's own hash the en passant file only counts if a pawn can capture onto it
+    if let Some(square) = board.get_en_passant() {
+        if board.en_passant_key() != 0 {
+            key ^= RANDOM64[EN_PASSANT + square.file() as usize];
+        }
+    }
+
+    if board.get_side_to_move() == Color::White {
+        key ^= RANDOM64[TURN];
+    }
+    key
+}
+
+/// One 16 byte record of a `.bin` book, every number is big endian
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct BookEntry {
+    pub key: u64,
+    /// the move as written by `encode_move`
+    pub mov: u16,
+    /// how often the move should be played relative to the other moves of the position
+    pub weight: u16,
+    pub learn: u32,
+}
+
+impl BookEntry {
+    pub const SIZE: usize = 16;
+
+    pub fn from_bytes(bytes: [u8; Self::SIZE]) -> Self {
+        BookEntry {
+            key: u64::from_be_bytes(bytes[0..8].try_into().unwrap()),
+            mov: u16::from_be_bytes(bytes[8..10].try_into().unwrap()),
+            weight: u16::from_be_bytes(bytes[10..12].try_into().unwrap()),
+            learn: u32::from_be_bytes(bytes[12..16].try_into().unwrap()),
+        }
+    }
+
+    pub fn to_bytes(self) -> [u8; Self::SIZE] {
+        let mut bytes = [0; Self::SIZE];
+        bytes[0..8].copy_from_slice(&self.key.to_be_bytes());
+        bytes[8..10].copy_from_slice(&self.mov.to_be_bytes());
+        bytes[10..12].copy_from_slice(&self.weight.to_be_bytes());
+        bytes[12..16].copy_from_slice(&self.learn.to_be_bytes());
+        bytes
+    }
+}
+
+/// `mov` as stored in a book, castling is written as the king taking its own rook
+pub fn encode_move(mov: Move) -> u16 {
+    let from = mov.from();
+    let mut to = mov.to();
+    if mov.is_castle() {
+        let file = if to.file() == 6 { 7 } else { 0 };
+        to = Squares::from_rank_file(to.rank(), file);
+    }
+    // knight, bishop, rook, queen are 1 to 4
+    let promotion = mov.get_promotion().map_or(0, |p| p as u16 % 6);
+
+    to.file() as u16
+        | (to.rank() as u16) << 3
+        | (from.file() as u16) << 6
+        | (from.rank() as u16) << 9
+        | promotion << 12
+}
+
+/// the legal move a book move stands for, `None` if there is none
+pub fn decode_move(board: &Board, mov: u16, gen: &MovGen) -> Option<Move> {
+    let mut moves = MoveList::new();
+    gen.generate_legal_moves(board, &mut moves);
+    moves.iter().copied().find(|m| encode_move(*m) == mov)
+}
+
+/// A Polyglot opening book, entries are kept sorted by key
+#[derive(Debug, Clone, Default)]
+pub struct Book {
+    entries: Vec<BookEntry>,
+}
+
+impl Book {
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Book> {
+        Ok(Self::from_bytes(&std::fs::read(path)?))
+    }
+
+    /// reads the records of a `.bin` file, a truncated last record is ignored
+    pub fn from_bytes(bytes: &[u8]) -> Book {
+        let entries = bytes
+            .chunks_exact(BookEntry::SIZE)
+            .map(|chunk| BookEntry::from_bytes(chunk.try_into().unwrap()))
+            .collect();
+        Self::from_entries(entries)
+    }
+
+    /// entries of one key keep their order, which is the order moves are listed in
+    pub fn from_entries(mut entries: Vec<BookEntry>) -> Book {
+        entries.sort_by_key(|e| e.key);
+        Book { entries }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.entries.iter().flat_map(|e| e.to_bytes()).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn entries(&self, key: u64) -> &[BookEntry] {
+        let start = self.entries.partition_point(|e| e.key < key);
+        let end = self.entries.partition_point(|e| e.key <= key);
+        &self.entries[start..end]
+    }
+
+    /// the legal book moves of the position with their weights, entries that don't decode to a
+    /// legal move are skipped
+    pub fn moves(&self, board: &Board, gen: &MovGen) -> Vec<(Move, u16)> {
+        self.entries(polyglot_key(board))
+            .iter()
+            .filter_map(|e| decode_move(board, e.mov, gen).map(|m| (m, e.weight)))
+            .collect()
+    }
+
+    /// the move with the highest weight, the first one listed on ties
+    pub fn best_move(&self, board: &Board, gen: &MovGen) -> Option<Move> {
+        let mut best: Option<(Move, u16)> = None;
+        for (mov, weight) in self.moves(board, gen) {
+            if weight > 0 && best.is_none_or(|(_, w)| weight > w) {
+                best = Some((mov, weight));
+            }
+        }
+        best.map(|(mov, _)| mov)
+    }
+
+    /// a random move, each one as likely as its share of the total weight. moves with weight 0
+    /// are never played
+    pub fn pick_move<R: Rng>(&self, board: &Board, gen: &MovGen, rng: &mut R) -> Option<Move> {
+        let moves = self.moves(board, gen);
+        let total: u32 = moves.iter().map(|(_, w)| *w as u32).sum();
+        if total == 0 {
+            return None;
+        }
+
+        let mut pick = rng.gen_range(0..total);
+        for (mov, weight) in moves {
+            if pick < weight as u32 {
+                return Some(mov);
+            }
+            pick -= weight as u32;
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kelp::game::Game;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    // positions and keys from the Polyglot book format specification
+    const REFERENCE: [(&str, u64); 9] = [
+        ("", 0x463b96181691fc9c),
+        ("e2e4", 0x823c9b50fd114196),
+        ("e2e4 d7d5", 0x0756b94461c50fb0),
+        ("e2e4 d7d5 e4e5", 0x662fafb965db29d4),
+        ("e2e4 d7d5 e4e5 f7f5", 0x22a48b5a8e47ff78),
+        ("e2e4 d7d5 e4e5 f7f5 e1e2", 0x652a607ca3f242c1),
+        ("e2e4 d7d5 e4e5 f7f5 e1e2 e8f7", 0x00fdd303c946bdd9),
+        ("a2a4 b7b5 h2h4 b5b4 c2c4", 0x3c8123ea7b067637),
+        ("a2a4 b7b5 h2h4 b5b4 c2c4 b4c3 a1a3", 0x5c3f9b829b279560),
+    ];
+
+    fn play(moves: &str) -> Game {
+        let mut game = Game::default();
+        for text in moves.split_whitespace() {
+            let mov = game.find_move(text).unwrap();
+            assert!(game.make_move(mov), "{}", text);
+        }
+        game
+    }
+
+    #[test]
+    fn polyglot_key_test() {
+        for (moves, key) in REFERENCE {
+            assert_eq!(polyglot_key(play(moves).board()), key, "{}", moves);
+        }
+    }
+
+    #[test]
+    fn book_test() {
+        let gen = MovGen::new();
+        let entry = |game: &Game, mov: &str, weight| BookEntry {
+            key: polyglot_key(game.board()),
+            mov: encode_move(game.find_move(mov).unwrap()),
+            weight,
+            learn: 0,
+        };
+
+        let start = Game::default();
+        let castle = Game::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        let promote = Game::from_fen("8/1P5k/8/8/8/8/8/K7 w - - 0 1").unwrap();
+        let book = Book::from_entries(vec![
+            entry(&start, "e2e4", 10),
+            entry(&castle, "e1c1", 1),
+            entry(&start, "d2d4", 30),
+            entry(&promote, "b7b8n", 1),
+            entry(&start, "h2h4", 0),
+        ]);
+        assert_eq!(book.len(), 5);
+
+        // castling is stored as the king taking the rook
+        assert_eq!(book.entries(polyglot_key(castle.board()))[0].mov, 0x0100);
+        assert_eq!(
+            book.best_move(castle.board(), &gen).unwrap().to_string(),
+            "e1c1"
+        );
+        assert_eq!(
+            book.best_move(promote.board(), &gen).unwrap().to_string(),
+            "b7b8n"
+        );
+
+        let book = Book::from_bytes(&book.to_bytes());
+        let moves = book.moves(start.board(), &gen);
+        let moves: Vec<String> = moves.iter().map(|(m, w)| format!("{} {}", m, w)).collect();
+        assert_eq!(moves, ["e2e4 10", "d2d4 30", "h2h4 0"]);
+        assert_eq!(
+            book.best_move(start.board(), &gen).unwrap().to_string(),
+            "d2d4"
+        );
+
+        let mut rng = ChaCha8Rng::seed_from_u64(1947);
+        let mut picked = [0; 2];
+        for _ in 0..400 {
+            match book
+                .pick_move(start.board(), &gen, &mut rng)
+                .unwrap()
+                .to_string()
+                .as_str()
+            {
+                "e2e4" => picked[0] += 1,
+                "d2d4" => picked[1] += 1,
+                mov => panic!("{} has no weight", mov),
+            }
+        }
+        assert!(picked[0] > 50 && picked[1] > 2 * picked[0], "{:?}", picked);
+
+        let empty = Game::from_fen("7k/8/6K1/8/8/8/8/8 w - - 0 1").unwrap();
+        assert!(book.moves(empty.board(), &gen).is_empty());
+        assert_eq!(book.pick_move(empty.board(), &gen, &mut rng), None);
+    }
+}
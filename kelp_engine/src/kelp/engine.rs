@@ -0,0 +1,258 @@
+use super::board::board::Board;
+use super::board::fen::{Fen, FenParse, FenParseError};
+use super::board::moves::{Move, MoveList};
+use super::book::polyglot::Book;
+use super::kelp::Kelp;
+use super::observer::{NoObserver, SearchObserver};
+use super::search::negamax::Negamax;
+use super::search::score::Score;
+use super::syzygy::Tablebase;
+use super::SearchMoveResult;
+use std::fmt::Display;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub enum EngineError {
+    InvalidFen(FenParseError),
+    IllegalMove(String),
+}
+
+impl Display for EngineError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EngineError::InvalidFen(e) => write!(f, "{}", e),
+            EngineError::IllegalMove(s) => write!(f, "IllegalMove: {}", s),
+        }
+    }
+}
+
+impl From<FenParseError> for EngineError {
+    fn from(value: FenParseError) -> Self {
+        EngineError::InvalidFen(value)
+    }
+}
+
+/// Limits for `Engine::search`, the search ends at whichever is reached first.
+/// Without any limit the search runs until its stop flag is raised.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct SearchLimits {
+    pub depth: Option<usize>,
+    pub movetime: Option<Duration>,
+    /// nodes searched by the main thread, checked every 1024 nodes
+    pub nodes: Option<u64>,
+}
+
+impl SearchLimits {
+    pub fn depth(depth: usize) -> Self {
+        SearchLimits {
+            depth: Some(depth),
+            ..Default::default()
+        }
+    }
+
+    pub fn movetime(movetime: Duration) -> Self {
+        SearchLimits {
+            movetime: Some(movetime),
+            ..Default::default()
+        }
+    }
+
+    pub fn nodes(nodes: u64) -> Self {
+        SearchLimits {
+            nodes: Some(nodes),
+            ..Default::default()
+        }
+    }
+}
+
+/// Owned engine for embedding kelp as a library, unlike the uci front-end it can be stored,
+/// returned and sent across threads. Attack tables are shared by every engine in the process.
+pub struct Engine {
+    kelp: Kelp,
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Engine {
+    /// new engine set to the starting position
+    pub fn new() -> Self {
+        let mut kelp = Kelp::new();
+        kelp.search.add_draw(kelp.board.hash);
+        Engine { kelp }
+    }
+
+    /// sets the position, the game history used for repetition detection starts over
+    pub fn set_position(&mut self, fen: &str) -> Result<(), EngineError> {
+        let board = Board::parse(Fen(fen.to_string()))?;
+        self.set_board(board);
+        Ok(())
+    }
+
+    pub fn set_startpos(&mut self) {
+        self.set_board(Board::default());
+    }
+
+    /// sets the position, the game history used for repetition detection starts over
+    pub fn set_board(&mut self, board: Board) {
+        self.kelp.board = board;
+        self.kelp.move_history.clear();
+        self.kelp.search.clear_draw();
+        self.kelp.search.add_draw(self.kelp.board.hash);
+    }
+
+    /// clears the transposition table, call between unrelated games
+    pub fn new_game(&mut self) {
+        self.kelp.search.reset_tables();
+        self.kelp.search.add_draw(self.kelp.board.hash);
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.kelp.board
+    }
+
+    pub fn fen(&self) -> String {
+        self.kelp.board.to_fen()
+    }
+
+    pub fn legal_moves(&mut self) -> Vec<Move> {
+        let kelp = &mut self.kelp;
+        let mut moves = MoveList::new();
+        kelp.mov_gen.generate_legal_moves(&kelp.board, &mut moves);
+        moves.iter().copied().collect()
+    }
+
+    /// plays a move given in uci notation or san, e.g. `e2e4`, `e7e8q` or `Nf3`
+    pub fn make_move(&mut self, mov: &str) -> Result<Move, EngineError> {
+        let found = self
+            .kelp
+            .parse_move(mov)
+            .ok_or_else(|| EngineError::IllegalMove(mov.to_string()))?;
+
+        self.kelp.make_move(found);
+        self.kelp.search.add_draw(self.kelp.board.hash);
+        Ok(found)
+    }
+
+    /// number of search threads, clamped to 1..=256
+    pub fn set_threads(&mut self, threads: usize) {
+        self.kelp.threads = threads.clamp(1, Kelp::MAX_THREADS);
+    }
+
+    /// the book `book_move` picks from, `None` turns the book off
+    pub fn set_book(&mut self, book: Option<Book>, random: bool) {
+        self.kelp.own_book = book.is_some();
+        self.kelp.book = book;
+        self.kelp.book_random = random;
+    }
+
+    /// the tablebase probed during search, `None` turns probing off
+    pub fn set_tablebase(&mut self, tablebase: Option<Arc<dyn Tablebase>>) {
+        self.kelp.search.tablebase = tablebase;
+    }
+
+    /// a move from the book set with `set_book`, search if there is none
+    pub fn book_move(&self) -> Option<Move> {
+        self.kelp.book_move()
+    }
+
+    pub fn search(&mut self, limits: SearchLimits) -> SearchMoveResult {
+        self.search_observed(limits, &mut NoObserver)
+    }
+
+    pub fn search_observed(
+        &mut self,
+        limits: SearchLimits,
+        observer: &mut dyn SearchObserver,
+    ) -> SearchMoveResult {
+        let depth = limits.depth.unwrap_or(Negamax::MAX_DEPTH);
+        self.kelp.search.deadline = limits.movetime.map(|time| Instant::now() + time);
+        self.kelp.search.node_limit = limits.nodes;
+        self.kelp.search_move_observed(depth, observer)
+    }
+
+    /// raising this flag stops a running search, it is cleared once the search returns
+    pub fn stop_flag(&self) -> Arc<AtomicBool> {
+        self.kelp.search.stop_flag()
+    }
+
+    /// static evaluation from the side to move's point of view
+    pub fn evaluate(&self) -> Score {
+        Score(
+            self.kelp
+                .search
+                .evaluate(&self.kelp.board, &self.kelp.mov_gen),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kelp::book::polyglot::{encode_move, polyglot_key, BookEntry};
+
+    #[test]
+    fn engine_test() {
+        fn assert_send<T: Send + 'static>() {}
+        assert_send::<Engine>();
+
+        let mut engine = Engine::new();
+        assert_eq!(engine.legal_moves().len(), 20);
+        assert!(engine.make_move("e2e5").is_err());
+        engine.make_move("e2e4").unwrap();
+        engine.make_move("e5").unwrap();
+        assert_eq!(
+            engine.fen(),
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2"
+        );
+
+        assert!(engine.set_position("not a fen").is_err());
+        // black is missing the queen
+        engine
+            .set_position("rnb1kbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1")
+            .unwrap();
+        assert!(engine.evaluate().0 > 0);
+        engine
+            .set_position("rnb1kbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b KQkq - 0 1")
+            .unwrap();
+        assert!(engine.evaluate().0 < 0);
+
+        engine
+            .set_position("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1")
+            .unwrap();
+
+        // engines can be moved to and searched on another thread
+        let handle = std::thread::spawn(move || {
+            let res = engine.search(SearchLimits::depth(3));
+            (engine, res)
+        });
+        let (mut engine, res) = handle.join().unwrap();
+        assert_eq!(res.best_move.unwrap().to_string(), "a1a8");
+
+        engine.set_startpos();
+        let res = engine.search(SearchLimits::movetime(Duration::from_millis(50)));
+        assert!(res.best_move.is_some());
+
+        let res = engine.search(SearchLimits::nodes(20_000));
+        assert!(res.best_move.is_some());
+        assert!(res.nodes < 20_000 + 1024);
+
+        let e4 = engine.kelp.parse_move("e2e4").unwrap();
+        let book = Book::from_entries(vec![BookEntry {
+            key: polyglot_key(engine.board()),
+            mov: encode_move(e4),
+            weight: 1,
+            learn: 0,
+        }]);
+        assert_eq!(engine.book_move(), None);
+        engine.set_book(Some(book), false);
+        assert_eq!(engine.book_move(), Some(e4));
+        engine.make_move("e4").unwrap();
+        assert_eq!(engine.book_move(), None);
+    }
+}
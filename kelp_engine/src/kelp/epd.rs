@@ -0,0 +1,479 @@
+use super::board::board::Board;
+use super::board::fen::{Fen, FenParse, FenParseError};
+use super::board::moves::{Move, MoveList};
+use super::board::san::parse_san;
+use super::engine::{Engine, SearchLimits};
+use super::mov_gen::generator::MovGen;
+use std::fmt::Display;
+use std::io::BufRead;
+use std::time::Duration;
+
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub enum EpdError {
+    Epd(String),
+    Fen(FenParseError),
+    Move(String),
+}
+
+impl Display for EpdError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EpdError::Epd(s) => write!(f, "InvalidEpd: {}", s),
+            EpdError::Fen(e) => write!(f, "{}", e),
+            EpdError::Move(s) => write!(f, "InvalidMove: {}", s),
+        }
+    }
+}
+
+impl From<FenParseError> for EpdError {
+    fn from(value: FenParseError) -> Self {
+        EpdError::Fen(value)
+    }
+}
+
+// opcodes whose operands are moves in san
+const MOVE_OPCODES: [&str; 3] = ["bm", "am", "pm"];
+
+/// A position with its opcodes, e.g.
+/// `2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id "WAC.001";`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Epd {
+    pub board: Board,
+    ops: Vec<(String, Vec<String>)>,
+}
+
+impl Epd {
+    /// reads a line of EPD, the clocks come from the `hmvc` and `fmvn` opcodes if present.
+    /// the moves of `bm`, `am` and `pm` have to be legal
+    pub fn parse(line: &str) -> Result<Epd, EpdError> {
+        let invalid = || EpdError::Epd(line.to_string());
+
+        let mut fields = line.trim().splitn(5, char::is_whitespace);
+        let position: Vec<&str> = fields.by_ref().take(4).collect();
+        if position.len() < 4 {
+            return Err(invalid());
+        }
+        let ops = parse_ops(fields.next().unwrap_or("")).ok_or_else(invalid)?;
+
+        let op = |name: &str| {
+            ops.iter()
+                .find(|(n, _)| n == name)
+                .and_then(|(_, operands)| operands.first().cloned())
+        };
+        let fen = format!(
+            "{} {} {}",
+            position.join(" "),
+            op("hmvc").unwrap_or_else(|| "0".to_string()),
+            op("fmvn").unwrap_or_else(|| "1".to_string())
+        );
+        let epd = Epd {
+            board: Board::parse(Fen(fen))?,
+            ops,
+        };
+
+        let gen = MovGen::new();
+        for (name, operands) in &epd.ops {
+            if MOVE_OPCODES.contains(&name.as_str()) {
+                for san in operands {
+                    parse_san(&epd.board, san, &gen)
+                        .map_err(|e| EpdError::Move(e.to_string()))?;
+                }
+            }
+        }
+        Ok(epd)
+    }
+
+    /// operands of `opcode`, quotes of strings are removed
+    pub fn get(&self, opcode: &str) -> Option<&[String]> {
+        self.ops
+            .iter()
+            .find(|(name, _)| name == opcode)
+            .map(|(_, operands)| operands.as_slice())
+    }
+
+    /// replaces the operands of `opcode` or adds it at the end
+    pub fn set(&mut self, opcode: &str, operands: Vec<String>) {
+        match self.ops.iter_mut().find(|(name, _)| name == opcode) {
+            Some((_, old)) => *old = operands,
+            None => self.ops.push((opcode.to_string(), operands)),
+        }
+    }
+
+    fn first(&self, opcode: &str) -> Option<&str> {
+        self.get(opcode)?.first().map(String::as_str)
+    }
+
+    fn moves(&self, opcode: &str) -> Vec<Move> {
+        let gen = MovGen::new();
+        self.get(opcode)
+            .unwrap_or(&[])
+            .iter()
+            .filter_map(|san| parse_san(&self.board, san, &gen).ok())
+            .collect()
+    }
+
+    pub fn id(&self) -> Option<&str> {
+        self.first("id")
+    }
+
+    /// comment `c0` to `c9`
+    pub fn comment(&self, n: u8) -> Option<&str> {
+        self.first(&format!("c{}", n))
+    }
+
+    /// `bm`, moves that solve the position
+    pub fn best_moves(&self) -> Vec<Move> {
+        self.moves("bm")
+    }
+
+    /// `am`, moves that fail the position
+    pub fn avoid_moves(&self) -> Vec<Move> {
+        self.moves("am")
+    }
+
+    /// `pm`, the move expected to be played
+    pub fn predicted_move(&self) -> Option<Move> {
+        self.moves("pm").first().copied()
+    }
+
+    /// `acd`, depth of the analysis that gave `ce`
+    pub fn acd(&self) -> Option<u32> {
+        self.first("acd")?.parse().ok()
+    }
+
+    /// `ce`, evaluation in centipawns from the side to move's point of view
+    pub fn ce(&self) -> Option<i32> {
+        self.first("ce")?.parse().ok()
+    }
+
+    /// points of moves in STS style suites, read from `c0` like `"Qxb3=10, Qe2=2"` or from uci
+    /// moves in `c9` with their points in `c8`. empty for suites that only have `bm`
+    pub fn move_points(&self) -> Vec<(Move, u32)> {
+        let gen = MovGen::new();
+        let mut points = Vec::new();
+
+        if let Some(c0) = self.comment(0) {
+            for entry in c0.split(',') {
+                let Some((san, value)) = entry.trim().split_once('=') else {
+                    continue;
+                };
+                if let (Ok(mov), Ok(value)) = (parse_san(&self.board, san, &gen), value.parse()) {
+                    points.push((mov, value));
+                }
+            }
+        }
+
+        if let (true, Some(moves), Some(values)) =
+            (points.is_empty(), self.comment(9), self.comment(8))
+        {
+            let mut legal = MoveList::new();
+            gen.generate_legal_moves(&self.board, &mut legal);
+            for (uci, value) in moves.split_whitespace().zip(values.split_whitespace()) {
+                let mov = legal.iter().find(|m| m.to_string() == uci);
+                if let (Some(mov), Ok(value)) = (mov, value.parse()) {
+                    points.push((*mov, value));
+                }
+            }
+        }
+        points
+    }
+
+    /// points `mov` scores and the most any move scores. with move points from STS style
+    /// comments these are used, otherwise a move from `bm` that isn't in `am` scores 1 like in WAC
+    pub fn score(&self, mov: Option<Move>) -> (u32, u32) {
+        let points = self.move_points();
+        if !points.is_empty() {
+            let max = points.iter().map(|(_, p)| *p).max().unwrap_or(0);
+            let scored = points
+                .iter()
+                .find(|(m, _)| Some(*m) == mov)
+                .map_or(0, |(_, p)| *p);
+            return (scored, max);
+        }
+
+        let best = self.best_moves();
+        let avoid = self.avoid_moves();
+        let solved =
+            mov.is_some_and(|m| (best.is_empty() || best.contains(&m)) && !avoid.contains(&m));
+        (solved as u32, 1)
+    }
+}
+
+// `opcode operand ...;` repeated, operands in quotes can contain spaces and `;`
+fn parse_ops(text: &str) -> Option<Vec<(String, Vec<String>)>> {
+    let mut ops = Vec::new();
+    let mut chars = text.chars().peekable();
+
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        if chars.peek().is_none() {
+            return Some(ops);
+        }
+
+        let mut words: Vec<String> = Vec::new();
+        let mut word = String::new();
+        loop {
+            match chars.next()? {
+                ';' => break,
+                '"' if word.is_empty() => {
+                    let quoted: String = chars.by_ref().take_while(|&c| c != '"').collect();
+                    words.push(quoted);
+                }
+                c if c.is_whitespace() => {
+                    if !word.is_empty() {
+                        words.push(std::mem::take(&mut word));
+                    }
+                }
+                c => word.push(c),
+            }
+        }
+        if !word.is_empty() {
+            words.push(word);
+        }
+
+        if words.is_empty() {
+            return None;
+        }
+        let name = words.remove(0);
+        ops.push((name, words));
+    }
+}
+
+impl Display for Epd {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let fen = self.board.to_fen();
+        let position: Vec<&str> = fen.split_whitespace().take(4).collect();
+        write!(f, "{}", position.join(" "))?;
+
+        for (name, operands) in &self.ops {
+            write!(f, " {}", name)?;
+            // ids and comments are strings, other operands only need quotes around spaces
+            let string = name == "id" || (name.len() == 2 && name.starts_with('c') && name != "ce");
+            for operand in operands {
+                if string || operand.is_empty() || operand.contains([' ', '\t', ';']) {
+                    write!(f, " \"{}\"", operand)?;
+                } else {
+                    write!(f, " {}", operand)?;
+                }
+            }
+            write!(f, ";")?;
+        }
+        Ok(())
+    }
+}
+
+/// positions of an EPD file with the line each came from, blank lines and lines starting with
+/// `#` are skipped. a line that fails to parse doesn't stop the others from being read
+pub fn read_epd<R: BufRead>(reader: R) -> Vec<(usize, Result<Epd, EpdError>)> {
+    reader
+        .lines()
+        .enumerate()
+        .filter_map(|(i, line)| {
+            let line = match line {
+                Ok(line) => line,
+                Err(e) => return Some((i + 1, Err(EpdError::Epd(e.to_string())))),
+            };
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                return None;
+            }
+            Some((i + 1, Epd::parse(trimmed)))
+        })
+        .collect()
+}
+
+/// How the search did on one position of a suite
+#[derive(Debug, Clone, PartialEq)]
+pub struct EpdResult {
+    pub id: Option<String>,
+    pub best_move: Option<Move>,
+    pub points: u32,
+    pub max_points: u32,
+    pub time: Duration,
+    pub nodes: u64,
+}
+
+impl EpdResult {
+    pub fn solved(&self) -> bool {
+        self.max_points > 0 && self.points == self.max_points
+    }
+}
+
+/// Results of a whole suite
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SuiteReport {
+    pub results: Vec<EpdResult>,
+}
+
+impl SuiteReport {
+    pub fn solved(&self) -> usize {
+        self.results.iter().filter(|r| r.solved()).count()
+    }
+
+    pub fn failed(&self) -> usize {
+        self.results.len() - self.solved()
+    }
+
+    pub fn points(&self) -> u32 {
+        self.results.iter().map(|r| r.points).sum()
+    }
+
+    pub fn max_points(&self) -> u32 {
+        self.results.iter().map(|r| r.max_points).sum()
+    }
+
+    pub fn average_time(&self) -> Duration {
+        let total: Duration = self.results.iter().map(|r| r.time).sum();
+        total / self.results.len().max(1) as u32
+    }
+}
+
+impl Display for SuiteReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Positions        : {}", self.results.len())?;
+        writeln!(f, "Solved           : {}", self.solved())?;
+        writeln!(f, "Failed           : {}", self.failed())?;
+        writeln!(
+            f,
+            "Points           : {}/{}",
+            self.points(),
+            self.max_points()
+        )?;
+        write!(f, "Average time (ms): {}", self.average_time().as_millis())
+    }
+}
+
+/// searches every position with `limits` on a fresh transposition table, `on_result` is called
+/// after each one so long suites can report progress
+pub fn run_suite(
+    engine: &mut Engine,
+    positions: &[Epd],
+    limits: SearchLimits,
+    mut on_result: impl FnMut(&Epd, &EpdResult),
+) -> SuiteReport {
+    let mut report = SuiteReport::default();
+
+    for epd in positions {
+        engine.new_game();
+        engine.set_board(epd.board);
+        let res = engine.search(limits);
+        let (points, max_points) = epd.score(res.best_move);
+
+        let result = EpdResult {
+            id: epd.id().map(String::from),
+            best_move: res.best_move,
+            points,
+            max_points,
+            time: res.time,
+            nodes: res.nodes,
+        };
+        on_result(epd, &result);
+        report.results.push(result);
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_epd_test() {
+        let line = r#"2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id "WAC.001"; c0 "a; b";"#;
+        let epd = Epd::parse(line).unwrap();
+        assert_eq!(epd.id(), Some("WAC.001"));
+        assert_eq!(epd.comment(0), Some("a; b"));
+        assert_eq!(epd.best_moves()[0].to_string(), "g3g6");
+        assert!(epd.avoid_moves().is_empty());
+        assert_eq!(epd.to_string(), line);
+
+        let epd = Epd::parse(
+            "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - hmvc 2; fmvn 3; \
+             am Qe2 Bc4; pm Bb5; acd 12; ce -35;",
+        )
+        .unwrap();
+        assert_eq!(
+            epd.board.to_fen(),
+            "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3"
+        );
+        assert_eq!(epd.avoid_moves().len(), 2);
+        assert_eq!(epd.acd(), Some(12));
+        assert_eq!(epd.ce(), Some(-35));
+        assert_eq!(epd.predicted_move().unwrap().to_string(), "f1b5");
+
+        assert!(matches!(
+            Epd::parse("6k1/5ppp/8/8/8/8/8/X5K1 w - - bm Kg6;"),
+            Err(EpdError::Fen(_))
+        ));
+        assert!(matches!(
+            Epd::parse("6k1/5ppp/8/8/8/8/8/R5K1 w - - bm Ra9;"),
+            Err(EpdError::Move(_))
+        ));
+        assert!(Epd::parse("6k1/5ppp/8/8/8/8/8/R5K1 w - - bm Ra8").is_err());
+        assert!(Epd::parse("6k1/5ppp/8/8/8/8/8/R5K1 w").is_err());
+    }
+
+    #[test]
+    fn score_epd_test() {
+        let epd = Epd::parse("6k1/5ppp/8/8/8/8/8/R5K1 w - - bm Ra8#; id \"mate\";").unwrap();
+        let mate = epd.best_moves()[0];
+        assert_eq!(epd.score(Some(mate)), (1, 1));
+        assert_eq!(epd.score(None), (0, 1));
+
+        let sts = r#"6k1/5ppp/8/8/8/8/8/R5K1 w - - bm Ra8; c0 "Ra8=10, Ra7=3"; c8 "1"; c9 "a1a2";"#;
+        let epd = Epd::parse(sts).unwrap();
+        let ra7 = parse_san(&epd.board, "Ra7", &MovGen::new()).unwrap();
+        assert_eq!(epd.score(Some(mate)), (10, 10));
+        assert_eq!(epd.score(Some(ra7)), (3, 10));
+
+        // points from c8 and c9 when c0 has none
+        let sts = r#"6k1/5ppp/8/8/8/8/8/R5K1 w - - bm Ra8; c8 "10 4"; c9 "a1a8 a1a7";"#;
+        let epd = Epd::parse(sts).unwrap();
+        assert_eq!(epd.score(Some(ra7)), (4, 10));
+    }
+
+    #[test]
+    fn run_suite_test() {
+        let suite = "\
+# mate in one and mate in two
+6k1/5ppp/8/8/8/8/8/R5K1 w - - bm Ra8#; id \"one\";
+kbK5/pp6/1P6/8/8/8/8/R7 w - - bm Ra6; id \"two\";
+kbK5/pp6/1P6/8/8/8/8/R7 w - - am Ra6; id \"avoid\";
+
+not an epd
+";
+        let read = read_epd(suite.as_bytes());
+        assert_eq!(read.len(), 4);
+        assert_eq!(read[3].0, 6);
+        assert!(read[3].1.is_err());
+        let positions: Vec<Epd> = read.into_iter().filter_map(|(_, e)| e.ok()).collect();
+
+        let mut engine = Engine::new();
+        let mut seen = Vec::new();
+        let report = run_suite(
+            &mut engine,
+            &positions,
+            SearchLimits::depth(4),
+            |epd, res| seen.push((epd.id().unwrap().to_string(), res.solved())),
+        );
+        assert_eq!(
+            seen,
+            [
+                ("one".to_string(), true),
+                ("two".to_string(), true),
+                ("avoid".to_string(), false)
+            ]
+        );
+        assert_eq!((report.solved(), report.failed()), (2, 1));
+        assert_eq!((report.points(), report.max_points()), (2, 3));
+        assert!(report.to_string().contains("Solved           : 2"));
+
+        let report = run_suite(
+            &mut engine,
+            &positions[..1],
+            SearchLimits::nodes(5000),
+            |_, _| {},
+        );
+        assert_eq!(report.solved(), 1);
+    }
+}
@@ -0,0 +1,300 @@
+use super::board::board::Board;
+use super::board::fen::{Fen, FenParse, FenParseError};
+use super::board::moves::{Move, MoveArray, MoveList};
+use super::board::piece::Color;
+use super::board::san::{parse_san, to_san};
+use super::mov_gen::generator::MovGen;
+use std::fmt::Display;
+
+/// How a game ended or can be ended by a claim
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum GameResult {
+    /// won by the given side
+    Checkmate(Color),
+    Stalemate,
+    /// neither side has the material to ever mate
+    InsufficientMaterial,
+    /// the same position occurred five times, ends the game without a claim
+    FivefoldRepetition,
+    /// 75 moves by each side without a capture or pawn move, ends the game without a claim
+    SeventyFiveMoves,
+    /// the same position occurred three times, only a draw if claimed
+    ThreefoldRepetition,
+    /// 50 moves by each side without a capture or pawn move, only a draw if claimed
+    FiftyMoves,
+}
+
+impl GameResult {
+    pub fn winner(&self) -> Option<Color> {
+        match self {
+            GameResult::Checkmate(color) => Some(*color),
+            _ => None,
+        }
+    }
+
+    pub fn is_draw(&self) -> bool {
+        self.winner().is_none()
+    }
+}
+
+/// the score as written in PGN
+impl Display for GameResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.winner() {
+            Some(Color::White) => write!(f, "1-0"),
+            Some(Color::Black) => write!(f, "0-1"),
+            None => write!(f, "1/2-1/2"),
+        }
+    }
+}
+
+/// A game from some starting position, keeps every move played so they can be taken back and
+/// repetitions can be detected
+#[derive(Debug, Clone)]
+pub struct Game {
+    start: Board,
+    board: Board,
+    history: MoveArray,
+    gen: MovGen,
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Self::new(Board::default())
+    }
+}
+
+impl Game {
+    pub fn new(board: Board) -> Self {
+        Game {
+            start: board,
+            board,
+            history: MoveArray::new(),
+            gen: MovGen::new(),
+        }
+    }
+
+    pub fn from_fen(fen: &str) -> Result<Self, FenParseError> {
+        Ok(Self::new(Board::parse(Fen(fen.to_string()))?))
+    }
+
+    /// the current position
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    /// the position the game started from
+    pub fn start(&self) -> &Board {
+        &self.start
+    }
+
+    /// moves played so far, oldest first
+    pub fn moves(&self) -> impl Iterator<Item = Move> + '_ {
+        self.history.iter().map(|h| h.mov)
+    }
+
+    /// number of half moves played
+    pub fn ply(&self) -> usize {
+        self.history.len()
+    }
+
+    pub fn legal_moves(&self) -> MoveList {
+        let mut moves = MoveList::new();
+        self.gen.generate_legal_moves(&self.board, &mut moves);
+        moves
+    }
+
+    /// the legal move written as `text`, in uci notation like `e7e8q` or san like `e8=Q`
+    pub fn find_move(&self, text: &str) -> Option<Move> {
+        self.legal_moves()
+            .iter()
+            .copied()
+            .find(|m| m.to_string() == text)
+            .or_else(|| parse_san(&self.board, text, &self.gen).ok())
+    }
+
+    /// `mov` in standard algebraic notation, it has to be legal in the current position
+    pub fn to_san(&self, mov: Move) -> String {
+        to_san(&self.board, mov, &self.gen)
+    }
+
+    /// plays `mov` if it is legal, returns whether it was played
+    pub fn make_move(&mut self, mov: Move) -> bool {
+        if !self.gen.is_legal(&self.board, mov) {
+            return false;
+        }
+        let history = self.board.make_move(mov, false).unwrap();
+        self.history.push(history);
+        true
+    }
+
+    /// takes back the last move, `None` at the start of the game
+    pub fn takeback(&mut self) -> Option<Move> {
+        let history = self.history.pop()?;
+        self.board.unmake_move(history);
+        Some(history.mov)
+    }
+
+    /// how often the current position occurred, itself included. only positions since the last
+    /// capture or pawn move can be the same
+    pub fn repetitions(&self) -> usize {
+        let reversible = self.board.info.get_halfmove_clock() as usize;
+        let earlier = self
+            .history
+            .iter()
+            .rev()
+            .take(reversible)
+            .filter(|h| h.hash == self.board.hash)
+            .count();
+        earlier + 1
+    }
+
+    pub fn is_check(&self) -> bool {
+        self.board.is_check(&self.gen)
+    }
+
+    /// the result if the game is over without either side claiming anything
+    pub fn result(&self) -> Option<GameResult> {
+        if self.legal_moves().is_empty() {
+            return Some(if self.is_check() {
+                GameResult::Checkmate(!self.board.get_side_to_move())
+            } else {
+                GameResult::Stalemate
+            });
+        }
+
+        if self.board.is_insufficient_material() {
+            Some(GameResult::InsufficientMaterial)
+        } else if self.repetitions() >= 5 {
+            Some(GameResult::FivefoldRepetition)
+        } else if self.board.info.get_halfmove_clock() >= 150 {
+            Some(GameResult::SeventyFiveMoves)
+        } else {
+            None
+        }
+    }
+
+    /// the draw the side to move can claim, `None` if the game is already over
+    pub fn claimable_draw(&self) -> Option<GameResult> {
+        if self.result().is_some() {
+            None
+        } else if self.repetitions() >= 3 {
+            Some(GameResult::ThreefoldRepetition)
+        } else if self.board.is_fifty_move_draw() {
+            Some(GameResult::FiftyMoves)
+        } else {
+            None
+        }
+    }
+
+    /// the result once every claimable draw is claimed, what engine matches usually score
+    pub fn result_with_claims(&self) -> Option<GameResult> {
+        self.result().or_else(|| self.claimable_draw())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play(game: &mut Game, moves: &[&str]) {
+        for uci in moves {
+            let mov = game.find_move(uci).unwrap();
+            assert!(game.make_move(mov), "{}", uci);
+        }
+    }
+
+    #[test]
+    fn game_result_test() {
+        let mut game = Game::default();
+        play(&mut game, &["f2f3", "e5", "g4"]);
+        let mate = game.find_move("Qh4#").unwrap();
+        assert_eq!(game.to_san(mate), "Qh4#");
+        assert!(game.make_move(mate));
+        assert_eq!(game.result(), Some(GameResult::Checkmate(Color::Black)));
+        assert_eq!(game.result().unwrap().to_string(), "0-1");
+        assert_eq!(game.claimable_draw(), None);
+
+        assert_eq!(game.takeback().unwrap().to_string(), "d8h4");
+        assert_eq!(game.result(), None);
+        assert_eq!(game.ply(), 3);
+
+        let game = Game::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(game.result(), Some(GameResult::Stalemate));
+
+        let game = Game::from_fen("7k/8/6K1/8/8/8/8/8 w - - 149 90").unwrap();
+        assert_eq!(game.result(), Some(GameResult::InsufficientMaterial));
+
+        let game = Game::from_fen("7k/8/6K1/8/8/8/8/R7 w - - 149 90").unwrap();
+        assert_eq!(game.result(), None);
+        assert_eq!(game.claimable_draw(), Some(GameResult::FiftyMoves));
+        let mut game = Game::from_fen("7k/8/6K1/8/8/8/8/R7 w - - 149 90").unwrap();
+        play(&mut game, &["a1b1"]);
+        assert_eq!(game.result(), Some(GameResult::SeventyFiveMoves));
+        // mate on the 75th move stands
+        let mut game = Game::from_fen("7k/8/6K1/8/8/8/8/R7 w - - 149 90").unwrap();
+        play(&mut game, &["a1a8"]);
+        assert_eq!(game.result(), Some(GameResult::Checkmate(Color::White)));
+    }
+
+    #[test]
+    fn repetition_test() {
+        let mut game = Game::default();
+        let shuffle = ["g1f3", "g8f6", "f3g1", "f6g8"];
+        assert_eq!(game.repetitions(), 1);
+
+        play(&mut game, &shuffle);
+        assert_eq!(game.repetitions(), 2);
+        assert_eq!(game.claimable_draw(), None);
+
+        play(&mut game, &shuffle);
+        assert_eq!(game.repetitions(), 3);
+        assert_eq!(game.result(), None);
+        assert_eq!(game.claimable_draw(), Some(GameResult::ThreefoldRepetition));
+        assert_eq!(game.result_with_claims().unwrap().to_string(), "1/2-1/2");
+
+        play(&mut game, &shuffle);
+        play(&mut game, &shuffle);
+        assert_eq!(game.result(), Some(GameResult::FivefoldRepetition));
+
+        // a pawn move makes every earlier position unreachable
+        play(&mut game, &["e2e4", "e7e5"]);
+        play(&mut game, &shuffle);
+        assert_eq!(game.repetitions(), 2);
+
+        while game.takeback().is_some() {}
+        assert_eq!(game.board(), game.start());
+        assert!(!game.make_move(Move::NULL));
+    }
+
+    #[test]
+    fn insufficient_material_test() {
+        let dead = [
+            "8/8/4k3/8/8/3K4/8/8 w - - 0 1",
+            "8/8/4k3/8/8/3K4/5N2/8 w - - 0 1",
+            "8/8/4k3/8/8/3K4/5b2/8 w - - 0 1",
+            // bishops of both sides, all on dark squares
+            "8/8/4k3/2b5/8/3K4/5B2/8 w - - 0 1",
+            "1b6/8/4k3/2b5/8/3K4/5B2/8 w - - 0 1",
+        ];
+        let alive = [
+            "8/8/4k3/8/8/3K4/5P2/8 w - - 0 1",
+            "8/8/4k3/8/8/3K4/5R2/8 w - - 0 1",
+            "8/8/4k3/8/8/3K4/4NN2/8 w - - 0 1",
+            "8/8/4k3/8/8/3K4/4nB2/8 w - - 0 1",
+            // bishops on both colours
+            "8/8/4k3/8/8/3K4/4bB2/8 w - - 0 1",
+        ];
+
+        for fen in dead {
+            let game = Game::from_fen(fen).unwrap();
+            assert!(game.board().is_insufficient_material(), "{}", fen);
+            assert_eq!(game.result(), Some(GameResult::InsufficientMaterial));
+        }
+        for fen in alive {
+            let game = Game::from_fen(fen).unwrap();
+            assert!(!game.board().is_insufficient_material(), "{}", fen);
+            assert_eq!(game.result(), None, "{}", fen);
+        }
+    }
+}
@@ -1,195 +1,314 @@
+use super::bench::{BenchResult, BENCH_DEPTH, BENCH_POSITIONS};
 use super::board::board::Board;
-use super::board::moves::Move;
-use super::kelp_core::lookup_table::LookupTable;
+use super::board::moves::{Move, MoveArray, MoveList};
+use super::board::san::parse_san;
+use super::book::polyglot::Book;
 use super::mov_gen::generator::MovGen;
+use super::observer::{NoObserver, SearchObserver};
 use super::uci_trait::UCI;
-use super::{stop_interval, STOP};
+use super::{cancel_stop_interval, stop_interval, STOP};
 use super::{SearchMoveResult, SearchMoveResultExtended, TimeControl};
 use crate::kelp::board::fen::{Fen, FenParse};
 use crate::kelp::board::piece::BoardPiece::*;
 use crate::kelp::search::negamax::Negamax;
+use crate::kelp::search::root::RootMove;
+use crate::kelp::search::score::Score;
+use crate::kelp::search::smp::spawn_helpers;
+use crate::kelp::syzygy::{Syzygy, Tablebase, MAX_PIECES};
 use log;
-use std::sync::atomic::Ordering;
-use std::sync::mpsc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Arc};
 use std::thread;
 
 /// Main Implementation for all UCI commands also acts as a library for the engine
 /// Kelp contains the board and the mov_gen from kelp::board and kelp::mov_gen respectively
-pub struct Kelp<'a> {
+pub struct Kelp {
     pub board: Board,
-    pub mov_gen: MovGen<'a>,
+    /// moves played since the position was set, undone by `unmake_move`
+    pub move_history: MoveArray,
+    pub mov_gen: MovGen,
     pub search: Negamax,
+    /// number of search threads including the main one
+    pub threads: usize,
+    /// send search statistics as an `info string` after every search
+    pub search_stats: bool,
+    pub book: Option<Book>,
+    /// play book moves before searching
+    pub own_book: bool,
+    /// pick book moves at random by weight instead of always the heaviest one
+    pub book_random: bool,
+    helper_nodes: Arc<AtomicU64>,
+    helper_tb_hits: Arc<AtomicU64>,
 }
 
-impl<'a> Kelp<'a> {
-    const ASPIRATION_WINDOW: i32 = 50;
+impl Default for Kelp {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Kelp {
+    pub const MAX_THREADS: usize = 256;
 
-    ///Creates a new instance of Kelp, populates the lookup table in case if its not populated beforehand
-    pub fn new(table: &'a mut LookupTable) -> Self {
-        table.populate();
+    ///Creates a new instance of Kelp, the lookup table is shared by every instance and populated on first use
+    pub fn new() -> Self {
         Kelp {
             board: Board::default(),
-            mov_gen: MovGen::new(table),
+            move_history: MoveArray::new(),
+            mov_gen: MovGen::new(),
             search: Negamax::default(),
+            threads: 1,
+            search_stats: false,
+            book: None,
+            own_book: false,
+            book_random: true,
+            helper_nodes: Arc::new(AtomicU64::new(0)),
+            helper_tb_hits: Arc::new(AtomicU64::new(0)),
         }
     }
 
-    ///compares mov_str by move.to_string, if it exist it checks if the move is legal by making/unmaking the move
+    /// finds the legal move written as `mov_str`, in uci notation or san
     pub fn parse_move(&mut self, mov_str: &str) -> Option<Move> {
-        if mov_str.len() < 4 || mov_str.len() > 5 {
-            return None;
-        }
-        self.mov_gen.generate_moves(&self.board);
+        let mut moves = MoveList::new();
+        self.mov_gen.generate_legal_moves(&self.board, &mut moves);
 
-        for mov in self.mov_gen.move_list.iter() {
+        for mov in moves.iter() {
             if mov_str == mov.to_string() {
                 return Some(*mov);
             }
         }
-        None
+        parse_san(&self.board, mov_str, &self.mov_gen).ok()
     }
 
     /// makes move on board, unmakes it if it is illegal
     pub fn make_move(&mut self, mov: Move) -> bool {
-        self.board.make(mov);
+        let history = self.board.make_move(mov, false).unwrap();
         if self.board.is_check_opp(&self.mov_gen) {
-            self.board.unmake();
+            self.board.unmake_move(history);
             return false;
         }
+        self.move_history.push(history);
         true
     }
 
     /// unmakes move from move history
     pub fn unmake_move(&mut self) {
-        self.board.unmake();
+        if let Some(history) = self.move_history.pop() {
+            self.board.unmake_move(history);
+        }
+    }
+
+    /// the book move for the current position if `own_book` is set
+    pub fn book_move(&self) -> Option<Move> {
+        if !self.own_book {
+            return None;
+        }
+        let book = self.book.as_ref()?;
+        if self.book_random {
+            book.pick_move(&self.board, &self.mov_gen, &mut rand::thread_rng())
+        } else {
+            book.best_move(&self.board, &self.mov_gen)
+        }
     }
 
     pub fn get_fen(&self) -> String {
         self.board.to_fen()
     }
 
+    /// runs `main` on this thread while `threads - 1` helpers search the same position,
+    /// helpers are stopped and joined once `main` returns. clears the search's stop flag, deadline and node limit after
+    fn with_helpers<T>(&mut self, depth: usize, main: impl FnOnce(&mut Self) -> T) -> T {
+        self.helper_nodes.store(0, Ordering::Relaxed);
+        self.helper_tb_hits.store(0, Ordering::Relaxed);
+        let stop = self.search.stop_flag();
+
+        let res = if self.threads <= 1 {
+            main(self)
+        } else {
+            let shared_nodes = Arc::clone(&self.helper_nodes);
+            let shared_tb_hits = Arc::clone(&self.helper_tb_hits);
+            thread::scope(|s| {
+                spawn_helpers(
+                    s,
+                    self.threads,
+                    &self.board,
+                    &self.search,
+                    depth,
+                    &shared_nodes,
+                    &shared_tb_hits,
+                );
+                let res = main(self);
+                stop.store(true, Ordering::Relaxed);
+                res
+            })
+        };
+
+        stop.store(false, Ordering::Relaxed);
+        self.search.deadline = None;
+        self.search.node_limit = None;
+        res
+    }
+
+    /// searches every bench position to `depth` on a single thread with a fresh search and TT,
+    /// the node count only depends on the code so it can be used as a signature.
+    /// the current position and search state are left untouched
+    pub fn bench(&mut self, depth: usize) -> BenchResult {
+        let mut search = Negamax::default();
+        let mut nodes = 0;
+        let now = std::time::Instant::now();
+
+        for fen in BENCH_POSITIONS {
+            let mut board = Board::parse(Fen(fen.to_string())).unwrap();
+            search.reset_tables();
+            search.reset();
+            search.add_draw(board.hash);
+
+            let mut prev_score = None;
+            for i in 1..=depth {
+                search.nodes = 0;
+                prev_score = Some(search.aspiration(
+                    i,
+                    prev_score,
+                    &mut board,
+                    &mut self.mov_gen,
+                    &mut NoObserver,
+                ));
+                nodes += search.nodes;
+            }
+        }
+
+        let time = now.elapsed();
+        BenchResult {
+            positions: BENCH_POSITIONS.len(),
+            depth,
+            nodes,
+            time,
+            nps: (nodes as f64 / time.as_secs_f64()) as u64,
+        }
+    }
+
     // for uci only
-    #[inline(always)]
     fn search_move(&mut self, depth: usize) -> Option<Move> {
-        self.search.reset();
-        let mut score = 0;
+        let mut observer = UciObserver::new();
+        let res = self.search_move_observed(depth, &mut observer);
+
+        if self.search_stats {
+            self.send(format!("info string {}", self.search.stats).as_str());
+        }
+
+        STOP.store(false, Ordering::Relaxed);
+        res.best_move
+    }
+
+    /// searches the current position to `depth` and returns every legal move with an exact score,
+    /// best first. slower than `search_move_lib` as no root move is searched with a null window
+    pub fn analyse(&mut self, depth: usize) -> Vec<RootMove> {
+        self.search.analysis = true;
+        self.search_move_lib(depth);
+        self.search.analysis = false;
+        self.search.root_moves.clone()
+    }
+
+    ///search move for library functions
+    #[inline(always)]
+    pub fn search_move_lib(&mut self, depth: usize) -> SearchMoveResult {
+        self.search_move_observed(depth, &mut NoObserver)
+    }
 
-        let mut alpha = Negamax::MIN;
-        let mut beta = Negamax::MAX;
+    /// same as `search_move_lib` but reports progress to `observer` while searching
+    pub fn search_move_observed(
+        &mut self,
+        depth: usize,
+        observer: &mut dyn SearchObserver,
+    ) -> SearchMoveResult {
+        self.with_helpers(depth, |kelp| kelp.search_move_main(depth, observer))
+    }
 
-        let mut prev_depth_best_move = None;
+    // iterative deepening, if the search is stopped the result of the last completed iteration is returned
+    fn search_move_main(
+        &mut self,
+        depth: usize,
+        observer: &mut dyn SearchObserver,
+    ) -> SearchMoveResult {
+        self.search.reset();
+        let mut prev_score = None;
+        let mut nodes = 0;
+        let start = std::time::Instant::now();
+
+        let mut res = SearchMoveResult {
+            best_move: None,
+            score: Score(0),
+            depth: 0,
+            nodes: 0,
+            time: Default::default(),
+            nps: 0,
+            pv: Vec::new(),
+            mate_in: None,
+        };
 
-        //Iterative Deepening
         for i in 1..=depth {
             self.search.nodes = 0;
-            self.search.follow_pv = true;
             let now = std::time::Instant::now();
 
-            score = self
-                .search
-                .negamax(alpha, beta, i, &mut self.board, &mut self.mov_gen, 0);
+            let score = Score(self.search.aspiration(
+                i,
+                prev_score,
+                &mut self.board,
+                &mut self.mov_gen,
+                observer,
+            ));
 
-            if STOP.load(Ordering::Relaxed) {
-                // STOP.store(false, Ordering::Relaxed);
-                break;
-            }
+            let iter_nodes = self.search.nodes + self.helper_nodes.swap(0, Ordering::Relaxed);
+            nodes += iter_nodes;
 
-            if (score <= alpha) || (score >= beta) {
-                alpha = Negamax::MIN;
-                beta = Negamax::MAX;
-                continue;
+            if self.search.stopped() {
+                break;
             }
 
-            alpha = score - Self::ASPIRATION_WINDOW;
-            beta = score + Self::ASPIRATION_WINDOW;
-
+            prev_score = Some(score.0);
             let elapsed = now.elapsed();
+            // counted over the whole search, the root is probed once and then the tree isn't
+            let tb_hits = self.search.tb_hits + self.helper_tb_hits.load(Ordering::Relaxed);
 
-            let mut mate_in: Option<i32> = None;
-
-            if score > -Negamax::MATE_VALUE && score < -Negamax::MATE_SCORE {
-                mate_in = Some(-(score + Negamax::MATE_VALUE) / 2 - 1);
-            } else if score > Negamax::MATE_SCORE && score < Negamax::MATE_VALUE {
-                mate_in = Some((Negamax::MATE_VALUE - score) / 2 + 1);
-            }
-
-            let res = SearchMoveResultExtended {
+            let info = SearchMoveResultExtended {
                 best_move: self.search.get_pv_table(0, 0),
                 score,
                 depth: i,
-                nodes: self.search.nodes,
+                nodes: iter_nodes,
                 time: elapsed,
-                nps: (self.search.nodes as f64 / elapsed.as_secs_f64()) as u64,
-                pv: self.search.get_pv_str(),
-                mate_in,
+                nps: (iter_nodes as f64 / elapsed.as_secs_f64()) as u64,
+                pv: self.search.get_pv(),
+                mate_in: score.mate_moves(),
                 hash_full: self.search.tt.get_hash_full_percentage() as usize,
-                tb_hits: self.search.tt.get_hits() as usize,
-                misses: self.search.tt.get_misses() as usize,
+                tb_hits: tb_hits as usize,
+                misses: self.search.tt_misses as usize,
                 size: self.search.tt.get_hashmap_size_mb(),
             };
-
-            prev_depth_best_move = res.best_move;
-            self.send_info(format!("{}", res).as_str());
-            self.search.tt.reset_hits_and_misses();
+            observer.on_iteration(&info);
+            self.search.tt_hits = 0;
+            self.search.tt_misses = 0;
+
+            res.best_move = info.best_move;
+            res.score = score;
+            res.depth = i;
+            res.pv = info.pv;
+            res.mate_in = info.mate_in;
         }
 
-        if STOP.load(Ordering::Relaxed) { // if search was stopped
-            STOP.store(false, Ordering::Relaxed);
-            if prev_depth_best_move.is_some() {
-                return prev_depth_best_move;
-            }
-        }
-
-        STOP.store(false, Ordering::Relaxed);
-        self.search.get_pv_table(0, 0)
-    }
-
-    ///search move for library functions
-    #[inline(always)]
-    pub fn search_move_lib(&mut self, depth: usize) -> SearchMoveResult {
-        self.search.reset();
-        let mut score = 0;
-
-        let mut alpha = Negamax::MIN;
-        let mut beta = Negamax::MAX;
-
-        let now = std::time::Instant::now();
-
-        for i in 1..=depth {
-            self.search.nodes = 0;
-            // let now = std::time::Instant::now();
-            score = self
+        // stopped during the first iteration, a partial result beats no move at all
+        if res.best_move.is_none() {
+            res.best_move = self
                 .search
-                .negamax(alpha, beta, i, &mut self.board, &mut self.mov_gen, 0);
-
-            if (score <= alpha) || (score >= beta) {
-                alpha = Negamax::MIN;
-                beta = Negamax::MAX;
-                continue;
-            }
-
-            alpha = score - Self::ASPIRATION_WINDOW;
-            beta = score + Self::ASPIRATION_WINDOW;
+                .get_pv_table(0, 0)
+                .or(self.search.root_moves.first().map(|m| m.mov));
         }
 
-        let mut mate_in: Option<i32> = None;
-
-        if score > -Negamax::MATE_VALUE && score < -Negamax::MATE_SCORE {
-            mate_in = Some(-(score + Negamax::MATE_VALUE) / 2 - 1);
-        } else if score > Negamax::MATE_SCORE && score < Negamax::MATE_VALUE {
-            mate_in = Some((Negamax::MATE_VALUE - score) / 2 + 1);
-        }
+        res.nodes = nodes;
+        res.time = start.elapsed();
+        res.nps = (nodes as f64 / res.time.as_secs_f64()) as u64;
 
-        SearchMoveResult {
-            best_move: self.search.get_pv_table(0, 0),
-            score,
-            depth,
-            nodes: self.search.nodes,
-            time: now.elapsed(),
-            nps: (self.search.nodes as f64 / now.elapsed().as_secs_f64()) as u64,
-            pv: self.search.get_pv_str(),
-            mate_in,
-        }
+        observer.on_finish(&res);
+        res
     }
 
     // custom uci handler
@@ -208,7 +327,42 @@ impl<'a> Kelp<'a> {
     }
 }
 
-impl UCI for Kelp<'_> {
+/// Sends search progress as uci `info` lines
+struct UciObserver {
+    start: std::time::Instant,
+}
+
+impl UciObserver {
+    // currmove is only worth sending once the search has been running for a while
+    const CURRMOVE_DELAY: std::time::Duration = std::time::Duration::from_millis(1000);
+
+    fn new() -> Self {
+        UciObserver {
+            start: std::time::Instant::now(),
+        }
+    }
+
+    fn send_info(&self, info: &str) {
+        log::info!("Sent: info {}", info);
+        println!("info {}", info);
+    }
+}
+
+impl SearchObserver for UciObserver {
+    fn on_iteration(&mut self, info: &SearchMoveResultExtended) {
+        self.send_info(format!("{}", info).as_str());
+    }
+
+    fn on_currmove(&mut self, mov: Move, number: usize, depth: usize) {
+        if self.start.elapsed() >= Self::CURRMOVE_DELAY {
+            self.send_info(
+                format!("depth {} currmove {} currmovenumber {}", depth, mov, number).as_str(),
+            );
+        }
+    }
+}
+
+impl UCI for Kelp {
     fn handle_position(&mut self, arg: &[&str]) {
         STOP.store(false, Ordering::Relaxed);
         if arg.len() < 1 {
@@ -238,6 +392,7 @@ impl UCI for Kelp<'_> {
             self.board = board.unwrap();
         }
 
+        self.move_history.clear();
         self.search.clear_draw(); // reset draw table
         self.search.add_draw(self.board.hash); // add current position to draw table
 
@@ -260,6 +415,7 @@ impl UCI for Kelp<'_> {
 
     fn handle_uci_newgame(&mut self) {
         self.board = Board::default();
+        self.move_history.clear();
         self.search.reset();
         self.search.reset_tables();
         self.search.add_draw(self.board.hash); // add current position to draw table
@@ -315,6 +471,14 @@ impl UCI for Kelp<'_> {
             }
         }
 
+        // an infinite search only ends with `stop`, so the book is skipped when analysing
+        if !time_control.infinite {
+            if let Some(mov) = self.book_move() {
+                self.send_bestmove(format!("{}", mov).as_str());
+                return;
+            }
+        }
+
         let time_to_search = time_control.calculate_time(self.board.get_side_to_move());
 
         if time_to_search.is_none() && depth == 0 && !time_control.infinite {
@@ -331,6 +495,7 @@ impl UCI for Kelp<'_> {
         }
 
         let best_move = self.search_move(depth);
+        cancel_stop_interval();
         STOP.store(false, Ordering::Relaxed);
 
         if best_move.is_none() {
@@ -340,7 +505,7 @@ impl UCI for Kelp<'_> {
         }
     }
 
-    fn handle_uci(&self, arg: &[&str]) {
+    fn handle_uci(&self, _arg: &[&str]) {
         let mut name = env!("CARGO_PKG_NAME").to_string();
         name = name.split("_").collect::<Vec<&str>>()[0].to_string();
         //capitalize first letter
@@ -357,9 +522,123 @@ impl UCI for Kelp<'_> {
 
         self.send(format!("id name {}", name).as_str());
         self.send(format!("id author {}", author).as_str());
+        self.send(
+            format!(
+                "option name Threads type spin default 1 min 1 max {}",
+                Self::MAX_THREADS
+            )
+            .as_str(),
+        );
+        self.send("option name SearchStats type check default false");
+        self.send("option name OwnBook type check default false");
+        self.send("option name Book File type string default <empty>");
+        self.send("option name BookRandom type check default true");
+        self.send("option name SyzygyPath type string default <empty>");
+        self.send(
+            format!(
+                "option name SyzygyProbeLimit type spin default {} min 0 max {}",
+                MAX_PIECES, MAX_PIECES
+            )
+            .as_str(),
+        );
         self.send("uciok");
     }
 
+    fn handle_setoption(&mut self, arg: &[&str]) {
+        // setoption name <id> [value <x>]
+        let name_pos = arg.iter().position(|&a| a == "name");
+        let value_pos = arg.iter().position(|&a| a == "value");
+
+        let name = match name_pos {
+            Some(pos) => arg[pos + 1..value_pos.unwrap_or(arg.len())].join(" "),
+            None => return,
+        };
+        let value = value_pos.map(|pos| arg[pos + 1..].join(" "));
+
+        match name.to_lowercase().as_str() {
+            "threads" => {
+                let threads = value.and_then(|v| v.parse::<usize>().ok());
+                match threads {
+                    Some(threads) => self.threads = threads.clamp(1, Self::MAX_THREADS),
+                    None => self.send("info string invalid value for Threads"),
+                }
+            }
+            "searchstats" => match value.as_deref() {
+                Some("true") => self.search_stats = true,
+                Some("false") => self.search_stats = false,
+                _ => self.send("info string invalid value for SearchStats"),
+            },
+            "ownbook" => match value.as_deref() {
+                Some("true") => self.own_book = true,
+                Some("false") => self.own_book = false,
+                _ => self.send("info string invalid value for OwnBook"),
+            },
+            "bookrandom" => match value.as_deref() {
+                Some("true") => self.book_random = true,
+                Some("false") => self.book_random = false,
+                _ => self.send("info string invalid value for BookRandom"),
+            },
+            "book file" => match value.as_deref() {
+                None | Some("") | Some("<empty>") => self.book = None,
+                Some(path) => match Book::open(path) {
+                    Ok(book) => self.book = Some(book),
+                    Err(e) => {
+                        self.book = None;
+                        self.send(format!("info string can't open book {}: {}", path, e).as_str());
+                    }
+                },
+            },
+            "syzygypath" => match value.as_deref() {
+                None | Some("") | Some("<empty>") => self.search.tablebase = None,
+                Some(paths) => match Syzygy::open(paths) {
+                    Ok(syzygy) => {
+                        self.send(
+                            format!(
+                                "info string found {} tablebases with up to {} pieces",
+                                syzygy.len(),
+                                syzygy.max_pieces()
+                            )
+                            .as_str(),
+                        );
+                        self.search.tablebase =
+                            (!syzygy.is_empty()).then(|| Arc::new(syzygy) as Arc<dyn Tablebase>);
+                    }
+                    Err(e) => {
+                        self.search.tablebase = None;
+                        self.send(
+                            format!("info string can't open tablebases {}: {}", paths, e).as_str(),
+                        );
+                    }
+                },
+            },
+            "syzygyprobelimit" => {
+                let limit = value.and_then(|v| v.parse::<usize>().ok());
+                match limit {
+                    Some(limit) => self.search.tb_probe_limit = limit.min(MAX_PIECES),
+                    None => self.send("info string invalid value for SyzygyProbeLimit"),
+                }
+            }
+            _ => self.send(format!("info string unknown option {}", name).as_str()),
+        }
+    }
+
+    fn handle_bench(&mut self, arg: &[&str]) {
+        let depth = match arg.first() {
+            Some(depth) => match depth.parse::<usize>() {
+                Ok(depth) if depth > 0 && depth < Negamax::MAX_DEPTH => depth,
+                _ => {
+                    self.send("info string invalid bench depth");
+                    return;
+                }
+            },
+            None => BENCH_DEPTH,
+        };
+
+        STOP.store(false, Ordering::Relaxed);
+        let res = self.bench(depth);
+        self.send(format!("{}", res).as_str());
+    }
+
     fn handle_quit(&self) {
         std::process::exit(0);
     }
@@ -372,7 +651,7 @@ impl UCI for Kelp<'_> {
         self.send("readyok"); //TODO: Implement this
     }
 
-    fn handle_unknown(&self, command: &str, arg: &[&str]) {
+    fn handle_unknown(&self, command: &str, _arg: &[&str]) {
         match command {
             "help" => self.send(env!("CARGO_PKG_DESCRIPTION")),
             "version" | "v" => {
@@ -425,3 +704,188 @@ impl UCI for Kelp<'_> {
         log::info!("{}", arg);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kelp::search::root::Bound;
+
+    fn search_mate(fen: &str, depth: usize) -> (String, Option<i32>) {
+        let mut kelp = Kelp::new();
+        kelp.handle_position(&["fen", fen]);
+        let res = kelp.search_move_lib(depth);
+        (res.best_move.unwrap().to_string(), res.mate_in)
+    }
+
+    #[test]
+    fn mate_in_one_test() {
+        let (mov, mate) = search_mate("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", 2);
+        assert_eq!(mov, "a1a8");
+        assert_eq!(mate, Some(1));
+
+        let (mov, mate) = search_mate(
+            "r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4",
+            3,
+        );
+        assert_eq!(mov, "h5f7");
+        assert_eq!(mate, Some(1));
+    }
+
+    #[test]
+    fn mate_in_two_test() {
+        let (mov, mate) = search_mate("kbK5/pp6/1P6/8/8/8/8/R7 w - - 0 1", 4);
+        assert_eq!(mov, "a1a6");
+        assert_eq!(mate, Some(2));
+    }
+
+    #[test]
+    fn mate_in_three_test() {
+        let (mov, mate) = search_mate("r5rk/5p1p/5R2/4B3/8/8/7P/7K w - - 0 1", 6);
+        assert_eq!(mov, "f6a6");
+        assert_eq!(mate, Some(3));
+    }
+
+    #[test]
+    fn threaded_search_test() {
+        let mut kelp = Kelp::new();
+        kelp.threads = 4;
+        kelp.handle_position(&["fen", "kbK5/pp6/1P6/8/8/8/8/R7 w - - 0 1"]);
+        let res = kelp.search_move_lib(4);
+        assert_eq!(res.best_move.unwrap().to_string(), "a1a6");
+        assert_eq!(res.score.mate_moves(), Some(2));
+    }
+
+    struct TbHitsObserver(Vec<usize>);
+
+    impl SearchObserver for TbHitsObserver {
+        fn on_iteration(&mut self, info: &SearchMoveResultExtended) {
+            self.0.push(info.tb_hits);
+        }
+    }
+
+    #[test]
+    fn tb_hits_test() {
+        let tables = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/syzygy");
+        let mut kelp = Kelp::new();
+        kelp.threads = 2;
+        kelp.search.tablebase = Some(Arc::new(Syzygy::open(tables).unwrap()));
+        kelp.handle_position(&["fen", "8/8/8/4k3/8/8/8/KQ6 w - - 0 1"]);
+
+        // the root moves are only probed once, later iterations still report them
+        let mut observer = TbHitsObserver(Vec::new());
+        kelp.search_move_observed(4, &mut observer);
+        assert_eq!(observer.0.len(), 4);
+        assert!(observer.0[0] > 0);
+        assert!(observer.0.windows(2).all(|w| w[0] <= w[1]));
+    }
+
+    #[test]
+    fn bench_deterministic_test() {
+        let mut kelp = Kelp::new();
+        let first = kelp.bench(3);
+        assert!(first.nodes > 0);
+
+        // leftover state from a previous search must not leak into the signature
+        kelp.handle_position(&["startpos", "moves", "e2e4", "e7e5"]);
+        kelp.search_move_lib(4);
+        let hash = kelp.board.hash;
+        let second = kelp.bench(3);
+        assert_eq!(first.nodes, second.nodes);
+        assert_eq!(kelp.board.hash, hash);
+    }
+
+    #[test]
+    fn analyse_test() {
+        let mut kelp = Kelp::new();
+        kelp.handle_position(&["fen", "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1"]);
+        let moves = kelp.analyse(3);
+
+        assert_eq!(moves.len(), 17);
+        assert_eq!(moves[0].mov.to_string(), "a1a8");
+        assert_eq!(moves[0].score.mate_moves(), Some(1));
+        assert!(moves.iter().all(|m| m.bound == Bound::Exact && m.depth == 3));
+        assert!(moves.windows(2).all(|w| w[0].score >= w[1].score));
+        assert!(moves[1..].iter().all(|m| !m.score.is_mate()));
+        assert_eq!(moves[0].pv[0], moves[0].mov);
+    }
+
+    #[derive(Default)]
+    struct RecordingObserver {
+        depths: Vec<usize>,
+        best_moves: Vec<Move>,
+        currmoves: usize,
+        finished: usize,
+    }
+
+    impl SearchObserver for RecordingObserver {
+        fn on_iteration(&mut self, info: &SearchMoveResultExtended) {
+            assert_eq!(info.pv.first().copied(), info.best_move);
+            self.depths.push(info.depth);
+        }
+
+        fn on_best_move(&mut self, mov: Move, _score: Score, _depth: usize) {
+            self.best_moves.push(mov);
+        }
+
+        fn on_currmove(&mut self, _mov: Move, number: usize, _depth: usize) {
+            assert!(number >= 1);
+            self.currmoves += 1;
+        }
+
+        fn on_finish(&mut self, _result: &SearchMoveResult) {
+            self.finished += 1;
+        }
+    }
+
+    #[test]
+    fn observer_test() {
+        let mut kelp = Kelp::new();
+        kelp.handle_position(&["startpos"]);
+
+        let mut observer = RecordingObserver::default();
+        let res = kelp.search_move_observed(4, &mut observer);
+
+        assert_eq!(observer.depths, vec![1, 2, 3, 4]);
+        assert_eq!(observer.finished, 1);
+        // every legal move is searched at least once per iteration
+        assert!(observer.currmoves >= 20 * 4);
+        assert!(observer.best_moves.contains(&res.best_move.unwrap()));
+        assert_eq!(res.pv.first().copied(), res.best_move);
+    }
+
+    #[test]
+    fn mated_in_test() {
+        // black to move after Ra6 in the mate in two above, every reply allows mate
+        let (_, mate) = search_mate("kbK5/pp6/RP6/8/8/8/8/8 b - - 0 1", 3);
+        assert_eq!(mate, Some(-1));
+    }
+
+    #[test]
+    fn long_game_test() {
+        let mut args = vec!["startpos", "moves", "e2e4", "e7e5"];
+        for _ in 0..150 {
+            args.extend(["g1f3", "b8c6", "f3g1", "c6b8"]);
+        }
+
+        let mut kelp = Kelp::new();
+        kelp.handle_position(&args);
+        assert_eq!(kelp.move_history.len(), 602);
+        assert_eq!(
+            kelp.get_fen(),
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 600 302"
+        );
+        let board = Board::parse(Fen(kelp.get_fen())).unwrap();
+        assert_eq!(board.to_fen(), kelp.get_fen());
+
+        args.extend(["d2d4", "d7d5"]);
+        kelp.handle_position(&args);
+        assert_eq!(kelp.move_history.len(), 604);
+        assert_eq!(kelp.board.info.get_halfmove_clock(), 0);
+        assert!(kelp.search_move_lib(4).best_move.is_some());
+
+        while !kelp.move_history.is_empty() {
+            kelp.unmake_move();
+        }
+        assert_eq!(kelp.board, Board::default());
+    }
+}
@@ -9,59 +9,59 @@ pub struct BitBoard(pub u64);
 
 impl BitBoard {
     #[inline(always)]
-    pub fn get_bit(&self, index: u8) -> bool {
+    pub const fn get_bit(&self, index: u8) -> bool {
         ((self.0 >> index) & 1) == 1
     }
 
     #[inline(always)]
-    pub fn set_bit(&mut self, index: u8) {
+    pub const fn set_bit(&mut self, index: u8) {
         self.0 |= 1 << index;
     }
 
     #[inline(always)]
-    pub fn clear_bit(&mut self, index: u8) {
+    pub const fn clear_bit(&mut self, index: u8) {
         self.0 &= !(1 << index);
     }
 
     #[inline(always)]
-    pub fn toggle_bit(&mut self, index: u8) {
+    pub const fn toggle_bit(&mut self, index: u8) {
         self.0 ^= 1 << index;
     }
 
     #[inline(always)]
-    pub fn count_bits(&self) -> u8 {
+    pub const fn count_bits(&self) -> u8 {
         self.0.count_ones() as u8
     }
 
     #[inline(always)]
-    pub fn get_lsb(&self) -> u8 {
+    pub const fn get_lsb(&self) -> u8 {
         self.0.trailing_zeros() as u8
     }
 
     #[inline(always)]
-    pub fn get_msb(&self) -> u8 {
+    pub const fn get_msb(&self) -> u8 {
         self.0.leading_zeros() as u8
     }
 
     #[inline(always)]
-    pub fn pop_lsb(&mut self) -> u8 {
+    pub const fn pop_lsb(&mut self) -> u8 {
         let lsb = self.get_lsb();
         self.clear_bit(lsb);
         lsb
     }
     #[inline(always)]
-    pub fn pop_msb(&mut self) -> u8 {
+    pub const fn pop_msb(&mut self) -> u8 {
         let msb = self.get_msb();
         self.clear_bit(msb);
         msb
     }
 
-    pub fn empty() -> BitBoard {
+    pub const fn empty() -> BitBoard {
         BitBoard(0)
     }
 
     #[inline(always)]
-    pub fn is_empty(&self) -> bool {
+    pub const fn is_empty(&self) -> bool {
         self.0 == 0
     }
 }
@@ -1,6 +1,8 @@
 use super::bitboard::BitBoard;
 use crate::kelp::board::piece::Color;
 
+// all generators are const fn so the lookup tables can be built at compile time
+
 const FILE_A: u64 = 0b00000001_00000001_00000001_00000001_00000001_00000001_00000001_00000001;
 const FILE_B: u64 = 0b00000010_00000010_00000010_00000010_00000010_00000010_00000010_00000010;
 const FILE_G: u64 = 0b01000000_01000000_01000000_01000000_01000000_01000000_01000000_01000000;
@@ -12,224 +14,122 @@ const NOT_FILE_H: u64 = !FILE_H;
 const NOT_FILE_AB: u64 = !FILE_AB;
 const NOT_FILE_GH: u64 = !FILE_GH;
 
-pub fn generate_pawn_attacks(color: Color, square: usize) -> BitBoard {
-    let mut attacks = BitBoard(0);
-    let mut square_bb = BitBoard(0);
-    square_bb.set_bit(square as u8);
-
-    if color == Color::Black {
-        attacks |= BitBoard((square_bb.0 >> 9) & NOT_FILE_H);
-        attacks |= BitBoard((square_bb.0 >> 7) & NOT_FILE_A);
-    } else {
-        attacks |= BitBoard((square_bb.0 << 9) & NOT_FILE_A);
-        attacks |= BitBoard((square_bb.0 << 7) & NOT_FILE_H);
-    }
-    attacks
-}
-
-pub fn generate_king_attacks(square: usize) -> BitBoard {
-    let mut attacks = BitBoard(0);
-    let mut square_bb = BitBoard(0);
-    square_bb.set_bit(square as u8);
-
-    attacks |= BitBoard((square_bb.0 >> 1) & NOT_FILE_H);
-    attacks |= BitBoard((square_bb.0 << 1) & NOT_FILE_A);
-    attacks |= BitBoard(square_bb.0 >> 8);
-    attacks |= BitBoard(square_bb.0 << 8);
-    attacks |= BitBoard((square_bb.0 >> 9) & NOT_FILE_H);
-    attacks |= BitBoard((square_bb.0 << 9) & NOT_FILE_A);
-    attacks |= BitBoard((square_bb.0 >> 7) & NOT_FILE_A);
-    attacks |= BitBoard((square_bb.0 << 7) & NOT_FILE_H);
-
-    attacks
-}
-
-pub fn generate_knight_attacks(square: usize) -> BitBoard {
-    let mut attacks = BitBoard(0);
-    let mut square_bb = BitBoard(0);
-    square_bb.set_bit(square as u8);
-
-    // 17, 15, 10, 6, -6, -10, -15, -17
-    if ((square_bb.0 >> 17) & NOT_FILE_H) != 0 {
-        attacks |= BitBoard(square_bb.0 >> 17);
-    }
-    if ((square_bb.0 >> 15) & NOT_FILE_A) != 0 {
-        attacks |= BitBoard(square_bb.0 >> 15);
-    }
+pub const fn generate_pawn_attacks(color: Color, square: usize) -> BitBoard {
+    let square_bb = 1u64 << square;
 
-    if ((square_bb.0 >> 10) & NOT_FILE_GH) != 0 {
-        attacks |= BitBoard(square_bb.0 >> 10);
+    match color {
+        Color::Black => BitBoard(((square_bb >> 9) & NOT_FILE_H) | ((square_bb >> 7) & NOT_FILE_A)),
+        Color::White => BitBoard(((square_bb << 9) & NOT_FILE_A) | ((square_bb << 7) & NOT_FILE_H)),
     }
-    if ((square_bb.0 >> 6) & NOT_FILE_AB) != 0 {
-        attacks |= BitBoard(square_bb.0 >> 6);
-    }
-
-    if ((square_bb.0 << 17) & NOT_FILE_A) != 0 {
-        attacks |= BitBoard(square_bb.0 << 17);
-    }
-    if ((square_bb.0 << 15) & NOT_FILE_H) != 0 {
-        attacks |= BitBoard(square_bb.0 << 15);
-    }
-    if ((square_bb.0 << 10) & NOT_FILE_AB) != 0 {
-        attacks |= BitBoard(square_bb.0 << 10);
-    }
-    if ((square_bb.0 << 6) & NOT_FILE_GH) != 0 {
-        attacks |= BitBoard(square_bb.0 << 6);
-    }
-
-    attacks
 }
 
-pub fn generate_bishop_mask(square: usize) -> BitBoard {
-    let mut mask = BitBoard(0);
-    let (t_rank, t_file): (usize, usize) = (square / 8, square % 8);
+pub const fn generate_king_attacks(square: usize) -> BitBoard {
+    let square_bb = 1u64 << square;
+    let mut attacks = 0;
 
-    for (rank, file) in (t_rank + 1..7).zip(t_file + 1..7) {
-        mask |= BitBoard(1u64 << (rank * 8 + file));
-    }
-    for (rank, file) in (t_rank + 1..7).zip((1..t_file).rev()) {
-        mask |= BitBoard(1u64 << (rank * 8 + file));
-    }
-    for (rank, file) in (1..t_rank).rev().zip(t_file + 1..7) {
-        mask |= BitBoard(1u64 << (rank * 8 + file));
-    }
-    for (rank, file) in (1..t_rank).rev().zip((1..t_file).rev()) {
-        mask |= BitBoard(1u64 << (rank * 8 + file));
-    }
+    attacks |= (square_bb >> 1) & NOT_FILE_H;
+    attacks |= (square_bb << 1) & NOT_FILE_A;
+    attacks |= square_bb >> 8;
+    attacks |= square_bb << 8;
+    attacks |= (square_bb >> 9) & NOT_FILE_H;
+    attacks |= (square_bb << 9) & NOT_FILE_A;
+    attacks |= (square_bb >> 7) & NOT_FILE_A;
+    attacks |= (square_bb << 7) & NOT_FILE_H;
 
-    mask
+    BitBoard(attacks)
 }
 
-pub fn generate_rook_mask(square: usize) -> BitBoard {
-    let mut mask = BitBoard(0);
-    let (t_rank, t_file): (usize, usize) = (square / 8, square % 8);
-
-    for rank in (t_rank + 1)..7 {
-        mask |= BitBoard(1u64 << (rank * 8 + t_file));
-    }
-    for rank in (1..=t_rank).rev() {
-        mask |= BitBoard(1u64 << (rank * 8 + t_file));
-    }
-    for file in (t_file + 1)..7 {
-        mask |= BitBoard(1u64 << (t_rank * 8 + file));
-    }
-    for file in (1..=t_file).rev() {
-        mask |= BitBoard(1u64 << (t_rank * 8 + file));
-    }
+pub const fn generate_knight_attacks(square: usize) -> BitBoard {
+    let square_bb = 1u64 << square;
+    let mut attacks = 0;
 
-    mask.clear_bit(square as u8);
-    mask
+    // 17, 15, 10, 6, -6, -10, -15, -17
+    attacks |= (square_bb >> 17) & NOT_FILE_H;
+    attacks |= (square_bb >> 15) & NOT_FILE_A;
+    attacks |= (square_bb >> 10) & NOT_FILE_GH;
+    attacks |= (square_bb >> 6) & NOT_FILE_AB;
+    attacks |= (square_bb << 17) & NOT_FILE_A;
+    attacks |= (square_bb << 15) & NOT_FILE_H;
+    attacks |= (square_bb << 10) & NOT_FILE_AB;
+    attacks |= (square_bb << 6) & NOT_FILE_GH;
+
+    BitBoard(attacks)
 }
 
-pub fn generate_bishop_attacks(square: usize, blockers: BitBoard) -> BitBoard {
-    let mut attacks = BitBoard(0);
-    let (mut t_rank, mut t_file): (usize, usize) = (square / 8, square % 8);
-
-    // Top-right direction
-    while t_rank < 7 && t_file < 7 {
-        t_rank += 1;
-        t_file += 1;
-        attacks |= BitBoard(1u64 << (t_rank * 8 + t_file));
-        if blockers.get_bit((t_rank * 8 + t_file) as u8) {
-            break;
-        }
-    }
-
-    (t_rank, t_file) = (square / 8, square % 8);
-
-    // Top-left direction
-    while t_rank < 7 && t_file > 0 {
-        t_rank += 1;
-        t_file -= 1;
-        attacks |= BitBoard(1u64 << (t_rank * 8 + t_file));
-        if blockers.get_bit((t_rank * 8 + t_file) as u8) {
+// squares reached from `square` stepping (d_rank, d_file), stopping after the first blocker.
+// with `mask` set the edge squares are left out, as they never change what a slider attacks
+const fn ray(square: usize, d_rank: i32, d_file: i32, blockers: u64, mask: bool) -> u64 {
+    let mut attacks = 0;
+    let mut rank = (square / 8) as i32 + d_rank;
+    let mut file = (square % 8) as i32 + d_file;
+
+    while rank >= 0 && rank < 8 && file >= 0 && file < 8 {
+        if mask
+            && ((d_rank != 0 && (rank == 0 || rank == 7))
+                || (d_file != 0 && (file == 0 || file == 7)))
+        {
             break;
         }
-    }
 
-    (t_rank, t_file) = (square / 8, square % 8);
-    // Bottom-right direction
-    while t_rank > 0 && t_file < 7 {
-        t_rank -= 1;
-        t_file += 1;
-        attacks |= BitBoard(1u64 << (t_rank * 8 + t_file));
-        if blockers.get_bit((t_rank * 8 + t_file) as u8) {
+        let bit = 1u64 << (rank * 8 + file);
+        attacks |= bit;
+        if blockers & bit != 0 {
             break;
         }
+        rank += d_rank;
+        file += d_file;
     }
 
-    (t_rank, t_file) = (square / 8, square % 8);
-    // Bottom-left direction
-    while t_rank > 0 && t_file > 0 {
-        t_rank -= 1;
-        t_file -= 1;
-        attacks |= BitBoard(1u64 << (t_rank * 8 + t_file));
-        if blockers.get_bit((t_rank * 8 + t_file) as u8) {
-            break;
-        }
-    }
-
-    attacks.clear_bit(square as u8);
     attacks
 }
 
-pub fn generate_rook_attacks(square: usize, blockers: BitBoard) -> BitBoard {
-    let mut attacks = BitBoard(0);
-    let (mut t_rank, mut t_file): (usize, usize) = (square / 8, square % 8);
-
-    // Top direction
-    while t_rank < 7 {
-        t_rank += 1;
-        attacks |= BitBoard(1u64 << (t_rank * 8 + t_file));
-        if blockers.get_bit((t_rank * 8 + t_file) as u8) {
-            break;
-        }
-    }
-
-    (t_rank, t_file) = (square / 8, square % 8);
-    // Bottom direction
-    while t_rank > 0 {
-        t_rank -= 1;
-        attacks |= BitBoard(1u64 << (t_rank * 8 + t_file));
-        if blockers.get_bit((t_rank * 8 + t_file) as u8) {
-            break;
-        }
-    }
+pub const fn generate_bishop_mask(square: usize) -> BitBoard {
+    BitBoard(
+        ray(square, 1, 1, 0, true)
+            | ray(square, 1, -1, 0, true)
+            | ray(square, -1, 1, 0, true)
+            | ray(square, -1, -1, 0, true),
+    )
+}
 
-    (t_rank, t_file) = (square / 8, square % 8);
-    // Right direction
-    while t_file < 7 {
-        t_file += 1;
-        attacks |= BitBoard(1u64 << (t_rank * 8 + t_file));
-        if blockers.get_bit((t_rank * 8 + t_file) as u8) {
-            break;
-        }
-    }
+pub const fn generate_rook_mask(square: usize) -> BitBoard {
+    BitBoard(
+        ray(square, 1, 0, 0, true)
+            | ray(square, -1, 0, 0, true)
+            | ray(square, 0, 1, 0, true)
+            | ray(square, 0, -1, 0, true),
+    )
+}
 
-    (t_rank, t_file) = (square / 8, square % 8);
-    // Left direction
-    while t_file > 0 {
-        t_file -= 1;
-        attacks |= BitBoard(1u64 << (t_rank * 8 + t_file));
-        if blockers.get_bit((t_rank * 8 + t_file) as u8) {
-            break;
-        }
-    }
+pub const fn generate_bishop_attacks(square: usize, blockers: BitBoard) -> BitBoard {
+    BitBoard(
+        ray(square, 1, 1, blockers.0, false)
+            | ray(square, 1, -1, blockers.0, false)
+            | ray(square, -1, 1, blockers.0, false)
+            | ray(square, -1, -1, blockers.0, false),
+    )
+}
 
-    attacks.clear_bit(square as u8);
-    attacks
+pub const fn generate_rook_attacks(square: usize, blockers: BitBoard) -> BitBoard {
+    BitBoard(
+        ray(square, 1, 0, blockers.0, false)
+            | ray(square, -1, 0, blockers.0, false)
+            | ray(square, 0, 1, blockers.0, false)
+            | ray(square, 0, -1, blockers.0, false),
+    )
 }
 
-pub fn set_occupancy(index: u32, bits_in_mask: u8, mask: BitBoard) -> BitBoard {
+pub const fn set_occupancy(index: u32, bits_in_mask: u8, mask: BitBoard) -> BitBoard {
     let mut occupancy = BitBoard(0);
     let mut temp = mask;
 
-    for bit in 0..bits_in_mask {
-        let square = temp.get_lsb();
-        temp.clear_bit(square);
+    let mut bit = 0;
+    while bit < bits_in_mask {
+        let square = temp.pop_lsb();
         if (index & (1 << bit)) != 0 {
             occupancy.set_bit(square);
         }
+        bit += 1;
     }
 
     occupancy
@@ -1,121 +1,255 @@
 use super::bitboard::BitBoard;
 use super::generate_attacks::*;
 use super::magics::*;
-use super::{BISHOP_RELEVANT_BITS, ROOK_RELEVANT_BITS};
 use crate::kelp::board::piece::Color;
-use log::info;
 
-pub struct LookupTable {
-    pawn_attacks: Vec<Vec<BitBoard>>,
-    knight_attacks: Vec<BitBoard>,
-    king_attacks: Vec<BitBoard>,
+// every table is generated at compile time, slider attacks of all squares share one flat table
+// indexed by `Magic::index`
+
+static PAWN_ATTACKS: [[BitBoard; 64]; 2] = [
+    generate_leaper_table(Leaper::WhitePawn),
+    generate_leaper_table(Leaper::BlackPawn),
+];
+static KNIGHT_ATTACKS: [BitBoard; 64] = generate_leaper_table(Leaper::Knight);
+static KING_ATTACKS: [BitBoard; 64] = generate_leaper_table(Leaper::King);
+
+static BISHOP_ATTACKS: [BitBoard; BISHOP_TABLE_SIZE] = generate_slider_table(false);
+// ~100k rook entries trip the const eval lint, it only costs a few seconds per build
+#[allow(long_running_const_eval)]
+static ROOK_ATTACKS: [BitBoard; ROOK_TABLE_SIZE] = generate_slider_table(true);
+
+// squares strictly between two aligned squares, and the full line through them, empty if not aligned
+static BETWEEN: [[BitBoard; 64]; 64] = generate_square_table(false);
+static LINE: [[BitBoard; 64]; 64] = generate_square_table(true);
+
+#[derive(Clone, Copy)]
+enum Leaper {
+    WhitePawn,
+    BlackPawn,
+    Knight,
+    King,
+}
 
-    bishop_attacks: Vec<Vec<BitBoard>>, // 64 * 512
-    rook_attacks: Vec<Vec<BitBoard>>, // 64 * 4096
+const fn generate_leaper_table(leaper: Leaper) -> [BitBoard; 64] {
+    let mut table = [BitBoard(0); 64];
+    let mut sq = 0;
+    while sq < 64 {
+        table[sq] = match leaper {
+            Leaper::WhitePawn => generate_pawn_attacks(Color::White, sq),
+            Leaper::BlackPawn => generate_pawn_attacks(Color::Black, sq),
+            Leaper::Knight => generate_knight_attacks(sq),
+            Leaper::King => generate_king_attacks(sq),
+        };
+        sq += 1;
+    }
+    table
+}
 
-    magic_table: MagicTable,
+// attacks along one line through `square` (hyperbola quintessence), a lot cheaper to
+// const evaluate than walking rays for every occupancy
+const fn line_attacks(square: usize, line: u64, occupancy: u64) -> u64 {
+    let slider = 1u64 << square;
+    let occ = occupancy & line;
+    let forward = occ.wrapping_sub(slider.wrapping_mul(2));
+    let reverse = occ
+        .reverse_bits()
+        .wrapping_sub(slider.reverse_bits().wrapping_mul(2))
+        .reverse_bits();
+    (forward ^ reverse) & line
 }
 
-impl Default for LookupTable {
-    fn default() -> Self {
-        Self::new()
-    }
+// lines through `square`, without the square itself
+const fn file_line(square: usize) -> u64 {
+    (0x0101_0101_0101_0101 << (square % 8)) & !(1 << square)
 }
 
-impl LookupTable {
-    pub fn populate(&mut self) {
-        self.magic_table.generate_magic_table();
-        self.init_leaper_pieces();
-        self.init_slider_pieces();
-        info!("Lookup table populated");
-    }
+const fn rank_line(square: usize) -> u64 {
+    (0xFF << (square / 8 * 8)) & !(1 << square)
+}
 
-    fn init_leaper_pieces(&mut self) {
-        for i in 0..64 {
-            self.pawn_attacks[Color::White as usize][i] = generate_pawn_attacks(Color::White, i);
-            self.pawn_attacks[Color::Black as usize][i] = generate_pawn_attacks(Color::Black, i);
-            self.knight_attacks[i] = generate_knight_attacks(i);
-            self.king_attacks[i] = generate_king_attacks(i);
-        }
-        info!("Leaper pieces initialized")
-    }
+const fn diagonal_line(square: usize) -> u64 {
+    let diff = (square / 8) as i32 - (square % 8) as i32;
+    let line: u64 = 0x8040_2010_0804_0201;
+    let line = if diff >= 0 {
+        line << (diff * 8)
+    } else {
+        line >> (-diff * 8)
+    };
+    line & !(1 << square)
+}
 
-    fn init_slider_pieces(&mut self) {
-        let magic_table = self.magic_table;
+const fn anti_diagonal_line(square: usize) -> u64 {
+    let diff = (square / 8 + square % 8) as i32 - 7;
+    let line: u64 = 0x0102_0408_1020_4080;
+    let line = if diff >= 0 {
+        line << (diff * 8)
+    } else {
+        line >> (-diff * 8)
+    };
+    line & !(1 << square)
+}
 
-        // Bishop
-        for sq in 0..64 {
-            let occ_idx = (1 << BISHOP_RELEVANT_BITS[sq]) as u32;
-            for idx in 0..occ_idx {
-                let magic = magic_table.bishop[sq];
-                let occupancy = set_occupancy(idx, BISHOP_RELEVANT_BITS[sq], magic.mask);
-                let magic_index = ((occupancy * magic.magic) >> magic.shift).0 as u16;
-                self.bishop_attacks[sq][magic_index as usize] =
-                    generate_bishop_attacks(sq, occupancy);
+const fn generate_slider_table<const N: usize>(rook: bool) -> [BitBoard; N] {
+    let mut table = [BitBoard(0); N];
+    let mut sq = 0;
+    while sq < 64 {
+        let magic = if rook {
+            ROOK_MAGIC_TABLE[sq]
+        } else {
+            BISHOP_MAGIC_TABLE[sq]
+        };
+        let (line_a, line_b) = if rook {
+            (file_line(sq), rank_line(sq))
+        } else {
+            (diagonal_line(sq), anti_diagonal_line(sq))
+        };
+
+        // every subset of the mask, carry rippler
+        let mut occupancy = 0u64;
+        loop {
+            table[magic.index(BitBoard(occupancy))] =
+                BitBoard(line_attacks(sq, line_a, occupancy) | line_attacks(sq, line_b, occupancy));
+            occupancy = occupancy.wrapping_sub(magic.mask.0) & magic.mask.0;
+            if occupancy == 0 {
+                break;
             }
         }
+        sq += 1;
+    }
+    table
+}
 
-        // Rook
-        for sq in 0..64 {
-            let occ_idx = (1 << ROOK_RELEVANT_BITS[sq]) as u32;
-            for idx in 0..occ_idx {
-                let magic = magic_table.rook[sq];
-                let occupancy = set_occupancy(idx, ROOK_RELEVANT_BITS[sq], magic.mask);
-                let magic_index = ((occupancy * magic.magic) >> magic.shift).0 as u16;
-                self.rook_attacks[sq][magic_index as usize] = generate_rook_attacks(sq, occupancy);
-            }
+const fn generate_square_table(full_line: bool) -> [[BitBoard; 64]; 64] {
+    let mut table = [[BitBoard(0); 64]; 64];
+    let mut a = 0;
+    while a < 64 {
+        let mut b = 0;
+        while b < 64 {
+            let bit_b = 1u64 << b;
+            let line = if a == b {
+                0
+            } else if file_line(a) & bit_b != 0 {
+                file_line(a)
+            } else if rank_line(a) & bit_b != 0 {
+                rank_line(a)
+            } else if diagonal_line(a) & bit_b != 0 {
+                diagonal_line(a)
+            } else if anti_diagonal_line(a) & bit_b != 0 {
+                anti_diagonal_line(a)
+            } else {
+                0
+            };
+
+            table[a][b] = if line == 0 {
+                BitBoard(0)
+            } else if full_line {
+                BitBoard(line | 1 << a)
+            } else {
+                let (low, high) = if a < b { (a, b) } else { (b, a) };
+                // bits above `low` and below `high`
+                BitBoard(line & ((1u64 << high) - (1u64 << low)) & !(1u64 << low))
+            };
+            b += 1;
         }
-        info!("Slider pieces initialized")
+        a += 1;
     }
+    table
+}
 
-    pub fn new() -> LookupTable {
-        LookupTable {
-            pawn_attacks: vec![vec![BitBoard(0); 64]; 2],
-            knight_attacks: vec![BitBoard(0); 64],
-            king_attacks: vec![BitBoard(0); 64],
-            bishop_attacks: vec![vec![BitBoard(0); 512]; 64],
-            rook_attacks: vec![vec![BitBoard(0); 4096]; 64],
-            magic_table: MagicTable::new(),
-        }
+/// Attack lookups, backed by static tables so there is nothing to populate
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LookupTable;
+
+static LOOKUP_TABLE: LookupTable = LookupTable;
+
+impl LookupTable {
+    /// table shared by every move generator
+    pub fn global() -> &'static LookupTable {
+        &LOOKUP_TABLE
     }
 
     #[inline(always)]
     pub fn get_pawn_attacks(&self, color: Color, square: u8) -> BitBoard {
-        self.pawn_attacks[color as usize][square as usize]
+        PAWN_ATTACKS[color as usize][square as usize]
     }
 
     #[inline(always)]
     pub fn get_knight_attacks(&self, square: u8) -> BitBoard {
-        self.knight_attacks[square as usize]
+        KNIGHT_ATTACKS[square as usize]
     }
 
     #[inline(always)]
     pub fn get_king_attacks(&self, square: u8) -> BitBoard {
-        self.king_attacks[square as usize]
+        KING_ATTACKS[square as usize]
     }
 
     #[inline(always)]
     pub fn get_bishop_attacks(&self, square: u8, occupancy: BitBoard) -> BitBoard {
-        let magic = self.magic_table.bishop[square as usize];
-        let mut occ = occupancy;
-        occ &= magic.mask;
-        occ *= magic.magic;
-        occ >>= magic.shift;
-        self.bishop_attacks[square as usize][occ.0 as usize]
+        BISHOP_ATTACKS[BISHOP_MAGIC_TABLE[square as usize].index(occupancy)]
     }
 
     #[inline(always)]
     pub fn get_rook_attacks(&self, square: u8, occupancy: BitBoard) -> BitBoard {
-        let magic = self.magic_table.rook[square as usize];
-        let mut occ = occupancy;
-        occ &= magic.mask;
-        occ *= magic.magic;
-        occ >>= magic.shift;
-        self.rook_attacks[square as usize][occ.0 as usize]
+        ROOK_ATTACKS[ROOK_MAGIC_TABLE[square as usize].index(occupancy)]
     }
 
     #[inline(always)]
     pub fn get_queen_attacks(&self, square: u8, occupancy: BitBoard) -> BitBoard {
         self.get_bishop_attacks(square, occupancy) | self.get_rook_attacks(square, occupancy)
     }
+
+    /// squares strictly between `a` and `b`, empty unless they share a rank, file or diagonal
+    #[inline(always)]
+    pub fn get_between(&self, a: u8, b: u8) -> BitBoard {
+        BETWEEN[a as usize][b as usize]
+    }
+
+    /// the whole rank, file or diagonal through `a` and `b`, empty if there is none
+    #[inline(always)]
+    pub fn get_line(&self, a: u8, b: u8) -> BitBoard {
+        LINE[a as usize][b as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slider_table_test() {
+        let table = LookupTable::global();
+        let mut rng = 0x9E37_79B9_7F4A_7C15u64;
+
+        for sq in 0..64 {
+            for _ in 0..64 {
+                // xorshift, sparse occupancies hit more distinct table entries
+                rng ^= rng << 13;
+                rng ^= rng >> 7;
+                rng ^= rng << 17;
+                let occupancy = BitBoard(rng & rng.rotate_left(17) & rng.rotate_left(31));
+
+                assert_eq!(
+                    table.get_rook_attacks(sq as u8, occupancy),
+                    generate_rook_attacks(sq, occupancy)
+                );
+                assert_eq!(
+                    table.get_bishop_attacks(sq as u8, occupancy),
+                    generate_bishop_attacks(sq, occupancy)
+                );
+            }
+        }
+
+        assert_eq!(ROOK_TABLE_SIZE, 102400);
+        assert_eq!(BISHOP_TABLE_SIZE, 5248);
+        assert_eq!(table.get_knight_attacks(0), BitBoard(0x20400));
+        assert_eq!(table.get_king_attacks(63).count_bits(), 3);
+        assert_eq!(table.get_pawn_attacks(Color::White, 8), BitBoard(0x20000));
+
+        // a1 to h8, d3 to d1, b1 to c3
+        assert_eq!(table.get_between(0, 63), BitBoard(0x0040_2010_0804_0200));
+        assert_eq!(table.get_between(19, 3), BitBoard(1 << 11));
+        assert_eq!(table.get_between(1, 18), BitBoard(0));
+        assert_eq!(table.get_line(3, 35), BitBoard(0x0808_0808_0808_0808));
+        assert_eq!(table.get_line(1, 18), BitBoard(0));
+    }
 }
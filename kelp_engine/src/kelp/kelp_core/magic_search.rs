@@ -0,0 +1,211 @@
+use super::bitboard::BitBoard;
+use super::generate_attacks::*;
+use super::{BISHOP_RELEVANT_BITS, ROOK_RELEVANT_BITS};
+use rand_chacha::rand_core::{RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::fmt::Write;
+
+// runtime search for the numbers in `magics.rs`, used by the `kelp_magics` tool
+
+/// Magic numbers of one slider with the number of index bits each square uses
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MagicSet {
+    pub magics: [u64; 64],
+    pub bits: [u8; 64],
+}
+
+impl MagicSet {
+    /// entries the attack table needs for this set
+    pub fn table_size(&self) -> usize {
+        self.bits.iter().map(|&b| 1usize << b).sum()
+    }
+}
+
+fn relevant_mask(square: usize, rook: bool) -> BitBoard {
+    if rook {
+        generate_rook_mask(square)
+    } else {
+        generate_bishop_mask(square)
+    }
+}
+
+// every occupancy of the relevant squares with the attacks it produces, from the slow generators
+fn occupancies(square: usize, rook: bool) -> (BitBoard, Vec<(u64, u64)>) {
+    let mask = relevant_mask(square, rook);
+    let count = mask.count_bits();
+
+    let entries = (0..1u32 << count)
+        .map(|index| {
+            let occupancy = set_occupancy(index, count, mask);
+            let attacks = if rook {
+                generate_rook_attacks(square, occupancy)
+            } else {
+                generate_bishop_attacks(square, occupancy)
+            };
+            (occupancy.0, attacks.0)
+        })
+        .collect();
+
+    (mask, entries)
+}
+
+// true if no two different attack sets land on the same index, `table` is scratch space
+fn fits(entries: &[(u64, u64)], magic: u64, bits: u8, table: &mut Vec<u64>) -> bool {
+    table.clear();
+    table.resize(1 << bits, 0);
+    let shift = 64 - bits;
+
+    for &(occupancy, attacks) in entries {
+        let index = (occupancy.wrapping_mul(magic) >> shift) as usize;
+        // attacks are never empty, so 0 marks a free entry
+        if table[index] == 0 {
+            table[index] = attacks;
+        } else if table[index] != attacks {
+            return false;
+        }
+    }
+    true
+}
+
+/// checks that `magic` indexes every occupancy of `square` into `1 << bits` entries without
+/// two different attack sets sharing one
+pub fn verify_magic(square: usize, rook: bool, magic: u64, bits: u8) -> bool {
+    let (_, entries) = occupancies(square, rook);
+    fits(&entries, magic, bits, &mut Vec::new())
+}
+
+fn search(
+    mask: BitBoard,
+    entries: &[(u64, u64)],
+    bits: u8,
+    tries: usize,
+    rng: &mut ChaCha8Rng,
+    table: &mut Vec<u64>,
+) -> Option<u64> {
+    for _ in 0..tries {
+        // sparse candidates work far better than uniform ones
+        let magic = rng.next_u64() & rng.next_u64() & rng.next_u64();
+        if (mask.0.wrapping_mul(magic) >> 56).count_ones() < 6 {
+            continue;
+        }
+        if fits(entries, magic, bits, table) {
+            return Some(magic);
+        }
+    }
+    None
+}
+
+/// searches a magic for `square` that indexes into `1 << bits` entries, gives up after `tries`
+/// candidates
+pub fn find_magic(square: usize, rook: bool, bits: u8, tries: usize, seed: u64) -> Option<u64> {
+    let (mask, entries) = occupancies(square, rook);
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    search(mask, &entries, bits, tries, &mut rng, &mut Vec::new())
+}
+
+/// searches magics for every square. squares first get a magic using every relevant bit, then
+/// up to `reduce` bits less are tried, `tries` candidates per size.
+/// `None` if some square found no magic even at full size
+pub fn find_magics(rook: bool, reduce: u8, tries: usize, seed: u64) -> Option<MagicSet> {
+    let relevant_bits = if rook {
+        &ROOK_RELEVANT_BITS
+    } else {
+        &BISHOP_RELEVANT_BITS
+    };
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    let mut table = Vec::new();
+    let mut set = MagicSet {
+        magics: [0; 64],
+        bits: *relevant_bits,
+    };
+
+    for (sq, &bits) in relevant_bits.iter().enumerate() {
+        let (mask, entries) = occupancies(sq, rook);
+        set.magics[sq] = search(mask, &entries, bits, tries, &mut rng, &mut table)?;
+
+        for smaller in (bits.saturating_sub(reduce)..bits).rev() {
+            match search(mask, &entries, smaller, tries, &mut rng, &mut table) {
+                Some(magic) => {
+                    set.magics[sq] = magic;
+                    set.bits[sq] = smaller;
+                }
+                None => break,
+            }
+        }
+    }
+
+    Some(set)
+}
+
+fn write_array<T: std::fmt::Display>(out: &mut String, name: &str, ty: &str, values: &[T]) {
+    writeln!(out, "pub const {}: [{}; 64] = [", name, ty).unwrap();
+    for value in values {
+        writeln!(out, "    {},", value).unwrap();
+    }
+    writeln!(out, "];").unwrap();
+}
+
+/// rust source for the magic and relevant bit constants, in the layout of `magics.rs` and
+/// `kelp_core/mod.rs`
+pub fn magics_to_rust(rook: &MagicSet, bishop: &MagicSet) -> String {
+    let hex = |set: &MagicSet| {
+        set.magics
+            .iter()
+            .map(|m| format!("{:#x}", m))
+            .collect::<Vec<_>>()
+    };
+
+    let mut out = String::new();
+    write_array(&mut out, "ROOK_MAGICS", "u64", &hex(rook));
+    out.push('\n');
+    write_array(&mut out, "BISHOP_MAGICS", "u64", &hex(bishop));
+    out.push('\n');
+    write_array(&mut out, "ROOK_RELEVANT_BITS", "u8", &rook.bits);
+    out.push('\n');
+    write_array(&mut out, "BISHOP_RELEVANT_BITS", "u8", &bishop.bits);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::magics::{BISHOP_MAGICS, ROOK_MAGICS};
+    use super::*;
+
+    #[test]
+    fn stored_magics_test() {
+        for sq in 0..64 {
+            assert!(
+                verify_magic(sq, true, ROOK_MAGICS[sq], ROOK_RELEVANT_BITS[sq]),
+                "rook magic of square {} collides",
+                sq
+            );
+            assert!(
+                verify_magic(sq, false, BISHOP_MAGICS[sq], BISHOP_RELEVANT_BITS[sq]),
+                "bishop magic of square {} collides",
+                sq
+            );
+        }
+    }
+
+    #[test]
+    fn find_magics_test() {
+        let bishop = find_magics(false, 0, 1_000_000, 1947).unwrap();
+        assert_eq!(find_magics(false, 0, 1_000_000, 1947), Some(bishop));
+        assert_eq!(bishop.bits, BISHOP_RELEVANT_BITS);
+        assert_eq!(bishop.table_size(), 5248);
+
+        for sq in 0..64 {
+            assert!(verify_magic(sq, false, bishop.magics[sq], bishop.bits[sq]));
+        }
+
+        let magic = find_magic(0, true, ROOK_RELEVANT_BITS[0], 1_000_000, 7).unwrap();
+        assert!(verify_magic(0, true, magic, ROOK_RELEVANT_BITS[0]));
+        // one bit less squeezes 4096 occupancies into 2048 entries, the stored magic collides
+        assert!(!verify_magic(
+            0,
+            true,
+            ROOK_MAGICS[0],
+            ROOK_RELEVANT_BITS[0] - 1
+        ));
+    }
+}
@@ -1,7 +1,6 @@
 use super::bitboard::BitBoard;
 use super::generate_attacks::{generate_bishop_mask, generate_rook_mask};
 use super::{BISHOP_RELEVANT_BITS, ROOK_RELEVANT_BITS};
-use log::info;
 
 pub const ROOK_MAGICS: [u64; 64] = [
     0x8a80104000800020,
@@ -142,43 +141,59 @@ pub struct Magic {
     pub mask: BitBoard,
     pub magic: BitBoard,
     pub shift: u8,
+    /// start of this square's attacks in the shared attack table
+    pub offset: usize,
 }
 
-#[derive(Copy, Clone)]
-pub struct MagicTable {
-    pub rook: [Magic; 64],
-    pub bishop: [Magic; 64],
+impl Magic {
+    /// index of the attacks for `occupancy` in the shared attack table
+    #[inline(always)]
+    pub const fn index(&self, occupancy: BitBoard) -> usize {
+        ((occupancy.0 & self.mask.0).wrapping_mul(self.magic.0) >> self.shift) as usize + self.offset
+    }
 }
 
-impl MagicTable {
-    pub fn new() -> MagicTable {
-        MagicTable {
-            rook: [Magic {
-                mask: BitBoard::empty(),
-                magic: BitBoard::empty(),
-                shift: 0,
-            }; 64],
-            bishop: [Magic {
-                mask: BitBoard::empty(),
-                magic: BitBoard::empty(),
-                shift: 0,
-            }; 64],
-        }
+const fn generate_magics(rook: bool) -> [Magic; 64] {
+    let mut magics = [Magic {
+        mask: BitBoard(0),
+        magic: BitBoard(0),
+        shift: 0,
+        offset: 0,
+    }; 64];
+
+    let mut offset = 0;
+    let mut sq = 0;
+    while sq < 64 {
+        let (mask, magic, bits) = if rook {
+            (generate_rook_mask(sq), ROOK_MAGICS[sq], ROOK_RELEVANT_BITS[sq])
+        } else {
+            (generate_bishop_mask(sq), BISHOP_MAGICS[sq], BISHOP_RELEVANT_BITS[sq])
+        };
+        magics[sq] = Magic {
+            mask,
+            magic: BitBoard(magic),
+            shift: 64 - bits,
+            offset,
+        };
+        offset += 1 << bits;
+        sq += 1;
     }
 
-    pub fn generate_magic_table(&mut self) {
-        for i in 0..64 {
-            self.rook[i] = Magic {
-                mask: generate_rook_mask(i),
-                magic: BitBoard::from(ROOK_MAGICS[i]),
-                shift: 64 - ROOK_RELEVANT_BITS[i],
-            };
-            self.bishop[i] = Magic {
-                mask: generate_bishop_mask(i),
-                magic: BitBoard::from(BISHOP_MAGICS[i]),
-                shift: 64 - BISHOP_RELEVANT_BITS[i],
-            };
-        }
-        info!("Magic tables generated");
+    magics
+}
+
+const fn table_size(relevant_bits: &[u8; 64]) -> usize {
+    let mut size = 0;
+    let mut sq = 0;
+    while sq < 64 {
+        size += 1 << relevant_bits[sq];
+        sq += 1;
     }
+    size
 }
+
+pub const ROOK_MAGIC_TABLE: [Magic; 64] = generate_magics(true);
+pub const BISHOP_MAGIC_TABLE: [Magic; 64] = generate_magics(false);
+
+pub const ROOK_TABLE_SIZE: usize = table_size(&ROOK_RELEVANT_BITS);
+pub const BISHOP_TABLE_SIZE: usize = table_size(&BISHOP_RELEVANT_BITS);
@@ -1,7 +1,8 @@
 pub mod bitboard;
-mod generate_attacks;
+pub mod generate_attacks;
 pub mod lookup_table;
-mod magics;
+pub mod magic_search;
+pub mod magics;
 
 pub const BISHOP_RELEVANT_BITS: [u8; 64] = [
     6, 5, 5, 5, 5, 5, 5, 6, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 7, 7, 7, 7, 5, 5, 5, 5, 7, 9, 9, 7, 5, 5,
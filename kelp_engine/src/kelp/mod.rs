@@ -1,8 +1,17 @@
+pub mod bench;
+pub mod bitbase;
 pub mod board;
+pub mod book;
+pub mod engine;
+pub mod epd;
+pub mod game;
 pub mod kelp;
 pub mod kelp_core;
 pub mod mov_gen;
+pub mod observer;
+pub mod pgn;
 pub mod search;
+pub mod syzygy;
 pub mod uci_trait;
 
 use std::fmt::{Debug, Display};
@@ -12,18 +21,29 @@ use strum_macros::{Display, EnumIter, EnumString, FromRepr};
 use board::moves::Castle;
 use board::piece::Color;
 use kelp_core::bitboard::BitBoard;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::thread;
 use std::time::Duration;
 pub static STOP: AtomicBool = AtomicBool::new(false);
+// bumped when a timed search ends so its timer can't stop a later search
+static STOP_GENERATION: AtomicU64 = AtomicU64::new(0);
 
+/// sets STOP after `duration` unless `cancel_stop_interval` is called first
 pub fn stop_interval(duration: Duration) {
+    let generation = STOP_GENERATION.load(Ordering::Relaxed);
     thread::spawn(move || {
         thread::sleep(duration);
-        STOP.store(true, Ordering::Relaxed);
+        if STOP_GENERATION.load(Ordering::Relaxed) == generation {
+            STOP.store(true, Ordering::Relaxed);
+        }
     });
 }
 
+/// cancels every pending `stop_interval`
+pub fn cancel_stop_interval() {
+    STOP_GENERATION.fetch_add(1, Ordering::Relaxed);
+}
+
 pub type BitBoardArray = [BitBoard; 12];
 pub type ZobristKey = u64;
 
@@ -58,7 +78,7 @@ pub struct BoardInfo {
     turn: Color,
     pub castle: Castle,
     en_passant: Option<Squares>,
-    halfmove_clock: u8,
+    halfmove_clock: u16,
     fullmove_clock: u16,
 }
 
@@ -71,7 +91,7 @@ impl BoardInfo {
         self.turn = color;
     }
 
-    pub fn set_halfmove_clock(&mut self, value: u8) {
+    pub fn set_halfmove_clock(&mut self, value: u16) {
         self.halfmove_clock = value;
     }
 
@@ -87,7 +107,7 @@ impl BoardInfo {
         self.turn
     }
 
-    pub fn get_halfmove_clock(&self) -> u8 {
+    pub fn get_halfmove_clock(&self) -> u16 {
         self.halfmove_clock
     }
 
@@ -174,6 +194,7 @@ impl Sub<u8> for Squares {
 }
 
 use crate::kelp::board::moves::Move;
+use crate::kelp::search::score::Score;
 use Squares::*;
 
 #[derive(Debug, Clone, Copy, Default)]
@@ -233,14 +254,22 @@ impl TimeControl {
     }
 }
 
+/// space separated moves in uci notation
+pub fn pv_to_string(pv: &[Move]) -> String {
+    pv.iter()
+        .map(|m| m.to_string())
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
 pub struct SearchMoveResult {
     pub best_move: Option<Move>,
-    pub score: i32,
+    pub score: Score,
     pub depth: usize,
     pub nodes: u64,
     pub time: Duration,
     pub nps: u64,
-    pub pv: String,
+    pub pv: Vec<Move>,
     pub mate_in: Option<i32>,
 }
 impl Display for SearchMoveResult {
@@ -248,16 +277,12 @@ impl Display for SearchMoveResult {
         let mut info = String::new();
 
         info.push_str(format!("depth {} ", self.depth).as_str());
-        info.push_str(format!("score cp {} ", self.score).as_str());
+        info.push_str(format!("score {} ", self.score).as_str());
         info.push_str(format!("nodes {} ", self.nodes).as_str());
         info.push_str(format!("time {} ", self.time.as_millis()).as_str());
         info.push_str(format!("nps {} ", self.nps).as_str());
 
-        if self.mate_in.is_some() {
-            info.push_str(format!("mate {} ", self.mate_in.unwrap()).as_str());
-        }
-
-        info.push_str(format!("pv {}", self.pv).as_str());
+        info.push_str(format!("pv {}", pv_to_string(&self.pv)).as_str());
 
         write!(f, "{}", info)
     }
@@ -265,12 +290,12 @@ impl Display for SearchMoveResult {
 
 pub struct SearchMoveResultExtended {
     pub best_move: Option<Move>,
-    pub score: i32,
+    pub score: Score,
     pub depth: usize,
     pub nodes: u64,
     pub time: Duration,
     pub nps: u64,
-    pub pv: String,
+    pub pv: Vec<Move>,
     pub mate_in: Option<i32>,
     pub hash_full: usize,
     pub tb_hits: usize,
@@ -283,20 +308,16 @@ impl Display for SearchMoveResultExtended {
         let mut info = String::new();
 
         info.push_str(format!("depth {} ", self.depth).as_str());
-        info.push_str(format!("score cp {} ", self.score).as_str());
+        info.push_str(format!("score {} ", self.score).as_str());
         info.push_str(format!("nodes {} ", self.nodes).as_str());
         info.push_str(format!("time {} ", self.time.as_millis()).as_str());
         info.push_str(format!("nps {} ", self.nps).as_str());
 
-        if self.mate_in.is_some() {
-            info.push_str(format!("mate {} ", self.mate_in.unwrap()).as_str());
-        }
-
         info.push_str(format!("hashfull {} ", self.hash_full).as_str());
         info.push_str(format!("tbhits {} ", self.tb_hits).as_str());
         info.push_str(format!("misses {} ", self.misses).as_str());
         info.push_str(format!("size {:.2} ", self.size).as_str());
-        info.push_str(format!("pv {}", self.pv).as_str());
+        info.push_str(format!("pv {}", pv_to_string(&self.pv)).as_str());
 
         write!(f, "{}", info)
     }
@@ -12,33 +12,24 @@ use crate::kelp::{
 };
 use log::info;
 
-pub struct MovGen<'a> {
-    pub table: &'a LookupTable,
-    pub move_list: MoveList, // TODO: Make this private
+#[derive(Debug, Clone, Copy)]
+pub struct MovGen {
+    pub table: &'static LookupTable,
 }
 
-// setters and getters
-impl<'a> MovGen<'a> {
-    pub fn get_move_list(&self) -> MoveList {
-        self.move_list.clone()
-    }
-
-    pub fn get_move_list_ref(&self) -> &MoveList {
-        &self.move_list
-    }
-
-    pub fn clear_move_list(&mut self) {
-        self.move_list.clear();
+impl Default for MovGen {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 // Move generation
-impl<'a> MovGen<'a> {
-    pub fn new(table: &'a LookupTable) -> MovGen<'a> {
+impl MovGen {
+    /// move generator using the process wide lookup table
+    pub fn new() -> MovGen {
         info!("Initializing move generator");
         MovGen {
-            table: table,
-            move_list: MoveList::new(),
+            table: LookupTable::global(),
         }
     }
 
@@ -121,11 +112,12 @@ impl<'a> MovGen<'a> {
 
     #[inline(always)]
     fn generate_pawn_attacks(
-        &mut self,
+        &self,
         target: Squares,
         source: Squares,
         side: Color,
         board: &Board,
+        list: &mut MoveList,
     ) {
         let piece = board.get_piece(target);
 
@@ -144,15 +136,15 @@ impl<'a> MovGen<'a> {
                         );
                         if source.rank() == 6 {
                             mv.set_type(MoveType::Promotion(Some(WhiteQueen)));
-                            self.move_list.push(mv);
+                            list.push(mv);
                             mv.set_type(MoveType::Promotion(Some(WhiteRook)));
-                            self.move_list.push(mv);
+                            list.push(mv);
                             mv.set_type(MoveType::Promotion(Some(WhiteBishop)));
-                            self.move_list.push(mv);
+                            list.push(mv);
                             mv.set_type(MoveType::Promotion(Some(WhiteKnight)));
-                            self.move_list.push(mv);
+                            list.push(mv);
                         } else {
-                            self.move_list.push(mv);
+                            list.push(mv);
                         }
                     }
                     Black => {
@@ -166,15 +158,15 @@ impl<'a> MovGen<'a> {
                         );
                         if source.rank() == 1 {
                             mv.set_type(MoveType::Promotion(Some(BlackQueen)));
-                            self.move_list.push(mv);
+                            list.push(mv);
                             mv.set_type(MoveType::Promotion(Some(BlackRook)));
-                            self.move_list.push(mv);
+                            list.push(mv);
                             mv.set_type(MoveType::Promotion(Some(BlackBishop)));
-                            self.move_list.push(mv);
+                            list.push(mv);
                             mv.set_type(MoveType::Promotion(Some(BlackKnight)));
-                            self.move_list.push(mv);
+                            list.push(mv);
                         } else {
-                            self.move_list.push(mv);
+                            list.push(mv);
                         }
                     }
                 }
@@ -183,7 +175,7 @@ impl<'a> MovGen<'a> {
     }
 
     #[inline(always)]
-    fn generate_pawn_moves(&mut self, side: Color, board: &Board) {
+    fn generate_pawn_moves(&self, side: Color, board: &Board, list: &mut MoveList) {
         match side {
             White => {
                 let bitboard = board.get_piece_occ(WhitePawn);
@@ -209,7 +201,7 @@ impl<'a> MovGen<'a> {
 
                         // Generate double pawn push
                         if square.rank() == 1 && !board.get_occ().get_bit(sq + 16) {
-                            self.move_list.push(mv);
+                            list.push(mv);
                             mv = Move::new(
                                 square,
                                 square + 16,
@@ -218,20 +210,20 @@ impl<'a> MovGen<'a> {
                                 MoveType::DoublePawnPush,
                                 GenType::Quiet,
                             );
-                            self.move_list.push(mv);
+                            list.push(mv);
 
                             // Generate Pawn Promotions
                         } else if square.rank() == 6 {
                             mv.set_type(MoveType::Promotion(Some(WhiteQueen)));
-                            self.move_list.push(mv);
+                            list.push(mv);
                             mv.set_type(MoveType::Promotion(Some(WhiteRook)));
-                            self.move_list.push(mv);
+                            list.push(mv);
                             mv.set_type(MoveType::Promotion(Some(WhiteBishop)));
-                            self.move_list.push(mv);
+                            list.push(mv);
                             mv.set_type(MoveType::Promotion(Some(WhiteKnight)));
-                            self.move_list.push(mv);
+                            list.push(mv);
                         } else {
-                            self.move_list.push(mv);
+                            list.push(mv);
                         }
                     }
 
@@ -243,6 +235,7 @@ impl<'a> MovGen<'a> {
                             square,
                             White,
                             board,
+                            list,
                         );
                     }
 
@@ -260,7 +253,7 @@ impl<'a> MovGen<'a> {
                                 MoveType::EnPassant,
                                 GenType::Capture,
                             );
-                            self.move_list.push(mv);
+                            list.push(mv);
                         }
                     }
                 }
@@ -290,7 +283,7 @@ impl<'a> MovGen<'a> {
 
                         // Generate Black double pawn push
                         if square.rank() == 6 && !board.get_occ().get_bit(sq - 16) {
-                            self.move_list.push(mv);
+                            list.push(mv);
                             mv = Move::new(
                                 square,
                                 square - 16,
@@ -299,20 +292,20 @@ impl<'a> MovGen<'a> {
                                 MoveType::DoublePawnPush,
                                 GenType::Quiet,
                             );
-                            self.move_list.push(mv);
+                            list.push(mv);
 
                             // Generate Black Pawn Promotions
                         } else if square.rank() == 1 {
                             mv.set_type(MoveType::Promotion(Some(BlackQueen)));
-                            self.move_list.push(mv);
+                            list.push(mv);
                             mv.set_type(MoveType::Promotion(Some(BlackRook)));
-                            self.move_list.push(mv);
+                            list.push(mv);
                             mv.set_type(MoveType::Promotion(Some(BlackBishop)));
-                            self.move_list.push(mv);
+                            list.push(mv);
                             mv.set_type(MoveType::Promotion(Some(BlackKnight)));
-                            self.move_list.push(mv);
+                            list.push(mv);
                         } else {
-                            self.move_list.push(mv);
+                            list.push(mv);
                         }
                     }
 
@@ -325,6 +318,7 @@ impl<'a> MovGen<'a> {
                             square,
                             Black,
                             board,
+                            list,
                         );
                     }
 
@@ -342,7 +336,7 @@ impl<'a> MovGen<'a> {
                                 MoveType::EnPassant,
                                 GenType::Capture,
                             );
-                            self.move_list.push(mv);
+                            list.push(mv);
                         }
                     }
                 }
@@ -351,7 +345,7 @@ impl<'a> MovGen<'a> {
     }
 
     #[inline(always)]
-    fn generate_castling_moves(&mut self, side: Color, board: &Board) {
+    fn generate_castling_moves(&self, side: Color, board: &Board, list: &mut MoveList) {
         //TODO: make private
         let castle = board.info.castle;
 
@@ -364,7 +358,7 @@ impl<'a> MovGen<'a> {
                     && !self.is_attacked(E1, !side, board)
                     && !self.is_attacked(Squares::F1, !side, board)
                 {
-                    self.move_list.push(Move::new(
+                    list.push(Move::new(
                         E1,
                         Squares::G1,
                         WhiteKing,
@@ -382,7 +376,7 @@ impl<'a> MovGen<'a> {
                     && !self.is_attacked(E1, !side, board)
                     && !self.is_attacked(Squares::D1, !side, board)
                 {
-                    self.move_list.push(Move::new(
+                    list.push(Move::new(
                         E1,
                         Squares::C1,
                         WhiteKing,
@@ -400,7 +394,7 @@ impl<'a> MovGen<'a> {
                     && !self.is_attacked(Squares::E8, !side, board)
                     && !self.is_attacked(Squares::F8, !side, board)
                 {
-                    self.move_list.push(Move::new(
+                    list.push(Move::new(
                         Squares::E8,
                         Squares::G8,
                         BlackKing,
@@ -418,7 +412,7 @@ impl<'a> MovGen<'a> {
                     && !self.is_attacked(Squares::E8, !side, board)
                     && !self.is_attacked(Squares::D8, !side, board)
                 {
-                    self.move_list.push(Move::new(
+                    list.push(Move::new(
                         Squares::E8,
                         Squares::C8,
                         BlackKing,
@@ -432,7 +426,7 @@ impl<'a> MovGen<'a> {
     }
 
     #[inline(always)]
-    pub fn generate_king_moves(&mut self, side: Color, board: &Board) {
+    pub fn generate_king_moves(&self, side: Color, board: &Board, list: &mut MoveList) {
         let king = match side {
             White => WhiteKing,
             Black => BlackKing,
@@ -451,7 +445,7 @@ impl<'a> MovGen<'a> {
                 let piece = board.get_piece(target);
                 if piece.is_some() {
                     if piece.unwrap().get_color() != side {
-                        self.move_list.push(Move::new(
+                        list.push(Move::new(
                             source,
                             target,
                             king,
@@ -461,7 +455,7 @@ impl<'a> MovGen<'a> {
                         ));
                     }
                 } else {
-                    self.move_list.push(Move::new(
+                    list.push(Move::new(
                         source,
                         target,
                         king,
@@ -475,7 +469,7 @@ impl<'a> MovGen<'a> {
     }
 
     #[inline(always)]
-    fn generate_knight_moves(&mut self, side: Color, board: &Board) {
+    fn generate_knight_moves(&self, side: Color, board: &Board, list: &mut MoveList) {
         let knight = match side {
             White => WhiteKnight,
             Black => BlackKnight,
@@ -494,7 +488,7 @@ impl<'a> MovGen<'a> {
                 let piece = board.get_piece(target);
                 if piece.is_some() {
                     if piece.unwrap().get_color() != side {
-                        self.move_list.push(Move::new(
+                        list.push(Move::new(
                             source,
                             target,
                             knight,
@@ -504,7 +498,7 @@ impl<'a> MovGen<'a> {
                         ));
                     }
                 } else {
-                    self.move_list.push(Move::new(
+                    list.push(Move::new(
                         source,
                         target,
                         knight,
@@ -518,7 +512,7 @@ impl<'a> MovGen<'a> {
     }
 
     #[inline(always)]
-    pub fn generate_bishop_moves(&mut self, side: Color, board: &Board) {
+    pub fn generate_bishop_moves(&self, side: Color, board: &Board, list: &mut MoveList) {
         let bishop = match side {
             White => WhiteBishop,
             Black => BlackBishop,
@@ -537,7 +531,7 @@ impl<'a> MovGen<'a> {
                 let piece = board.get_piece(target);
                 if piece.is_some() {
                     if piece.unwrap().get_color() != side {
-                        self.move_list.push(Move::new(
+                        list.push(Move::new(
                             source,
                             target,
                             bishop,
@@ -547,7 +541,7 @@ impl<'a> MovGen<'a> {
                         ));
                     }
                 } else {
-                    self.move_list.push(Move::new(
+                    list.push(Move::new(
                         source,
                         target,
                         bishop,
@@ -561,7 +555,7 @@ impl<'a> MovGen<'a> {
     }
 
     #[inline(always)]
-    pub fn generate_rook_moves(&mut self, side: Color, board: &Board) {
+    pub fn generate_rook_moves(&self, side: Color, board: &Board, list: &mut MoveList) {
         let rook = match side {
             White => WhiteRook,
             Black => BlackRook,
@@ -580,7 +574,7 @@ impl<'a> MovGen<'a> {
                 let piece = board.get_piece(target);
                 if piece.is_some() {
                     if piece.unwrap().get_color() != side {
-                        self.move_list.push(Move::new(
+                        list.push(Move::new(
                             source,
                             target,
                             rook,
@@ -590,7 +584,7 @@ impl<'a> MovGen<'a> {
                         ));
                     }
                 } else {
-                    self.move_list.push(Move::new(
+                    list.push(Move::new(
                         source,
                         target,
                         rook,
@@ -604,7 +598,7 @@ impl<'a> MovGen<'a> {
     }
 
     #[inline(always)]
-    pub fn generate_queen_moves(&mut self, side: Color, board: &Board) {
+    pub fn generate_queen_moves(&self, side: Color, board: &Board, list: &mut MoveList) {
         let queen = match side {
             White => WhiteQueen,
             Black => BlackQueen,
@@ -623,7 +617,7 @@ impl<'a> MovGen<'a> {
                 let piece = board.get_piece(target);
                 if piece.is_some() {
                     if piece.unwrap().get_color() != side {
-                        self.move_list.push(Move::new(
+                        list.push(Move::new(
                             source,
                             target,
                             queen,
@@ -633,7 +627,7 @@ impl<'a> MovGen<'a> {
                         ));
                     }
                 } else {
-                    self.move_list.push(Move::new(
+                    list.push(Move::new(
                         source,
                         target,
                         queen,
@@ -646,17 +640,17 @@ impl<'a> MovGen<'a> {
         }
     }
 
+    /// pseudo legal moves, appended to `list`
     #[inline(always)]
-    pub fn generate_moves(&mut self, board: &Board) {
+    pub fn generate_moves(&self, board: &Board, list: &mut MoveList) {
         let side = board.get_side_to_move();
-        self.move_list.clear();
-        self.generate_pawn_moves(side, board);
-        self.generate_castling_moves(side, board);
-        self.generate_king_moves(side, board);
-        self.generate_knight_moves(side, board);
-        self.generate_bishop_moves(side, board);
-        self.generate_rook_moves(side, board);
-        self.generate_queen_moves(side, board);
+        self.generate_pawn_moves(side, board, list);
+        self.generate_castling_moves(side, board, list);
+        self.generate_king_moves(side, board, list);
+        self.generate_knight_moves(side, board, list);
+        self.generate_bishop_moves(side, board, list);
+        self.generate_rook_moves(side, board, list);
+        self.generate_queen_moves(side, board, list);
     }
 
     pub fn print_attacked(&self, side: Color, board: &Board) {
@@ -0,0 +1,552 @@
+use crate::kelp::board::board::Board;
+use crate::kelp::board::moves::{CastlingRights, GenType, Move, MoveList, MoveType};
+use crate::kelp::board::piece::{
+    BoardPiece::{self, *},
+    Color::{self, *},
+};
+use crate::kelp::kelp_core::bitboard::BitBoard;
+use crate::kelp::mov_gen::generator::MovGen;
+use crate::kelp::Squares::{self, *};
+
+// pieces of each color, ordered pawn, knight, bishop, rook, queen, king
+#[rustfmt::skip]
+pub(crate) const PIECES: [[BoardPiece; 6]; 2] = [
+    [WhitePawn, WhiteKnight, WhiteBishop, WhiteRook, WhiteQueen, WhiteKing],
+    [BlackPawn, BlackKnight, BlackBishop, BlackRook, BlackQueen, BlackKing],
+];
+
+/// Which of the legal moves a generator call produces
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GenMode {
+    All,
+    /// captures, en passant and every promotion
+    Captures,
+    /// everything `Captures` leaves out, castling included
+    Quiets,
+}
+
+/// Checks and pins against the king of the side to move
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CheckInfo {
+    /// enemy pieces giving check
+    pub checkers: BitBoard,
+    /// own pieces pinned to the king
+    pub pinned: BitBoard,
+    /// squares a non king move has to land on, every square when not in check and none in double check
+    pub evasion_mask: BitBoard,
+}
+
+// Legal move generation, computes checkers and pins up front so no move has to be made to test it.
+// moves come out in the same order as `generate_moves`, minus the illegal ones
+impl MovGen {
+    /// pieces of `color` attacking `square` if the board had occupancy `occ`
+    #[inline(always)]
+    pub fn attackers(&self, square: u8, color: Color, occ: BitBoard, board: &Board) -> BitBoard {
+        let [pawn, knight, bishop, rook, queen, king] = PIECES[color as usize];
+        let queens = board.get_piece_occ(queen);
+
+        (self.table.get_pawn_attacks(!color, square) & board.get_piece_occ(pawn))
+            | (self.table.get_knight_attacks(square) & board.get_piece_occ(knight))
+            | (self.table.get_king_attacks(square) & board.get_piece_occ(king))
+            | (self.table.get_bishop_attacks(square, occ) & (board.get_piece_occ(bishop) | queens))
+            | (self.table.get_rook_attacks(square, occ) & (board.get_piece_occ(rook) | queens))
+    }
+
+    pub fn check_info(&self, board: &Board) -> CheckInfo {
+        let side = board.get_side_to_move();
+        let king = board.get_king_square(side) as u8;
+        let occ = board.get_occ();
+        let own = self.own_occ(side, board);
+        let [_, _, bishop, rook, queen, _] = PIECES[!side as usize];
+        let queens = board.get_piece_occ(queen);
+
+        let checkers = self.attackers(king, !side, occ, board);
+
+        // enemy sliders on a line with the king, pinning if exactly one own piece is between
+        let snipers = (self.table.get_bishop_attacks(king, BitBoard::empty())
+            & (board.get_piece_occ(bishop) | queens))
+            | (self.table.get_rook_attacks(king, BitBoard::empty())
+                & (board.get_piece_occ(rook) | queens));
+        let mut pinned = BitBoard::empty();
+        for sniper in snipers {
+            let blockers = self.table.get_between(king, sniper) & occ;
+            if blockers.count_bits() == 1 && !(blockers & own).is_empty() {
+                pinned |= blockers;
+            }
+        }
+
+        let evasion_mask = match checkers.count_bits() {
+            0 => BitBoard(!0),
+            1 => checkers | self.table.get_between(king, checkers.get_lsb()),
+            _ => BitBoard::empty(),
+        };
+
+        CheckInfo {
+            checkers,
+            pinned,
+            evasion_mask,
+        }
+    }
+
+    /// legal moves, appended to `list`
+    pub fn generate_legal_moves(&self, board: &Board, list: &mut MoveList) {
+        self.generate_legal(board, GenMode::All, BitBoard(!0), list);
+    }
+
+    /// legal captures, en passant and promotions, appended to `list`
+    pub fn generate_captures(&self, board: &Board, list: &mut MoveList) {
+        self.generate_legal(board, GenMode::Captures, BitBoard(!0), list);
+    }
+
+    /// legal moves `generate_captures` leaves out, appended to `list`
+    pub fn generate_quiets(&self, board: &Board, list: &mut MoveList) {
+        self.generate_legal(board, GenMode::Quiets, BitBoard(!0), list);
+    }
+
+    /// true if `mov` is legal on `board`, for moves that didn't come from generating on it,
+    /// like transposition table and killer moves
+    pub fn is_legal(&self, board: &Board, mov: Move) -> bool {
+        let side = board.get_side_to_move();
+        if !board
+            .get_piece(mov.from())
+            .is_some_and(|p| p.get_color() == side)
+        {
+            return false;
+        }
+
+        // no piece has more than 27 moves
+        let mut list = MoveList::<32>::new();
+        self.generate_legal(
+            board,
+            GenMode::All,
+            BitBoard(1 << mov.from() as u8),
+            &mut list,
+        );
+        list.iter().any(|m| *m == mov)
+    }
+
+    // legal moves of `mode` by pieces standing on `sources`
+    fn generate_legal<const N: usize>(
+        &self,
+        board: &Board,
+        mode: GenMode,
+        sources: BitBoard,
+        list: &mut MoveList<N>,
+    ) {
+        let side = board.get_side_to_move();
+        let info = self.check_info(board);
+
+        let targets = match mode {
+            GenMode::All => !self.own_occ(side, board),
+            GenMode::Captures => self.own_occ(!side, board),
+            GenMode::Quiets => !board.get_occ(),
+        };
+        let king = sources.get_bit(board.get_king_square(side) as u8);
+
+        // in double check only the king can move
+        let double_check = info.checkers.count_bits() > 1;
+
+        if !double_check {
+            self.legal_pawn_moves(side, mode, sources, &info, board, list);
+            if king && mode != GenMode::Captures && info.checkers.is_empty() {
+                self.legal_castling_moves(side, board, list);
+            }
+        }
+        if king {
+            self.legal_king_moves(side, targets, board, list);
+        }
+        if !double_check {
+            for &piece in &PIECES[side as usize][1..5] {
+                let targets = targets & info.evasion_mask;
+                self.legal_piece_moves(piece, targets, sources, &info, board, list);
+            }
+        }
+    }
+
+    #[inline(always)]
+    fn own_occ(&self, side: Color, board: &Board) -> BitBoard {
+        match side {
+            White => board.get_white_occ(),
+            Black => board.get_black_occ(),
+        }
+    }
+
+    // squares a piece on `square` may move to without exposing its king
+    #[inline(always)]
+    fn pin_mask(&self, square: u8, info: &CheckInfo, board: &Board) -> BitBoard {
+        if info.pinned.get_bit(square) {
+            let king = board.get_king_square(board.get_side_to_move()) as u8;
+            self.table.get_line(king, square)
+        } else {
+            BitBoard(!0)
+        }
+    }
+
+    #[inline(always)]
+    fn push_targets<const N: usize>(
+        &self,
+        piece: BoardPiece,
+        source: u8,
+        targets: BitBoard,
+        board: &Board,
+        list: &mut MoveList<N>,
+    ) {
+        let source = Squares::from_repr(source).unwrap();
+        for target in targets {
+            let target = Squares::from_repr(target).unwrap();
+            let capture = board.get_piece(target);
+            let gen_type = match capture {
+                Some(_) => GenType::Capture,
+                None => GenType::Quiet,
+            };
+            list.push(Move::new(
+                source,
+                target,
+                piece,
+                capture,
+                MoveType::Normal,
+                gen_type,
+            ));
+        }
+    }
+
+    // pushes `mv`, or all four promotions of it when `promotion` is set
+    #[inline(always)]
+    fn push_pawn_move<const N: usize>(
+        &self,
+        mut mv: Move,
+        side: Color,
+        promotion: bool,
+        list: &mut MoveList<N>,
+    ) {
+        if !promotion {
+            list.push(mv);
+            return;
+        }
+        let [_, knight, bishop, rook, queen, _] = PIECES[side as usize];
+        for piece in [queen, rook, bishop, knight] {
+            mv.set_type(MoveType::Promotion(Some(piece)));
+            list.push(mv);
+        }
+    }
+
+    #[inline(always)]
+    fn legal_pawn_moves<const N: usize>(
+        &self,
+        side: Color,
+        mode: GenMode,
+        sources: BitBoard,
+        info: &CheckInfo,
+        board: &Board,
+        list: &mut MoveList<N>,
+    ) {
+        let pawn = PIECES[side as usize][0];
+        let occ = board.get_occ();
+        let enemy = self.own_occ(!side, board);
+        let (start_rank, promotion_rank) = match side {
+            White => (1, 6),
+            Black => (6, 1),
+        };
+
+        for sq in board.get_piece_occ(pawn) & sources {
+            // Check if pawn is on the 8th rank or 1st rank
+            if sq >= 56 || sq <= 7 {
+                continue;
+            }
+            let square = Squares::from_repr(sq).unwrap();
+            let allowed = info.evasion_mask & self.pin_mask(sq, info, board);
+            let promotion = square.rank() == promotion_rank;
+            // `double` is only read on the start rank
+            let (single, double) = match side {
+                White => (sq + 8, sq.wrapping_add(16)),
+                Black => (sq - 8, sq.wrapping_sub(16)),
+            };
+
+            // pushes, promotions count as captures
+            if !occ.get_bit(single) {
+                let mv = Move::new(
+                    square,
+                    Squares::from_repr(single).unwrap(),
+                    pawn,
+                    None,
+                    MoveType::Normal,
+                    GenType::Quiet,
+                );
+                let wanted = match mode {
+                    GenMode::All => true,
+                    GenMode::Captures => promotion,
+                    GenMode::Quiets => !promotion,
+                };
+                if wanted && allowed.get_bit(single) {
+                    self.push_pawn_move(mv, side, promotion, list);
+                }
+
+                if mode != GenMode::Captures
+                    && square.rank() == start_rank
+                    && !occ.get_bit(double)
+                    && allowed.get_bit(double)
+                {
+                    list.push(Move::new(
+                        square,
+                        Squares::from_repr(double).unwrap(),
+                        pawn,
+                        None,
+                        MoveType::DoublePawnPush,
+                        GenType::Quiet,
+                    ));
+                }
+            }
+
+            if mode == GenMode::Quiets {
+                continue;
+            }
+
+            // captures
+            let attacks = self.table.get_pawn_attacks(side, sq);
+            for target in attacks & enemy & allowed {
+                let target = Squares::from_repr(target).unwrap();
+                let mv = Move::new(
+                    square,
+                    target,
+                    pawn,
+                    board.get_piece(target),
+                    MoveType::Normal,
+                    GenType::Capture,
+                );
+                self.push_pawn_move(mv, side, promotion, list);
+            }
+
+            // en passant, tested by removing both pawns from the board since the capture can
+            // uncover a check along the rank that no pin covers
+            if let Some(en_passant) = board.get_en_passant() {
+                if attacks.get_bit(en_passant as u8) {
+                    let captured = match side {
+                        White => en_passant as u8 - 8,
+                        Black => en_passant as u8 + 8,
+                    };
+                    let king = board.get_king_square(side) as u8;
+                    let after =
+                        BitBoard((occ.0 & !(1 << sq) & !(1 << captured)) | 1 << (en_passant as u8));
+                    let attackers =
+                        self.attackers(king, !side, after, board) & BitBoard(!(1 << captured));
+
+                    if attackers.is_empty() {
+                        list.push(Move::new(
+                            square,
+                            en_passant,
+                            pawn,
+                            Some(PIECES[!side as usize][0]),
+                            MoveType::EnPassant,
+                            GenType::Capture,
+                        ));
+                    }
+                }
+            }
+        }
+    }
+    // only called when not in check, so the king's own square is known to be safe
+    #[inline(always)]
+    fn legal_castling_moves<const N: usize>(
+        &self,
+        side: Color,
+        board: &Board,
+        list: &mut MoveList<N>,
+    ) {
+        let castle = board.info.castle;
+        let occ = board.get_occ();
+        let king = PIECES[side as usize][5];
+        let (from, king_side, queen_side) = match side {
+            White => (
+                E1,
+                CastlingRights::WhiteKingSide,
+                CastlingRights::WhiteQueenSide,
+            ),
+            Black => (
+                E8,
+                CastlingRights::BlackKingSide,
+                CastlingRights::BlackQueenSide,
+            ),
+        };
+        let sq = from as u8;
+
+        // squares between king and rook must be empty, the king may not pass or land on an attacked one
+        if castle.can_castle(king_side)
+            && !occ.get_bit(sq + 1)
+            && !occ.get_bit(sq + 2)
+            && !self.is_attacked(from + 1, !side, board)
+            && !self.is_attacked(from + 2, !side, board)
+        {
+            list.push(Move::new(
+                from,
+                from + 2,
+                king,
+                None,
+                MoveType::Castle(king_side),
+                GenType::Quiet,
+            ));
+        }
+
+        if castle.can_castle(queen_side)
+            && !occ.get_bit(sq - 1)
+            && !occ.get_bit(sq - 2)
+            && !occ.get_bit(sq - 3)
+            && !self.is_attacked(from - 1, !side, board)
+            && !self.is_attacked(from - 2, !side, board)
+        {
+            list.push(Move::new(
+                from,
+                from - 2,
+                king,
+                None,
+                MoveType::Castle(queen_side),
+                GenType::Quiet,
+            ));
+        }
+    }
+
+    #[inline(always)]
+    fn legal_king_moves<const N: usize>(
+        &self,
+        side: Color,
+        targets: BitBoard,
+        board: &Board,
+        list: &mut MoveList<N>,
+    ) {
+        let king = PIECES[side as usize][5];
+        let sq = board.get_king_square(side) as u8;
+        // without the king, so sliders see through to the squares behind it
+        let occ = BitBoard(board.get_occ().0 & !(1 << sq));
+
+        let mut targets = self.table.get_king_attacks(sq) & targets;
+        for target in targets {
+            if !self.attackers(target, !side, occ, board).is_empty() {
+                targets.clear_bit(target);
+            }
+        }
+        self.push_targets(king, sq, targets, board, list);
+    }
+
+    #[inline(always)]
+    fn legal_piece_moves<const N: usize>(
+        &self,
+        piece: BoardPiece,
+        targets: BitBoard,
+        sources: BitBoard,
+        info: &CheckInfo,
+        board: &Board,
+        list: &mut MoveList<N>,
+    ) {
+        let occ = board.get_occ();
+
+        for sq in board.get_piece_occ(piece) & sources {
+            let attacks = match piece {
+                WhiteKnight | BlackKnight => self.table.get_knight_attacks(sq),
+                WhiteBishop | BlackBishop => self.table.get_bishop_attacks(sq, occ),
+                WhiteRook | BlackRook => self.table.get_rook_attacks(sq, occ),
+                _ => self.table.get_queen_attacks(sq, occ),
+            };
+            let targets = attacks & targets & self.pin_mask(sq, info, board);
+            self.push_targets(piece, sq, targets, board, list);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::kelp::board::board::Board;
+    use crate::kelp::board::fen::{Fen, FenParse};
+    use crate::kelp::board::moves::MoveList;
+    use crate::kelp::mov_gen::generator::MovGen;
+
+    fn legal_strings(fen: &str) -> Vec<String> {
+        let board = Board::parse(Fen(fen.to_string())).unwrap();
+        let mut moves = MoveList::new();
+        MovGen::new().generate_legal_moves(&board, &mut moves);
+        moves.iter().map(|m| m.to_string()).collect()
+    }
+
+    #[test]
+    fn legal_moves_test() {
+        // en passant would expose the king along the rank
+        let moves = legal_strings("8/8/8/8/k2Pp2Q/8/8/3K4 b - d3 0 1");
+        assert!(!moves.contains(&"e4d3".to_string()));
+        assert!(moves.contains(&"e4e3".to_string()));
+
+        // en passant removing the checking pawn
+        let moves = legal_strings("8/8/8/2k5/3Pp3/8/8/3K4 b - d3 0 1");
+        assert!(moves.contains(&"e4d3".to_string()));
+        assert!(moves.contains(&"c5d4".to_string()));
+
+        // double check from rook and knight, only the king may move
+        let moves = legal_strings("4r1k1/8/8/8/8/R2n4/8/4K3 w - - 0 1");
+        assert!(moves.iter().all(|m| m.starts_with("e1")));
+        assert_eq!(moves.len(), 3);
+
+        // the pinned rook may only move along the pin
+        let moves = legal_strings("4r1k1/8/8/8/8/8/4R3/4K3 w - - 0 1");
+        let rook: Vec<_> = moves.iter().filter(|m| m.starts_with("e2")).collect();
+        assert_eq!(rook.len(), 6);
+        assert!(rook.iter().all(|m| m.as_bytes()[2] == b'e'));
+
+        // castling through an attacked square
+        let moves = legal_strings("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+        assert!(moves.contains(&"e1g1".to_string()));
+        let moves = legal_strings("r3k2r/8/8/8/8/8/6r1/R3K2R w KQkq - 0 1");
+        assert!(!moves.contains(&"e1g1".to_string()));
+    }
+
+    #[test]
+    fn captures_quiets_test() {
+        let fens = [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "8/8/8/2k5/3Pp3/8/8/3K4 b - d3 0 1",
+            "4r1k1/8/8/8/8/R2n4/8/4K3 w - - 0 1",
+        ];
+
+        for fen in fens {
+            let board = Board::parse(Fen(fen.to_string())).unwrap();
+            let gen = MovGen::new();
+            let mut moves = MoveList::new();
+            gen.generate_legal_moves(&board, &mut moves);
+            let mut all: Vec<_> = moves.iter().map(|m| m.to_string()).collect();
+
+            let mut captures = MoveList::new();
+            gen.generate_captures(&board, &mut captures);
+            let mut quiets = MoveList::new();
+            gen.generate_quiets(&board, &mut quiets);
+
+            assert!(captures
+                .iter()
+                .all(|m| m.capture(&board).is_some() || m.is_promotion()));
+            assert!(quiets
+                .iter()
+                .all(|m| m.capture(&board).is_none() && !m.is_promotion()));
+
+            let mut split: Vec<_> = captures
+                .iter()
+                .chain(&quiets)
+                .map(|m| m.to_string())
+                .collect();
+            all.sort();
+            split.sort();
+            assert_eq!(all, split, "{}", fen);
+
+            for mov in captures.iter().chain(&quiets) {
+                assert!(gen.is_legal(&board, *mov));
+            }
+        }
+
+        // a pinned piece's move isn't legal even if it is pseudo legal
+        let board = Board::parse(Fen("4r1k1/8/8/8/8/8/4R3/4K3 w - - 0 1".to_string())).unwrap();
+        let gen = MovGen::new();
+        let mut pseudo_legal = MoveList::new();
+        gen.generate_moves(&board, &mut pseudo_legal);
+        let legal = pseudo_legal
+            .iter()
+            .filter(|m| gen.is_legal(&board, **m))
+            .count();
+        assert_eq!(legal, 6 + 4);
+    }
+}
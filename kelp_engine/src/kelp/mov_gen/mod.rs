@@ -1,2 +1,4 @@
 pub mod generator;
+pub mod legal;
 pub mod perft;
+pub mod see;
@@ -1,4 +1,5 @@
 use crate::kelp::board::board::Board;
+use crate::kelp::board::moves::MoveList;
 use crate::kelp::mov_gen::generator::MovGen;
 
 pub fn perft_driver(depth: u16, board: &mut Board, gen: &mut MovGen, nodes: &mut u64) {
@@ -8,8 +9,8 @@ pub fn perft_driver(depth: u16, board: &mut Board, gen: &mut MovGen, nodes: &mut
     }
 
     let side = board.get_side_to_move();
-    gen.generate_moves(board);
-    let moves_list = gen.move_list.clone();
+    let mut moves_list = MoveList::new();
+    gen.generate_moves(board, &mut moves_list);
 
     for &move_to_make in moves_list.iter() {
         let undo_info = board.make_move(move_to_make, false);
@@ -25,32 +26,46 @@ pub fn perft_driver(depth: u16, board: &mut Board, gen: &mut MovGen, nodes: &mut
     }
 }
 
+/// perft on the legal move generator, the last ply is counted without making its moves
+pub fn perft_legal_driver(depth: u16, board: &mut Board, gen: &mut MovGen, nodes: &mut u64) {
+    if depth == 0 {
+        *nodes += 1;
+        return;
+    }
+
+    let mut moves_list = MoveList::new();
+    gen.generate_legal_moves(board, &mut moves_list);
+    if depth == 1 {
+        *nodes += moves_list.len() as u64;
+        return;
+    }
+
+    for &move_to_make in moves_list.iter() {
+        let undo_info = board.make_move(move_to_make, false).unwrap();
+        perft_legal_driver(depth - 1, board, gen, nodes);
+        board.unmake_move(undo_info);
+    }
+}
+
+/// prints the node count under every root move, uses the legal move generator
 pub fn perft_test(depth: u16, board: &mut Board, gen: &mut MovGen, nodes: &mut u64) {
     *nodes = 0;
 
     // println!("Starting Perft Test to depth: {depth}");
-    gen.generate_moves(board);
-    let moves_list = gen.move_list.clone();
+    let mut moves_list = MoveList::new();
+    gen.generate_legal_moves(board, &mut moves_list);
     let time = std::time::Instant::now();
 
     for moves in moves_list.iter() {
-        let a = board.make_move(*moves, false);
-        if a.is_none() {
-            continue;
-        }
-        let side = board.get_side_to_move();
-        if board.is_king_checked(!side, gen) {
-            board.unmake_move(a.unwrap());
-            continue;
-        }
+        let a = board.make_move(*moves, false).unwrap();
 
         let cummutative_nodes = *nodes;
 
-        perft_driver(depth - 1, board, gen, nodes);
+        perft_legal_driver(depth - 1, board, gen, nodes);
 
         let old_nodes = *nodes - cummutative_nodes;
 
-        board.unmake_move(a.unwrap());
+        board.unmake_move(a);
 
         println!("{} {}", moves, old_nodes);
     }
@@ -60,20 +75,21 @@ pub fn perft_test(depth: u16, board: &mut Board, gen: &mut MovGen, nodes: &mut u
 
 #[cfg(test)]
 mod tests {
-    use super::perft_driver;
+    use super::{perft_driver, perft_legal_driver};
     use crate::kelp::board::board::Board;
     use crate::kelp::board::fen::{Fen, FenParse};
-    use crate::kelp::kelp_core::lookup_table::LookupTable;
     use crate::kelp::mov_gen::generator::MovGen;
 
     fn test_by_depth(depth: u16, fen: String, expected: u64) {
         let mut board = Board::parse(Fen(fen)).unwrap();
-        let mut table = LookupTable::new();
-        table.populate();
-        let mut gen = MovGen::new(&table);
+        let mut gen = MovGen::new();
         let mut nodes = 0;
         perft_driver(depth, &mut board, &mut gen, &mut nodes);
         assert_eq!(nodes, expected);
+
+        let mut legal_nodes = 0;
+        perft_legal_driver(depth, &mut board, &mut gen, &mut legal_nodes);
+        assert_eq!(legal_nodes, expected, "legal generator");
     }
 
     #[test]
@@ -232,6 +248,26 @@ mod tests {
         );
     }
 
+    #[test]
+    fn legal_edge_cases_test() {
+        // discovered checks, en passant pins along the rank, promotions and castling rights
+        test_by_depth(6, "3k4/3p4/8/K1P4r/8/8/8/8 b - - 0 1".to_string(), 1134888);
+        test_by_depth(6, "8/8/4k3/8/2p5/8/B2P2K1/8 w - - 0 1".to_string(), 1015133);
+        test_by_depth(
+            6,
+            "8/8/1k6/2b5/2pP4/8/5K2/8 b - d3 0 1".to_string(),
+            1440467,
+        );
+        test_by_depth(6, "5k2/8/8/8/8/8/8/4K2R w K - 0 1".to_string(), 661072);
+        test_by_depth(4, "8/8/2k5/5q2/5n2/8/5K2/8 b - - 0 1".to_string(), 23527);
+        test_by_depth(6, "4k3/1P6/8/8/8/8/K7/8 w - - 0 1".to_string(), 217342);
+        test_by_depth(
+            4,
+            "r3k2r/1b4bq/8/8/8/8/7B/R3K2R w KQkq - 0 1".to_string(),
+            1274206,
+        );
+    }
+
     #[test]
     fn steven_edwards_pos_test() {
         // pos 6
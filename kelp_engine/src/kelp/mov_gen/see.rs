@@ -0,0 +1,112 @@
+use crate::kelp::board::board::Board;
+use crate::kelp::board::moves::Move;
+use crate::kelp::kelp_core::bitboard::BitBoard;
+use crate::kelp::mov_gen::generator::MovGen;
+use crate::kelp::mov_gen::legal::PIECES;
+use crate::kelp::PAWN_VALUE;
+
+// Static exchange evaluation, plays out every capture on the target square with the least
+// valuable attacker first, either side may stop capturing when that is better for it
+impl MovGen {
+    /// material `mov` wins or loses once all captures on its target square are played out
+    pub fn see(&self, board: &Board, mov: Move) -> i32 {
+        let to = mov.to() as u8;
+        let mut occ = BitBoard(board.get_occ().0 & !(1 << mov.from() as u8));
+        if mov.is_en_passant() {
+            let captured = if mov.to() > mov.from() {
+                to - 8
+            } else {
+                to + 8
+            };
+            occ.clear_bit(captured);
+        }
+
+        let mut gain = [0; 32];
+        gain[0] = mov.capture(board).map_or(0, |p| p.get_value());
+        // value of the piece standing on the target square
+        let mut on_target = match mov.get_promotion() {
+            Some(promotion) => {
+                gain[0] += promotion.get_value() - PAWN_VALUE;
+                promotion.get_value()
+            }
+            None => mov.piece(board).get_value(),
+        };
+        let mut side = !mov.piece(board).get_color();
+        let mut depth = 0;
+
+        while depth < gain.len() - 1 {
+            depth += 1;
+            gain[depth] = on_target - gain[depth - 1];
+            // neither side can gain from going on
+            if (-gain[depth - 1]).max(gain[depth]) < 0 {
+                break;
+            }
+
+            // sliders behind a piece that captured attack through the square it left
+            let attackers = self.attackers(to, side, occ, board) & occ;
+            let Some((sq, piece)) = PIECES[side as usize].iter().find_map(|&piece| {
+                let pieces = attackers & board.get_piece_occ(piece);
+                (!pieces.is_empty()).then(|| (pieces.get_lsb(), piece))
+            }) else {
+                break;
+            };
+
+            occ.clear_bit(sq);
+            on_target = piece.get_value();
+            side = !side;
+        }
+
+        // the last gain is a capture that never happens
+        while depth > 1 {
+            depth -= 1;
+            gain[depth - 1] = -(-gain[depth - 1]).max(gain[depth]);
+        }
+        gain[0]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::kelp::board::board::Board;
+    use crate::kelp::board::fen::{Fen, FenParse};
+    use crate::kelp::board::moves::MoveList;
+    use crate::kelp::mov_gen::generator::MovGen;
+
+    fn see(fen: &str, mov: &str) -> i32 {
+        let board = Board::parse(Fen(fen.to_string())).unwrap();
+        let gen = MovGen::new();
+        let mut moves = MoveList::new();
+        gen.generate_legal_moves(&board, &mut moves);
+        let mov = *moves.iter().find(|m| m.to_string() == mov).unwrap();
+        gen.see(&board, mov)
+    }
+
+    #[test]
+    fn see_test() {
+        // undefended pawn
+        assert_eq!(
+            see("1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1", "e1e5"),
+            100
+        );
+        // pawn defended by a knight, the rook is lost for it
+        assert_eq!(
+            see(
+                "1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1",
+                "d3e5"
+            ),
+            -200
+        );
+        // queen takes a pawn defended by a pawn
+        assert_eq!(see("4k3/8/3p4/4p3/8/8/8/4QK2 w - - 0 1", "e1e5"), -900);
+        // rooks doubled on the file, the second one recaptures through the first
+        assert_eq!(see("4r1k1/8/8/4p3/8/8/4R3/4R1K1 w - - 0 1", "e2e5"), 100);
+        // quiet moves onto a safe and an attacked square
+        assert_eq!(see("4k3/8/8/4p3/8/8/8/2B1K3 w - - 0 1", "c1e3"), 0);
+        assert_eq!(see("4k3/8/8/4p3/8/8/8/2B1K3 w - - 0 1", "c1f4"), -350);
+        // promotion that gets recaptured only loses the pawn
+        assert_eq!(see("1r2k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a7a8q"), -100);
+        assert_eq!(see("4k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a7a8q"), 900);
+        // en passant
+        assert_eq!(see("4k3/8/8/3Pp3/8/8/8/4K3 w - e6 0 1", "d5e6"), 100);
+    }
+}
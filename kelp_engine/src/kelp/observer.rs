@@ -0,0 +1,24 @@
+use super::board::moves::Move;
+use super::search::score::Score;
+use super::{SearchMoveResult, SearchMoveResultExtended};
+
+/// Receives progress of a search as it runs, every callback does nothing by default.
+/// Callbacks are made from the thread running the search, helper threads never report.
+pub trait SearchObserver {
+    /// an iteration of iterative deepening completed
+    fn on_iteration(&mut self, _info: &SearchMoveResultExtended) {}
+
+    /// a root move became the best move, may be reported again in the same iteration
+    fn on_best_move(&mut self, _mov: Move, _score: Score, _depth: usize) {}
+
+    /// a root move is about to be searched, `number` starts from 1
+    fn on_currmove(&mut self, _mov: Move, _number: usize, _depth: usize) {}
+
+    /// the search finished or was stopped, called once per search
+    fn on_finish(&mut self, _result: &SearchMoveResult) {}
+}
+
+/// Observer that ignores everything
+pub struct NoObserver;
+
+impl SearchObserver for NoObserver {}
@@ -0,0 +1,626 @@
+use super::board::board::Board;
+use super::board::fen::{Fen, FenParse};
+use super::board::moves::Move;
+use super::board::piece::Color;
+use super::board::san::{parse_san, to_san};
+use super::game::Game;
+use super::mov_gen::generator::MovGen;
+use std::fmt::Display;
+use std::io::BufRead;
+
+// export format keeps movetext lines at most this long
+const LINE_WIDTH: usize = 80;
+
+/// A game that could not be read, `game` counts from 1 in the file and `ply` is the half move
+/// that failed, 0 for the tags
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub struct PgnError {
+    pub game: usize,
+    pub ply: usize,
+    pub message: String,
+}
+
+impl Display for PgnError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "game {}, ply {}: {}", self.game, self.ply, self.message)
+    }
+}
+
+/// A move of the movetext with what was written after it
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct PgnMove {
+    pub mov: Move,
+    pub nags: Vec<u8>,
+    pub comment: Option<String>,
+    /// lines played instead of this move
+    pub variations: Vec<Vec<PgnMove>>,
+}
+
+impl PgnMove {
+    pub fn new(mov: Move) -> Self {
+        PgnMove {
+            mov,
+            nags: Vec::new(),
+            comment: None,
+            variations: Vec::new(),
+        }
+    }
+}
+
+/// A game record, tags in the order they were read or set, the main line and the result
+/// written after it (`1-0`, `0-1`, `1/2-1/2` or `*`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct PgnGame {
+    pub tags: Vec<(String, String)>,
+    pub start: Board,
+    /// comment before the first move
+    pub comment: Option<String>,
+    pub moves: Vec<PgnMove>,
+    pub result: String,
+}
+
+impl PgnGame {
+    /// record of `game` with the seven tag roster set to unknown
+    pub fn from_game(game: &Game) -> Self {
+        let result = game.result().map_or("*".to_string(), |r| r.to_string());
+        let mut tags: Vec<(String, String)> = ["Event", "Site", "Date", "Round", "White", "Black"]
+            .iter()
+            .map(|name| (name.to_string(), "?".to_string()))
+            .collect();
+        tags.push(("Result".to_string(), result.clone()));
+        if *game.start() != Board::default() {
+            tags.push(("SetUp".to_string(), "1".to_string()));
+            tags.push(("FEN".to_string(), game.start().to_fen()));
+        }
+
+        PgnGame {
+            tags,
+            start: *game.start(),
+            comment: None,
+            moves: game.moves().map(PgnMove::new).collect(),
+            result,
+        }
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// replaces the value of tag `name` or adds it at the end
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(n, _)| n == name) {
+            Some((_, v)) => *v = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    /// the main line played out
+    pub fn game(&self) -> Game {
+        let mut game = Game::new(self.start);
+        for m in &self.moves {
+            game.make_move(m.mov);
+        }
+        game
+    }
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+// movetext tokens of `moves` played from `board`, a number is written before the first move
+fn line_tokens(board: &Board, moves: &[PgnMove], gen: &MovGen, tokens: &mut Vec<String>) {
+    let mut board = *board;
+    let mut numbered = false;
+
+    for m in moves {
+        let number = board.info.get_fullmove_clock();
+        if board.get_side_to_move() == Color::White {
+            tokens.push(format!("{}.", number));
+        } else if !numbered {
+            tokens.push(format!("{}...", number));
+        }
+        tokens.push(to_san(&board, m.mov, gen));
+        numbered = true;
+
+        for nag in &m.nags {
+            tokens.push(format!("${}", nag));
+        }
+        if let Some(comment) = &m.comment {
+            comment_tokens(comment, tokens);
+            numbered = false;
+        }
+        for variation in &m.variations {
+            let start = tokens.len();
+            line_tokens(&board, variation, gen, tokens);
+            if tokens.len() > start {
+                tokens[start].insert(0, '(');
+                tokens.last_mut().unwrap().push(')');
+            }
+            numbered = false;
+        }
+
+        board.make_move(m.mov, false);
+    }
+}
+
+// comment split into words so long comments wrap too
+fn comment_tokens(comment: &str, tokens: &mut Vec<String>) {
+    let start = tokens.len();
+    tokens.extend(comment.split_whitespace().map(String::from));
+    if tokens.len() == start {
+        tokens.push(String::new());
+    }
+    tokens[start].insert(0, '{');
+    tokens.last_mut().unwrap().push('}');
+}
+
+/// export format: tags, a blank line and the movetext wrapped at 80 columns
+impl Display for PgnGame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (name, value) in &self.tags {
+            writeln!(f, "[{} \"{}\"]", name, escape(value))?;
+        }
+        writeln!(f)?;
+
+        let mut tokens = Vec::new();
+        if let Some(comment) = &self.comment {
+            comment_tokens(comment, &mut tokens);
+        }
+        line_tokens(&self.start, &self.moves, &MovGen::new(), &mut tokens);
+        tokens.push(self.result.clone());
+
+        let mut line = String::new();
+        for token in tokens {
+            if !line.is_empty() && line.len() + 1 + token.len() > LINE_WIDTH {
+                writeln!(f, "{}", line)?;
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(&token);
+        }
+        writeln!(f, "{}", line)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    San(String),
+    Nag(u8),
+    Comment(String),
+    Open,
+    Close,
+    Result(String),
+}
+
+const RESULTS: [&str; 4] = ["1-0", "0-1", "1/2-1/2", "*"];
+
+// annotation glyphs written as suffixes and the NAG each stands for
+const SUFFIXES: [(&str, u8); 6] = [
+    ("!!", 3),
+    ("??", 4),
+    ("!?", 5),
+    ("?!", 6),
+    ("!", 1),
+    ("?", 2),
+];
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '{' => {
+                let comment: String = chars.by_ref().take_while(|&c| c != '}').collect();
+                // comments span lines, they are kept as words separated by single spaces
+                let words: Vec<&str> = comment.split_whitespace().collect();
+                tokens.push(Token::Comment(words.join(" ")));
+            }
+            ';' => {
+                let comment: String = chars.by_ref().take_while(|&c| c != '\n').collect();
+                tokens.push(Token::Comment(comment.trim().to_string()));
+            }
+            '(' => tokens.push(Token::Open),
+            ')' => tokens.push(Token::Close),
+            '}' => return Err("unmatched }".to_string()),
+            '$' => {
+                let mut digits = String::new();
+                while let Some(d) = chars.next_if(|d| d.is_ascii_digit()) {
+                    digits.push(d);
+                }
+                let nag = digits
+                    .parse()
+                    .map_err(|_| format!("invalid NAG ${}", digits))?;
+                tokens.push(Token::Nag(nag));
+            }
+            _ => {
+                let mut word = c.to_string();
+                while let Some(c) = chars.next_if(|c| !c.is_whitespace() && !"{}();$".contains(*c))
+                {
+                    word.push(c);
+                }
+                word_tokens(&word, &mut tokens);
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+// a word is a result, a move number, a move or a move number glued to a move like `12...Nf6`
+fn word_tokens(word: &str, tokens: &mut Vec<Token>) {
+    if RESULTS.contains(&word) {
+        tokens.push(Token::Result(word.to_string()));
+        return;
+    }
+
+    let mut san = word;
+    if san.starts_with(|c: char| c.is_ascii_digit()) && san.contains('.') {
+        san = san.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.');
+    }
+    if san.is_empty() {
+        return;
+    }
+
+    let suffix = SUFFIXES
+        .iter()
+        .find(|(s, _)| san.ends_with(s) && san.len() > s.len());
+    match suffix {
+        Some((s, nag)) => {
+            tokens.push(Token::San(san[..san.len() - s.len()].to_string()));
+            tokens.push(Token::Nag(*nag));
+        }
+        None => tokens.push(Token::San(san.to_string())),
+    }
+}
+
+struct Parser {
+    tokens: std::vec::IntoIter<Token>,
+    gen: MovGen,
+    index: usize,
+    result: Option<String>,
+}
+
+impl Parser {
+    fn error(&self, ply: usize, message: String) -> PgnError {
+        PgnError {
+            game: self.index,
+            ply,
+            message,
+        }
+    }
+
+    // moves of one line until its `)` or the result, `game` is at the line's first position.
+    // comments before the first move go to `comment`
+    fn line(
+        &mut self,
+        game: &mut Game,
+        moves: &mut Vec<PgnMove>,
+        comment: &mut Option<String>,
+        nested: bool,
+    ) -> Result<(), PgnError> {
+        while let Some(token) = self.tokens.next() {
+            let ply = game.ply() + 1;
+            match token {
+                Token::San(san) => {
+                    let mov = parse_san(game.board(), &san, &self.gen)
+                        .map_err(|e| self.error(ply, e.to_string()))?;
+                    game.make_move(mov);
+                    moves.push(PgnMove::new(mov));
+                }
+                Token::Nag(nag) => match moves.last_mut() {
+                    Some(last) => last.nags.push(nag),
+                    None => return Err(self.error(ply, format!("${} before any move", nag))),
+                },
+                Token::Comment(text) => {
+                    let target = match moves.last_mut() {
+                        Some(last) => &mut last.comment,
+                        None => &mut *comment,
+                    };
+                    match target {
+                        Some(existing) => {
+                            existing.push(' ');
+                            existing.push_str(&text);
+                        }
+                        None => *target = Some(text),
+                    }
+                }
+                Token::Open => {
+                    let Some(last) = moves.last_mut() else {
+                        return Err(self.error(ply, "variation before any move".to_string()));
+                    };
+                    let mut before = game.clone();
+                    before.takeback();
+                    let mut variation = Vec::new();
+                    // a comment at the start of a variation is kept with the move it replaces
+                    let mut leading = None;
+                    self.line(&mut before, &mut variation, &mut leading, true)?;
+                    if let Some(text) = leading {
+                        last.comment = Some(match last.comment.take() {
+                            Some(existing) => format!("{} {}", existing, text),
+                            None => text,
+                        });
+                    }
+                    last.variations.push(variation);
+                }
+                Token::Close if nested => return Ok(()),
+                Token::Close => return Err(self.error(ply, "unmatched )".to_string())),
+                Token::Result(_) if nested => {
+                    return Err(self.error(ply, "result inside a variation".to_string()))
+                }
+                Token::Result(result) => {
+                    self.result = Some(result);
+                    return Ok(());
+                }
+            }
+        }
+
+        if nested {
+            Err(self.error(game.ply(), "unterminated variation".to_string()))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+// `[Name "value"]`
+fn parse_tag(line: &str) -> Option<(String, String)> {
+    let inner = line.trim().strip_prefix('[')?.strip_suffix(']')?;
+    let (name, value) = inner.split_once(char::is_whitespace)?;
+    let value = value.trim().strip_prefix('"')?.strip_suffix('"')?;
+
+    let mut unescaped = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unescaped.push(chars.next()?),
+            c => unescaped.push(c),
+        }
+    }
+    Some((name.to_string(), unescaped))
+}
+
+/// reads one game from its tag lines and movetext, `index` is only used for errors
+pub fn parse_game(index: usize, tags: &[String], movetext: &str) -> Result<PgnGame, PgnError> {
+    let error = |ply, message| PgnError {
+        game: index,
+        ply,
+        message,
+    };
+
+    let tags = tags
+        .iter()
+        .map(|line| parse_tag(line).ok_or_else(|| error(0, format!("invalid tag {}", line))))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let fen = tags.iter().find(|(name, _)| name == "FEN");
+    let start = match fen {
+        Some((_, fen)) => Board::parse(Fen(fen.clone())).map_err(|e| error(0, e.to_string()))?,
+        None => Board::default(),
+    };
+
+    let tokens = tokenize(movetext).map_err(|e| error(0, e))?;
+    let mut parser = Parser {
+        tokens: tokens.into_iter(),
+        gen: MovGen::new(),
+        index,
+        result: None,
+    };
+    let mut game = Game::new(start);
+    let mut moves = Vec::new();
+    let mut comment = None;
+    parser.line(&mut game, &mut moves, &mut comment, false)?;
+
+    // the result tag stands in for a missing termination marker
+    let result = parser
+        .result
+        .or_else(|| {
+            tags.iter()
+                .find(|(name, _)| name == "Result")
+                .map(|(_, v)| v.clone())
+        })
+        .unwrap_or_else(|| "*".to_string());
+
+    Ok(PgnGame {
+        tags,
+        start,
+        comment,
+        moves,
+        result,
+    })
+}
+
+/// Streams the games of a PGN file one at a time. A game that fails to parse is returned as an
+/// error and reading goes on with the next one
+pub struct PgnReader<R: BufRead> {
+    lines: std::io::Lines<R>,
+    // first tag line of the next game, read while looking for the end of the previous one
+    pending: Option<String>,
+    index: usize,
+}
+
+impl<R: BufRead> PgnReader<R> {
+    pub fn new(reader: R) -> Self {
+        PgnReader {
+            lines: reader.lines(),
+            pending: None,
+            index: 0,
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for PgnReader<R> {
+    type Item = Result<PgnGame, PgnError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut tags = Vec::new();
+        let mut movetext = String::new();
+        // open braces, a `[` inside a comment doesn't start a tag
+        let mut depth = 0i32;
+
+        loop {
+            let line = match self.pending.take() {
+                Some(line) => line,
+                None => match self.lines.next() {
+                    Some(Ok(line)) => line,
+                    Some(Err(e)) => {
+                        self.index += 1;
+                        return Some(Err(PgnError {
+                            game: self.index,
+                            ply: 0,
+                            message: e.to_string(),
+                        }));
+                    }
+                    None => break,
+                },
+            };
+
+            // escaped lines are ignored
+            if line.starts_with('%') {
+                continue;
+            }
+            if depth == 0 && line.trim_start().starts_with('[') {
+                if !movetext.trim().is_empty() {
+                    self.pending = Some(line);
+                    break;
+                }
+                tags.push(line);
+            } else {
+                for c in line.chars() {
+                    match c {
+                        '{' => depth += 1,
+                        '}' => depth -= 1,
+                        ';' if depth == 0 => break,
+                        _ => {}
+                    }
+                }
+                movetext.push_str(&line);
+                movetext.push('\n');
+            }
+        }
+
+        if tags.is_empty() && movetext.trim().is_empty() {
+            return None;
+        }
+        self.index += 1;
+        Some(parse_game(self.index, &tags, &movetext))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kelp::game::GameResult;
+
+    const GAMES: &str = r#"[Event "Test \"quoted\""]
+[Site "?"]
+[Result "1-0"]
+
+{Opening} 1. e4 e5 2. Nf3 $1 Nc6 {develops} (2... d6 3. d4 (3. Bc4) 3... exd4)
+3. Bb5!? a6 ; the Morphy defence
+4. Ba4 Nf6 5. O-O 1-0
+
+[Event "Broken"]
+
+1. e4 e5 2. Nf3 Nf6 3. Nc3 {fine so far} Nxe5 *
+
+[Event "From a position"]
+[SetUp "1"]
+[FEN "7k/8/6K1/8/8/8/8/R7 b - - 0 40"]
+
+40... Kg8 41.Ra8# 1-0
+"#;
+
+    #[test]
+    fn read_pgn_test() {
+        let games: Vec<_> = PgnReader::new(GAMES.as_bytes()).collect();
+        assert_eq!(games.len(), 3);
+
+        let game = games[0].as_ref().unwrap();
+        assert_eq!(game.tag("Event"), Some("Test \"quoted\""));
+        assert_eq!(game.result, "1-0");
+        assert_eq!(game.comment.as_deref(), Some("Opening"));
+        assert_eq!(game.moves.len(), 9);
+        assert_eq!(game.moves[2].nags, vec![1]);
+        assert_eq!(game.moves[3].comment.as_deref(), Some("develops"));
+        assert_eq!(game.moves[4].nags, vec![5]);
+        assert_eq!(game.moves[5].comment.as_deref(), Some("the Morphy defence"));
+
+        let variation = &game.moves[3].variations[0];
+        let line: Vec<String> = variation.iter().map(|m| m.mov.to_string()).collect();
+        assert_eq!(line, ["d7d6", "d2d4", "e5d4"]);
+        assert_eq!(variation[1].variations[0][0].mov.to_string(), "f1c4");
+        assert_eq!(game.game().board().to_fen(), {
+            "r1bqkb1r/1ppp1ppp/p1n2n2/4p3/B3P3/5N2/PPPP1PPP/RNBQ1RK1 b kq - 3 5"
+        });
+
+        let error = games[1].as_ref().unwrap_err();
+        assert_eq!((error.game, error.ply), (2, 6));
+        assert!(error.message.contains("Nxe5"));
+
+        let game = games[2].as_ref().unwrap();
+        assert_eq!(game.moves.len(), 2);
+        assert_eq!(
+            game.game().result(),
+            Some(GameResult::Checkmate(Color::White))
+        );
+    }
+
+    #[test]
+    fn write_pgn_test() {
+        let mut game = Game::default();
+        for san in ["f3", "e5", "g4", "Qh4#"] {
+            assert!(game.make_move(game.find_move(san).unwrap()));
+        }
+        let mut pgn = PgnGame::from_game(&game);
+        pgn.set_tag("White", "Fool");
+        assert_eq!(
+            pgn.to_string(),
+            "[Event \"?\"]\n[Site \"?\"]\n[Date \"?\"]\n[Round \"?\"]\n[White \"Fool\"]\n\
+             [Black \"?\"]\n[Result \"0-1\"]\n\n1. f3 e5 2. g4 Qh4# 0-1\n"
+        );
+
+        // games read back the way they were written
+        for read in PgnReader::new(GAMES.as_bytes()).filter_map(Result::ok) {
+            let written = read.to_string();
+            let again = PgnReader::new(written.as_bytes()).next().unwrap().unwrap();
+            assert_eq!(again, read, "{}", written);
+        }
+        let text = PgnReader::new(GAMES.as_bytes())
+            .next()
+            .unwrap()
+            .unwrap()
+            .to_string();
+        assert!(text.ends_with(
+            "{Opening} 1. e4 e5 2. Nf3 $1 Nc6 {develops} (2... d6 3. d4 (3. Bc4) 3... exd4)\n\
+             3. Bb5 $5 a6 {the Morphy defence} 4. Ba4 Nf6 5. O-O 1-0\n"
+        ));
+
+        let game = PgnReader::new(GAMES.as_bytes()).nth(2).unwrap().unwrap();
+        assert!(game.to_string().ends_with("\n\n40... Kg8 41. Ra8# 1-0\n"));
+    }
+
+    #[test]
+    fn long_pgn_test() {
+        let mut game = Game::default();
+        for _ in 0..60 {
+            for san in ["Nf3", "Nf6", "Ng1", "Ng8"] {
+                assert!(game.make_move(game.find_move(san).unwrap()));
+            }
+        }
+        let mut pgn = PgnGame::from_game(&game);
+        pgn.moves[10].comment = Some("word ".repeat(40));
+        let text = pgn.to_string();
+        assert!(text.lines().all(|line| line.len() <= LINE_WIDTH));
+
+        let read = PgnReader::new(text.as_bytes()).next().unwrap().unwrap();
+        assert_eq!(read.moves.len(), 240);
+        assert_eq!(read.game().board(), game.board());
+    }
+}
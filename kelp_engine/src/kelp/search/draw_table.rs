@@ -1,51 +1,42 @@
 use crate::kelp::ZobristKey;
-const MAX_DRAW_TABLE_SIZE: usize = 200;
-
 
+/// hashes of the positions played before the current one, in the game and in the search
+#[derive(Clone, Default)]
 pub struct DrawTable {
-    index: usize,
-    table: [ZobristKey; MAX_DRAW_TABLE_SIZE],
+    table: Vec<ZobristKey>,
 }
 
 impl DrawTable {
     pub fn new() -> Self {
         Self {
-            index: 0,
-            table: [0; MAX_DRAW_TABLE_SIZE],
+            table: Vec::with_capacity(256),
         }
     }
 
     #[inline(always)]
     pub fn push(&mut self, key: ZobristKey) {
-        self.table[self.index] = key;
-        self.index += 1;
+        self.table.push(key);
     }
 
     #[inline(always)]
     pub fn pop(&mut self) -> Option<ZobristKey> {
-        if self.index == 0 {
-            return None;
-        }
-
-        self.index -= 1;
-        Some(self.table[self.index])
+        self.table.pop()
     }
 
     #[inline(always)]
     pub fn clear(&mut self) {
-        self.index = 0;
+        self.table.clear();
     }
 
+    /// true if `key` was seen at least twice since the last irreversible move, which is at most
+    /// `halfmove_clock` plies back. one extra entry is scanned since the root is stored twice
     #[inline(always)]
-    pub fn is_repeat(&self, key: ZobristKey) -> bool {
-        // for i in 0..self.index {
-        //     if self.table[i] == key {
-        //         return true;
-        //     }
-        // }
+    pub fn is_repeat(&self, key: ZobristKey, halfmove_clock: u16) -> bool {
+        let start = self.table.len().saturating_sub(halfmove_clock as usize + 1);
+
         let mut count = 0;
-        for i in 0..self.index {
-            if self.table[i] == key {
+        for &entry in self.table[start..].iter().rev() {
+            if entry == key {
                 count += 1;
 
                 if count >= 2 {
@@ -54,6 +45,28 @@ impl DrawTable {
             }
         }
 
-        count >= 2
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn draw_table_test() {
+        let mut table = DrawTable::new();
+        for i in 0..1000 {
+            table.push(i % 4);
+        }
+        assert!(table.is_repeat(0, 8));
+        // the last 4 plies hold 0 only once
+        assert!(!table.is_repeat(0, 3));
+        assert!(!table.is_repeat(0, 0));
+        assert!(!table.is_repeat(7, 1000));
+
+        assert_eq!(table.pop(), Some(3));
+        table.clear();
+        assert_eq!(table.pop(), None);
     }
 }
@@ -6,6 +6,7 @@ use super::{
 
 use crate::kelp::GamePhase::{self, *};
 
+use crate::kelp::bitbase;
 use crate::kelp::board::board::Board;
 use crate::kelp::board::moves::Move;
 use crate::kelp::board::piece::BoardPiece::{self, *};
@@ -13,6 +14,7 @@ use crate::kelp::board::piece::Color;
 use crate::kelp::board::piece::Color::{Black, White};
 use crate::kelp::kelp_core::bitboard::BitBoard;
 use crate::kelp::mov_gen::generator::MovGen;
+use crate::kelp::syzygy::Wdl;
 use crate::kelp::{Squares, MIRROR};
 use strum::IntoEnumIterator;
 
@@ -27,6 +29,8 @@ const ROOK_ON_SEVENTH_ENDGAME_SCORE: i32 = 40;
 const QUEEN_ON_SEVENTH_OPENING_SCORE: i32 = 10;
 const QUEEN_ON_SEVENTH_ENDGAME_SCORE: i32 = 20;
 const KING_SHIELD_BONUS: i32 = 5;
+// added to the eval of a bitbase win, below tablebase and mate scores
+const KNOWN_WIN: i32 = 10000;
 
 const PASSED_PAWN_BONUS: [i32; 8] = [ 0, 10, 30, 50, 75, 100, 150, 200 ];
 const KING_PAWN_SHIELD_SCORES: [i32; 6] = [ 4, 7, 4, 3, 6, 3];
@@ -534,6 +538,11 @@ impl Eval {
 
     #[inline(always)]
     pub fn evaluate(&self, board: &Board, gen: &MovGen) -> i32 {
+        let known = bitbase::probe(board);
+        if known == Some(Wdl::Draw) {
+            return 0;
+        }
+
         let game_phase_score = self.get_game_phase_score(board);
         let mut game_phase;
 
@@ -615,7 +624,12 @@ impl Eval {
             score = -score;
         }
 
-        score
+        // the eval still tells how far along a known win is
+        match known {
+            Some(Wdl::Win) => score + KNOWN_WIN,
+            Some(Wdl::Loss) => score - KNOWN_WIN,
+            _ => score,
+        }
     }
 }
 
@@ -641,10 +655,9 @@ impl Default for Eval {
 
 
 #[inline(always)]
-pub fn get_mvv_lva(mov: &Move) -> i32 {
-    if mov.capture.is_none() {
-        return 0;
+pub fn get_mvv_lva(mov: &Move, board: &Board) -> i32 {
+    match mov.capture(board) {
+        Some(capture) => MVV_LVA[mov.piece(board) as usize][capture as usize],
+        None => 0,
     }
-
-    MVV_LVA[mov.piece as usize][mov.capture.unwrap() as usize]
 }
@@ -1,6 +1,11 @@
 mod draw_table;
 pub mod eval;
+pub mod move_picker;
 pub mod negamax;
+pub mod root;
+pub mod score;
+pub mod smp;
+pub mod stats;
 mod transposition;
 
 const OPENING_PHASE_SCORE: i32 = 6192;
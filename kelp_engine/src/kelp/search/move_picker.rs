@@ -0,0 +1,221 @@
+use crate::kelp::board::board::Board;
+use crate::kelp::board::moves::{Move, MoveList};
+use crate::kelp::mov_gen::generator::MovGen;
+use crate::kelp::search::eval::get_mvv_lva;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Stage {
+    TtMove,
+    GenerateCaptures,
+    GoodCaptures,
+    Killers,
+    GenerateQuiets,
+    Quiets,
+    BadCaptures,
+    Done,
+}
+
+/// Hands out the moves of a node best first, generating quiets only once every capture and killer
+/// has been searched, since most cutoffs happen before that
+pub struct MovePicker {
+    stage: Stage,
+    tt_move: Option<Move>,
+    killers: [Option<Move>; 2],
+    killer_index: usize,
+    quiets: bool,
+    moves: MoveList,
+    // next move of `moves` to hand out
+    current: usize,
+    // losing captures are moved to the front of `moves`, up to here
+    bad_end: usize,
+}
+
+impl MovePicker {
+    /// every legal move, `tt_move` and `killers` don't have to be legal on the board
+    pub fn new(tt_move: Option<Move>, killers: [Option<Move>; 2]) -> MovePicker {
+        MovePicker {
+            stage: Stage::TtMove,
+            tt_move,
+            killers,
+            killer_index: 0,
+            quiets: true,
+            moves: MoveList::new(),
+            current: 0,
+            bad_end: 0,
+        }
+    }
+
+    /// captures and promotions only, losing captures last
+    pub fn captures() -> MovePicker {
+        MovePicker {
+            quiets: false,
+            stage: Stage::GenerateCaptures,
+            ..MovePicker::new(None, [None; 2])
+        }
+    }
+
+    /// the next move to search, `history` scores the quiets
+    pub fn next(&mut self, board: &Board, gen: &MovGen, history: &[[i32; 64]; 12]) -> Option<Move> {
+        loop {
+            match self.stage {
+                Stage::TtMove => {
+                    self.stage = Stage::GenerateCaptures;
+                    if let Some(mov) = self.tt_move {
+                        if gen.is_legal(board, mov) {
+                            return Some(mov);
+                        }
+                        self.tt_move = None;
+                    }
+                }
+                Stage::GenerateCaptures => {
+                    gen.generate_captures(board, &mut self.moves);
+                    for i in 0..self.moves.len() {
+                        let score = Self::capture_score(self.moves.get(i), board);
+                        self.moves.set_score(i, score);
+                    }
+                    self.stage = Stage::GoodCaptures;
+                }
+                Stage::GoodCaptures => {
+                    let Some(mov) = self.moves.pick(self.current) else {
+                        if self.quiets {
+                            self.stage = Stage::Killers;
+                        } else {
+                            self.stage = Stage::BadCaptures;
+                            self.current = 0;
+                        }
+                        continue;
+                    };
+                    self.current += 1;
+
+                    if Some(mov) == self.tt_move {
+                        continue;
+                    }
+                    // losing captures wait until the quiets are done
+                    if !mov.is_promotion() && gen.see(board, mov) < 0 {
+                        self.moves.swap(self.bad_end, self.current - 1);
+                        self.bad_end += 1;
+                        continue;
+                    }
+                    return Some(mov);
+                }
+                Stage::Killers => {
+                    let Some(&killer) = self.killers.get(self.killer_index) else {
+                        self.stage = Stage::GenerateQuiets;
+                        continue;
+                    };
+                    self.killer_index += 1;
+
+                    match killer {
+                        Some(mov)
+                            if Some(mov) != self.tt_move
+                                && !mov.is_capture()
+                                && !mov.is_promotion()
+                                && gen.is_legal(board, mov) =>
+                        {
+                            return Some(mov);
+                        }
+                        // killers the position doesn't allow can't come up as quiets either
+                        _ => self.killers[self.killer_index - 1] = None,
+                    }
+                }
+                Stage::GenerateQuiets => {
+                    self.current = self.moves.len();
+                    gen.generate_quiets(board, &mut self.moves);
+                    for i in self.current..self.moves.len() {
+                        let mov = self.moves.get(i);
+                        let score = history[mov.piece(board) as usize][mov.to() as usize];
+                        self.moves.set_score(i, score);
+                    }
+                    self.stage = Stage::Quiets;
+                }
+                Stage::Quiets => {
+                    let Some(mov) = self.moves.pick(self.current) else {
+                        self.stage = Stage::BadCaptures;
+                        self.current = 0;
+                        continue;
+                    };
+                    self.current += 1;
+
+                    if Some(mov) != self.tt_move && !self.killers.contains(&Some(mov)) {
+                        return Some(mov);
+                    }
+                }
+                Stage::BadCaptures => {
+                    if self.current >= self.bad_end {
+                        self.stage = Stage::Done;
+                        continue;
+                    }
+                    self.current += 1;
+                    return Some(self.moves.get(self.current - 1));
+                }
+                Stage::Done => return None,
+            }
+        }
+    }
+
+    fn capture_score(mov: Move, board: &Board) -> i32 {
+        let promotion = mov.get_promotion().map_or(0, |p| p.get_value());
+        get_mvv_lva(&mov, board) + promotion
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kelp::board::fen::{Fen, FenParse};
+
+    fn picked(picker: &mut MovePicker, board: &Board, gen: &MovGen) -> Vec<Move> {
+        let history = [[0; 64]; 12];
+        let mut moves = Vec::new();
+        while let Some(mov) = picker.next(board, gen, &history) {
+            moves.push(mov);
+        }
+        moves
+    }
+
+    #[test]
+    fn move_picker_test() {
+        let board = Board::parse(Fen(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1".to_string(),
+        ))
+        .unwrap();
+        let gen = MovGen::new();
+        let mut legal = MoveList::new();
+        gen.generate_legal_moves(&board, &mut legal);
+        let find = |s: &str| *legal.iter().find(|m| m.to_string() == s).unwrap();
+
+        // the tt move comes first, killers right after the winning captures
+        let tt_move = find("e2a6");
+        let killer = find("a2a3");
+        let mut picker = MovePicker::new(Some(tt_move), [Some(killer), Some(tt_move)]);
+        let moves = picked(&mut picker, &board, &gen);
+
+        assert_eq!(moves.len(), legal.len());
+        assert!(legal.iter().all(|m| moves.contains(m)));
+        assert_eq!(moves[0], tt_move);
+        let killer_index = moves.iter().position(|&m| m == killer).unwrap();
+        assert!(moves[1..killer_index].iter().all(|m| m.is_capture()));
+        // f3f6 loses the queen for a knight, g2h3 wins a pawn
+        let bad = moves.iter().position(|m| m.to_string() == "f3f6").unwrap();
+        let good = moves.iter().position(|m| m.to_string() == "g2h3").unwrap();
+        assert!(good < killer_index && bad > killer_index);
+
+        // moves that aren't legal here are skipped
+        let illegal = find("e1g1");
+        let board = Board::parse(Fen(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R b KQkq - 0 1".to_string(),
+        ))
+        .unwrap();
+        let mut picker = MovePicker::new(Some(illegal), [Some(killer), None]);
+        let moves = picked(&mut picker, &board, &gen);
+        legal.clear();
+        gen.generate_legal_moves(&board, &mut legal);
+        assert_eq!(moves.len(), legal.len());
+
+        let mut picker = MovePicker::captures();
+        let moves = picked(&mut picker, &board, &gen);
+        let mut captures = MoveList::new();
+        gen.generate_captures(&board, &mut captures);
+        assert_eq!(moves.len(), captures.len());
+    }
+}
@@ -1,23 +1,61 @@
 use super::draw_table::DrawTable;
+use super::root::{Bound, RootMove};
+use super::score::Score;
+use super::stats::SearchStats;
 use super::transposition::{Entry, EntryType, TranspositionTable};
 use crate::kelp::board::board::Board;
-use crate::kelp::board::moves::Move;
+use crate::kelp::board::moves::{Move, MoveList};
 use crate::kelp::mov_gen::generator::MovGen;
+use crate::kelp::observer::SearchObserver;
 use crate::kelp::search::eval::{get_mvv_lva, Eval};
-use crate::kelp::STOP;
-use std::sync::atomic::Ordering;
+use crate::kelp::search::move_picker::MovePicker;
+use crate::kelp::syzygy::{self, Tablebase, Wdl};
+use crate::kelp::{pv_to_string, STOP};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::cmp::Reverse;
+use std::sync::Arc;
+use std::time::Instant;
 
 pub struct Negamax {
     pub nodes: u64,
+    pub tt_hits: u64,
+    pub tt_misses: u64,
+    pub stats: SearchStats,
+    /// legal root moves of the current search, ordered by the last iteration's scores
+    pub root_moves: Vec<RootMove>,
+    /// search every root move with a full window so all of them get an exact score
+    pub analysis: bool,
     killer_moves: [[Option<Move>; Self::MAX_DEPTH]; 2],
     history_moves: [[i32; 64]; 12],
     pv_length: [usize; Self::MAX_DEPTH],
-    pv_table: [[Option<Move>; Self::MAX_DEPTH]; Self::MAX_DEPTH],
+    // on the heap, it is too big to move around on the stack of a search thread
+    pv_table: Vec<[Option<Move>; Self::MAX_DEPTH]>,
     draw_table: DrawTable,
     eval: Eval,
     pub follow_pv: bool,
     pub score_pv: bool,
-    pub tt: TranspositionTable,
+    pub qsearch_checks: bool,
+    pub tt: Arc<TranspositionTable>,
+    // set for helper threads, nodes are flushed into it periodically
+    shared_nodes: Option<Arc<AtomicU64>>,
+    // stops this search and its helpers, the global STOP stops every search
+    stop: Arc<AtomicBool>,
+    /// search stops once this is reached, checked every few thousand nodes
+    pub deadline: Option<Instant>,
+    /// search stops once this thread searched this many nodes since the last `reset`,
+    /// checked with the deadline
+    pub node_limit: Option<u64>,
+    // nodes since the last `reset`, `nodes` starts over every iteration
+    total_nodes: u64,
+    /// endgame tablebase probed once few enough pieces are left
+    pub tablebase: Option<Arc<dyn Tablebase>>,
+    /// positions with more pieces than this aren't probed
+    pub tb_probe_limit: usize,
+    pub tb_hits: u64,
+    // set for helper threads, tablebase hits are added to it as they happen
+    shared_tb_hits: Option<Arc<AtomicU64>>,
+    // the root moves were filtered by the tablebase, there is nothing left to probe in the tree
+    tb_root: bool,
 }
 
 impl Default for Negamax {
@@ -25,47 +63,443 @@ impl Default for Negamax {
         log::info!("Negamax::default() Initialized");
         Negamax {
             nodes: 0,
+            tt_hits: 0,
+            tt_misses: 0,
+            stats: SearchStats::default(),
+            root_moves: Vec::new(),
+            analysis: false,
             killer_moves: [[None; Self::MAX_DEPTH]; 2],
             history_moves: [[0; 64]; 12],
             pv_length: [0; Self::MAX_DEPTH],
-            pv_table: [[None; Self::MAX_DEPTH]; Self::MAX_DEPTH],
+            pv_table: vec![[None; Self::MAX_DEPTH]; Self::MAX_DEPTH],
             draw_table: DrawTable::new(),
             eval: Eval::default(),
             follow_pv: false,
             score_pv: false,
-            tt: TranspositionTable::new(),
+            qsearch_checks: false,
+            tt: Arc::new(TranspositionTable::new()),
+            shared_nodes: None,
+            stop: Arc::new(AtomicBool::new(false)),
+            deadline: None,
+            node_limit: None,
+            total_nodes: 0,
+            tablebase: None,
+            tb_probe_limit: syzygy::MAX_PIECES,
+            tb_hits: 0,
+            shared_tb_hits: None,
+            tb_root: false,
         }
     }
 }
 
 impl Negamax {
-    pub const MIN: i32 = -50000;
-    pub const MAX: i32 = 50000;
+    pub const MIN: i32 = -Score::INFINITY;
+    pub const MAX: i32 = Score::INFINITY;
     pub const MAX_DEPTH: usize = 128;
-    pub const MATE_VALUE: i32 = 49000;
-    pub const MATE_SCORE: i32 = 48000;
 
     const NULL_MOVE_REDUCTION: usize = 3;
     const FULL_DEPTH: usize = 4;
     const NULL_WINDOW: usize = 2;
+    const DELTA_MARGIN: i32 = 200;
+    const ASPIRATION_WINDOW: i32 = 50;
+    // must be a power of two
+    const NODE_FLUSH: u64 = 1024;
+
+    /// creates a search for a helper thread, sharing the transposition table and game history of `self`
+    pub fn new_helper(
+        &self,
+        shared_nodes: Arc<AtomicU64>,
+        shared_tb_hits: Arc<AtomicU64>,
+    ) -> Negamax {
+        Negamax {
+            nodes: 0,
+            tt_hits: 0,
+            tt_misses: 0,
+            stats: SearchStats::default(),
+            root_moves: Vec::new(),
+            analysis: false,
+            killer_moves: [[None; Self::MAX_DEPTH]; 2],
+            history_moves: [[0; 64]; 12],
+            pv_length: [0; Self::MAX_DEPTH],
+            pv_table: vec![[None; Self::MAX_DEPTH]; Self::MAX_DEPTH],
+            draw_table: self.draw_table.clone(),
+            eval: Eval::default(),
+            follow_pv: false,
+            score_pv: false,
+            qsearch_checks: self.qsearch_checks,
+            tt: Arc::clone(&self.tt),
+            shared_nodes: Some(shared_nodes),
+            stop: Arc::clone(&self.stop),
+            deadline: None,
+            node_limit: None,
+            total_nodes: 0,
+            tablebase: self.tablebase.clone(),
+            tb_probe_limit: self.tb_probe_limit,
+            tb_hits: 0,
+            shared_tb_hits: Some(shared_tb_hits),
+            tb_root: false,
+        }
+    }
+
+    /// true if the search was stopped by uci, its stop flag or its deadline
+    #[inline(always)]
+    pub fn stopped(&self) -> bool {
+        STOP.load(Ordering::Relaxed) || self.stop.load(Ordering::Relaxed)
+    }
+
+    /// static evaluation of `board` from the side to move's point of view
+    pub fn evaluate(&self, board: &Board, gen: &MovGen) -> i32 {
+        self.eval.evaluate(board, gen)
+    }
+
+    /// flag stopping this search and its helpers when set, can be raised from any thread
+    pub fn stop_flag(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.stop)
+    }
+
+    #[inline(always)]
+    fn count_node(&mut self) {
+        self.nodes += 1;
+        self.total_nodes += 1;
+        if self.nodes & (Self::NODE_FLUSH - 1) == 0 {
+            if let Some(shared) = &self.shared_nodes {
+                shared.fetch_add(Self::NODE_FLUSH, Ordering::Relaxed);
+            }
+            if self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+                || self
+                    .node_limit
+                    .is_some_and(|limit| self.total_nodes >= limit)
+            {
+                self.stop.store(true, Ordering::Relaxed);
+            }
+        }
+    }
+
+    fn count_tb_hits(&mut self, hits: u64) {
+        self.tb_hits += hits;
+        if let Some(shared) = &self.shared_tb_hits {
+            shared.fetch_add(hits, Ordering::Relaxed);
+        }
+    }
+
+    #[inline(always)]
+    fn probe_tt(&mut self, hash: u64) -> Option<Entry> {
+        let entry = self.tt.get(hash);
+        if entry.is_some() {
+            self.tt_hits += 1;
+        } else {
+            self.tt_misses += 1;
+        }
+        entry
+    }
+
+    /// searches a single depth inside an aspiration window around the previous iteration's score,
+    /// on failing low or high the same depth is searched again with a full window
+    pub fn aspiration(
+        &mut self,
+        depth: usize,
+        prev_score: Option<i32>,
+        board: &mut Board,
+        gen: &mut MovGen,
+        observer: &mut dyn SearchObserver,
+    ) -> i32 {
+        let (mut alpha, mut beta) = match prev_score {
+            Some(score) if !self.analysis => (
+                score - Self::ASPIRATION_WINDOW,
+                score + Self::ASPIRATION_WINDOW,
+            ),
+            _ => (Self::MIN, Self::MAX),
+        };
+
+        let start_nodes = self.nodes;
+
+        loop {
+            self.follow_pv = true;
+            let score = self.search_root(alpha, beta, depth, board, gen, observer);
+
+            if self.stopped() {
+                return score;
+            }
+
+            if score > alpha && score < beta {
+                self.stats.iteration_nodes.push(self.nodes - start_nodes);
+                return score;
+            }
+
+            alpha = Self::MIN;
+            beta = Self::MAX;
+        }
+    }
 
     #[inline(always)]
-    fn score_move(&mut self, mov: &Move, ply: usize) -> i32 {
+    fn score_move(&mut self, mov: &Move, ply: usize, board: &Board) -> i32 {
         if self.score_pv && self.pv_table[0][ply] == Some(*mov) {
             self.score_pv = false;
             return 20000;
         }
 
 
-        if mov.capture.is_some() {
-            get_mvv_lva(mov) + 10000
+        if mov.is_capture() {
+            get_mvv_lva(mov, board) + 10000
         } else if self.killer_moves[0][ply] == Some(*mov) {
             return 9000;
         } else if self.killer_moves[1][ply] == Some(*mov) {
             return 8000;
         } else {
-            return self.history_moves[mov.piece as usize][mov.to as usize];
+            return self.history_moves[mov.piece(board) as usize][mov.to() as usize];
+        }
+    }
+
+    /// fills `root_moves` with the legal moves of `board` in move ordering order
+    fn init_root_moves(&mut self, board: &mut Board, gen: &mut MovGen) {
+        let mut moves_list = MoveList::new();
+        gen.generate_legal_moves(board, &mut moves_list);
+        for i in 0..moves_list.len() {
+            let score = self.score_move(&moves_list.get(i), 0, board);
+            moves_list.set_score(i, score);
+        }
+
+        self.root_moves.clear();
+        let mut i = 0;
+        while let Some(mov) = moves_list.pick(i) {
+            self.root_moves.push(RootMove::new(mov));
+            i += 1;
         }
+
+        self.tb_root = self.filter_tb_root_moves(board, gen);
+    }
+
+    // the tablebase probe limit, 0 without a tablebase
+    fn tb_pieces(&self) -> usize {
+        self.tablebase
+            .as_ref()
+            .map_or(0, |tb| tb.max_pieces().min(self.tb_probe_limit))
+    }
+
+    /// keeps the root moves with the best DTZ rank, so a won endgame is converted before the
+    /// fifty move rule. false if the root isn't in the tablebase or a probe failed
+    fn filter_tb_root_moves(&mut self, board: &Board, gen: &MovGen) -> bool {
+        let Some(tb) = self.tablebase.clone() else {
+            return false;
+        };
+        if board.info.castle.0 != 0 || board.get_occ().count_bits() as usize > self.tb_pieces() {
+            return false;
+        }
+
+        let halfmove_clock = board.info.get_halfmove_clock();
+        let mut ranks = Vec::with_capacity(self.root_moves.len());
+        self.draw_table.push(board.hash);
+        for root_move in self.root_moves.iter() {
+            let mut next = *board;
+            next.make_move(root_move.mov, false);
+            let dtz = if self
+                .draw_table
+                .is_repeat(next.hash, next.info.get_halfmove_clock())
+            {
+                Some(0)
+            } else {
+                syzygy::move_dtz(tb.as_ref(), board, root_move.mov, gen)
+            };
+            match dtz {
+                Some(dtz) => ranks.push(syzygy::root_rank(dtz, halfmove_clock)),
+                None => break,
+            }
+        }
+        self.draw_table.pop();
+
+        if ranks.len() != self.root_moves.len() {
+            return false;
+        }
+        self.count_tb_hits(ranks.len() as u64);
+        let best = ranks.iter().copied().max().unwrap_or(0);
+        let mut ranks = ranks.into_iter();
+        self.root_moves.retain(|_| ranks.next() == Some(best));
+        true
+    }
+
+    /// the tablebase score of `board` if it is exact or cuts off, stored in the TT. otherwise the
+    /// window is narrowed to the bound the tablebase gives. only positions right after a capture
+    /// or pawn move are probed, they are the ones a table can answer
+    fn probe_tablebase(
+        &mut self,
+        alpha: &mut i32,
+        beta: &mut i32,
+        depth: usize,
+        board: &Board,
+        gen: &MovGen,
+        ply: usize,
+    ) -> Option<i32> {
+        if self.tb_root
+            || board.info.get_halfmove_clock() != 0
+            || board.info.castle.0 != 0
+            || board.get_occ().count_bits() as usize > self.tb_pieces()
+        {
+            return None;
+        }
+
+        let wdl = self.tablebase.as_ref()?.probe_wdl(board, gen)?;
+        self.count_tb_hits(1);
+
+        // cursed wins and blessed losses are draws under the fifty move rule
+        let (score, flag) = match wdl {
+            Wdl::Win => (Score::tb_win_in(ply).0, EntryType::Beta),
+            Wdl::Loss => (Score::tb_loss_in(ply).0, EntryType::Alpha),
+            _ => (0, EntryType::Exact),
+        };
+        // wins and losses are bounds, a faster one may be found by searching on
+        match flag {
+            EntryType::Beta if score < *beta => {
+                *alpha = (*alpha).max(score);
+                return None;
+            }
+            EntryType::Alpha if score > *alpha => {
+                *beta = (*beta).min(score);
+                return None;
+            }
+            _ => {}
+        }
+
+        let depth = (depth + 6).min(Self::MAX_DEPTH - 1);
+        let entry = Entry::new(
+            board.hash,
+            depth as u8,
+            flag,
+            Score(score).to_tt(ply).0,
+            None,
+        );
+        self.tt.insert(board.hash, entry);
+        Some(score)
+    }
+
+    /// negamax at ply 0, records the score, bound, pv and nodes of every root move,
+    /// root moves are searched in order of the previous iteration's scores
+    fn search_root(
+        &mut self,
+        mut alpha: i32,
+        beta: i32,
+        mut depth: usize,
+        board: &mut Board,
+        gen: &mut MovGen,
+        observer: &mut dyn SearchObserver,
+    ) -> i32 {
+        self.pv_length[0] = 0;
+        self.count_node();
+
+        let in_check = board.is_check(gen);
+        if in_check {
+            depth += 1;
+        }
+
+        if self.root_moves.is_empty() {
+            self.init_root_moves(board, gen);
+        }
+
+        if self.root_moves.is_empty() {
+            return if in_check {
+                Score::mated_in(0).0
+            } else {
+                0
+            };
+        }
+
+        let pv_move = self.pv_table[0][0];
+        let mut flag = EntryType::Alpha;
+        let mut best_move = None;
+
+        for i in 0..self.root_moves.len() {
+            let mov = self.root_moves[i].mov;
+            let start_nodes = self.nodes;
+
+            observer.on_currmove(mov, i + 1, depth);
+
+            self.follow_pv = i == 0 && pv_move == Some(mov);
+            self.score_pv = false;
+
+            self.draw_table.push(board.hash);
+            let history = board.make_move(mov, false).unwrap();
+
+            let score = if self.analysis {
+                -self.negamax(Self::MIN, Self::MAX, depth - 1, board, gen, 1)
+            } else if i == 0 {
+                -self.negamax(-beta, -alpha, depth - 1, board, gen, 1)
+            } else {
+                // PVS
+                let mut score = -self.negamax(-alpha - 1, -alpha, depth - 1, board, gen, 1);
+                if score > alpha && score < beta {
+                    self.stats.pvs_researches += 1;
+                    score = -self.negamax(-beta, -alpha, depth - 1, board, gen, 1);
+                }
+                score
+            };
+
+            self.draw_table.pop();
+            board.unmake_move(history);
+
+            if self.stopped() {
+                return 0;
+            }
+
+            if i == 0 {
+                self.stats.searched_nodes += 1;
+            }
+
+            let root_move = &mut self.root_moves[i];
+            root_move.nodes += self.nodes - start_nodes;
+            root_move.depth = depth;
+            root_move.score = Score(score);
+            root_move.bound = if self.analysis {
+                Bound::Exact
+            } else if score <= alpha {
+                Bound::Upper
+            } else if score >= beta {
+                Bound::Lower
+            } else {
+                Bound::Exact
+            };
+            if score > alpha || self.analysis {
+                root_move.pv.truncate(1);
+                root_move
+                    .pv
+                    .extend((1..self.pv_length[1]).filter_map(|j| self.pv_table[1][j]));
+            }
+
+            if score > alpha {
+                if !mov.is_capture() {
+                    self.history_moves[mov.piece(board) as usize][mov.to() as usize] += depth as i32;
+                }
+
+                if self.pv_table[0][0] != Some(mov) {
+                    observer.on_best_move(mov, Score(score), depth);
+                }
+
+                alpha = score;
+                self.pv_table[0][0] = Some(mov);
+                for j in 1..self.pv_length[1] {
+                    self.pv_table[0][j] = self.pv_table[1][j];
+                }
+                self.pv_length[0] = self.pv_length[1].max(1);
+
+                best_move = Some(mov);
+                flag = EntryType::Exact;
+
+                if score >= beta {
+                    self.stats.beta_cutoff(i);
+                    flag = EntryType::Beta;
+                    break;
+                }
+            }
+        }
+
+        // best first, moves that failed low keep the order they were searched in
+        self.root_moves.sort_by_key(|m| Reverse(m.score));
+
+        let score = if flag == EntryType::Beta { beta } else { alpha };
+        let entry = Entry::new(board.hash, depth as u8, flag, score, best_move);
+        self.tt.insert(board.hash, entry);
+
+        score
     }
 
     #[inline(always)]
@@ -81,10 +515,14 @@ impl Negamax {
         self.pv_length[ply] = ply;
         let mut score = Self::MIN;
 
-        let mut entry_def = Entry::default();
-        entry_def.flag = EntryType::Alpha;
+        let mut flag = EntryType::Alpha;
+        let mut best_move = None;
 
-        if ply != 0 && self.draw_table.is_repeat(board.hash) {
+        if ply != 0
+            && self
+                .draw_table
+                .is_repeat(board.hash, board.info.get_halfmove_clock())
+        {
             return 0;
         }
 
@@ -92,29 +530,38 @@ impl Negamax {
             return 0;
         }
 
+        // Mate Distance Pruning
+        if ply != 0 {
+            alpha = alpha.max(Score::mated_in(ply).0);
+            beta = beta.min(Score::mate_in(ply + 1).0);
+            if alpha >= beta {
+                return alpha;
+            }
+        }
+
         let pv_node = beta - alpha > 1;
+        let mut tt_move = None;
 
-        if let Some(entry) = self.tt.get(board.hash) {
+        if let Some(entry) = self.probe_tt(board.hash) {
+            tt_move = entry.best_move(board);
             if entry.depth >= depth as u8 && entry.hash == board.hash && ply != 0 && !pv_node {
+                let tt_score = Score::from_tt(entry.score, ply).0;
                 match entry.flag {
                     EntryType::Exact => {
+                        self.stats.tt_cutoff(entry.flag);
                         self.pv_length[ply] = ply + 1;
-                        self.pv_table[ply][ply] = entry.best_move;
-
-                        if entry.score < -Self::MATE_SCORE {
-                            return entry.score + ply as i32;
-                        } else if entry.score > Self::MATE_SCORE {
-                            return entry.score - ply as i32;
-                        }
-                        return entry.score;
+                        self.pv_table[ply][ply] = entry.best_move(board);
+                        return tt_score;
                     }
                     EntryType::Alpha => {
-                        if entry.score <= alpha {
+                        if tt_score <= alpha {
+                            self.stats.tt_cutoff(entry.flag);
                             return alpha;
                         }
                     }
                     EntryType::Beta => {
-                        if entry.score >= beta {
+                        if tt_score >= beta {
+                            self.stats.tt_cutoff(entry.flag);
                             return beta;
                         }
                     }
@@ -123,14 +570,21 @@ impl Negamax {
         }
 
         if depth == 0 {
-            return self.quiescence(alpha, beta, board, gen, ply + 1);
+            return self.quiescence(alpha, beta, board, gen, ply, 0);
         }
 
         if ply >= Self::MAX_DEPTH - 1 {
             return self.eval.evaluate(board, gen);
         }
 
-        self.nodes += 1;
+        if ply != 0 {
+            if let Some(score) = self.probe_tablebase(&mut alpha, &mut beta, depth, board, gen, ply)
+            {
+                return score;
+            }
+        }
+
+        self.count_node();
         let in_check = board.is_check(gen);
 
         if in_check {
@@ -139,6 +593,7 @@ impl Negamax {
 
         //Null Move Pruning
         if depth >= 3 && in_check == false && ply != 0 {
+            self.stats.null_move_tries += 1;
             self.draw_table.push(board.hash);
             let (enpassant, old_hash) = board.make_null_move();
 
@@ -154,56 +609,43 @@ impl Negamax {
             self.draw_table.pop();
             board.unmake_null_move(enpassant, old_hash);
 
-            if STOP.load(Ordering::Relaxed) {
+            if self.stopped() {
                 return 0;
             }
 
             if score >= beta {
-                let ent = Entry {
-                    hash: board.hash,
-                    depth: depth as u8,
-                    flag: EntryType::Beta,
-                    score: beta,
-                    best_move: None,
-                };
+                self.stats.null_move_cutoffs += 1;
+                let ent = Entry::new(
+                    board.hash,
+                    depth as u8,
+                    EntryType::Beta,
+                    Score(beta).to_tt(ply).0,
+                    None,
+                );
                 self.tt.insert(board.hash, ent);
                 return beta;
             }
         }
 
-        gen.generate_moves(board);
-        let mut moves_list = gen.move_list.clone();
-        let mut legal_moves = 0;
-
+        // the pv move of the last iteration goes first while still on its line
         if self.follow_pv {
-            if moves_list.iter().any(|x| self.pv_table[0][ply] == Some(*x)) {
-                self.score_pv = true;
-                self.follow_pv = true;
+            let pv_move = self.pv_table[0][ply];
+            if pv_move.is_some_and(|m| gen.is_legal(board, m)) {
+                tt_move = pv_move;
             } else {
                 self.follow_pv = false;
             }
         }
 
-        moves_list
-            .0
-            .sort_by(|a, b| self.score_move(b, ply).cmp(&self.score_move(a, ply)));
-
+        let killers = [self.killer_moves[0][ply], self.killer_moves[1][ply]];
+        let mut picker = MovePicker::new(tt_move, killers);
+        let mut legal_moves = 0;
         let mut moves_searched = 0;
 
-        for moves in moves_list.iter() {
+        while let Some(moves) = picker.next(board, gen, &self.history_moves) {
             self.draw_table.push(board.hash);
 
-            let a = board.make_move(*moves, false);
-            if a.is_none() {
-                self.draw_table.pop();
-                continue;
-            }
-
-            if board.is_check_opp(gen) {
-                board.unmake_move(a.unwrap());
-                self.draw_table.pop();
-                continue;
-            }
+            let a = board.make_move(moves, false).unwrap();
 
             legal_moves += 1;
             // self.draw_table.push(board.hash);
@@ -215,10 +657,14 @@ impl Negamax {
                 if moves_searched >= Self::FULL_DEPTH
                     && depth >= Self::NULL_MOVE_REDUCTION
                     && in_check == false
-                    && moves.capture.is_none()
+                    && !moves.is_capture()
                     && moves.is_promotion() == false
                 {
+                    self.stats.lmr_tries += 1;
                     score = -self.negamax(-alpha - 1, -alpha, depth - 2, board, gen, ply + 1);
+                    if score > alpha {
+                        self.stats.lmr_researches += 1;
+                    }
                 } else {
                     score = alpha + 1;
                 }
@@ -228,28 +674,33 @@ impl Negamax {
                     score = -self.negamax(-alpha - 1, -alpha, depth - 1, board, gen, ply + 1);
 
                     if (score > alpha) && (score < beta) {
+                        self.stats.pvs_researches += 1;
                         score = -self.negamax(-beta, -alpha, depth - 1, board, gen, ply + 1);
                     }
                 }
             }
 
             self.draw_table.pop();
-            board.unmake_move(a.unwrap());
+            board.unmake_move(a);
 
-            if STOP.load(Ordering::Relaxed) {
+            if self.stopped() {
                 return 0;
             }
 
+            if moves_searched == 0 {
+                self.stats.searched_nodes += 1;
+            }
             moves_searched += 1;
 
             if score > alpha {
-                if moves.capture.is_none() {
-                    self.history_moves[moves.piece as usize][moves.to as usize] += depth as i32;
+                if !moves.is_capture() {
+                    let piece = moves.piece(board) as usize;
+                    self.history_moves[piece][moves.to() as usize] += depth as i32;
                 }
 
                 alpha = score;
 
-                self.pv_table[ply][ply] = Some(*moves);
+                self.pv_table[ply][ply] = Some(moves);
 
                 for i in (ply + 1)..self.pv_length[ply + 1] {
                     self.pv_table[ply][i] = self.pv_table[ply + 1][i];
@@ -257,22 +708,23 @@ impl Negamax {
 
                 self.pv_length[ply] = self.pv_length[ply + 1];
 
-                entry_def.best_move = Some(*moves);
-                entry_def.flag = EntryType::Exact;
+                best_move = Some(moves);
+                flag = EntryType::Exact;
 
                 if score >= beta {
-                    if moves.capture.is_none() {
+                    self.stats.beta_cutoff(moves_searched - 1);
+                    if !moves.is_capture() {
                         self.killer_moves[1][ply] = self.killer_moves[0][ply];
-                        self.killer_moves[0][ply] = Some(*moves);
+                        self.killer_moves[0][ply] = Some(moves);
                     }
 
-                    let ent = Entry {
-                        hash: board.hash,
-                        depth: depth as u8,
-                        flag: EntryType::Beta,
-                        score: beta,
-                        best_move: None,
-                    };
+                    let ent = Entry::new(
+                        board.hash,
+                        depth as u8,
+                        EntryType::Beta,
+                        Score(beta).to_tt(ply).0,
+                        best_move,
+                    );
                     self.tt.insert(board.hash, ent);
                     return beta;
                 }
@@ -281,33 +733,19 @@ impl Negamax {
 
         if legal_moves == 0 {
             return if in_check {
-                -Self::MATE_VALUE + ply as i32
+                Score::mated_in(ply).0
             } else {
                 0
             };
         }
 
-        let sc = {
-            if entry_def.flag == EntryType::Exact {
-                if alpha > Self::MATE_SCORE {
-                    alpha + ply as i32
-                } else if alpha < -Self::MATE_SCORE {
-                    alpha - ply as i32
-                } else {
-                    alpha
-                }
-            } else {
-                alpha
-            }
-        };
-
-        let entry = Entry {
-            hash: board.hash,
-            depth: depth as u8,
-            flag: entry_def.flag,
-            score: sc,
-            best_move: entry_def.best_move,
-        };
+        let entry = Entry::new(
+            board.hash,
+            depth as u8,
+            flag,
+            Score(alpha).to_tt(ply).0,
+            best_move,
+        );
 
         self.tt.insert(board.hash, entry);
 
@@ -322,62 +760,149 @@ impl Negamax {
         board: &mut Board,
         gen: &mut MovGen,
         ply: usize,
+        qply: usize,
     ) -> i32 {
-        self.nodes += 1;
+        self.count_node();
+        self.stats.qnodes += 1;
 
         if ply > Self::MAX_DEPTH - 1 {
             return self.eval.evaluate(board, gen);
         }
 
-        let eval = self.eval.evaluate(board, gen);
-
-        if eval >= beta {
-            return beta;
+        if let Some(entry) = self.probe_tt(board.hash) {
+            if entry.hash == board.hash {
+                let tt_score = Score::from_tt(entry.score, ply).0;
+                match entry.flag {
+                    EntryType::Exact => {
+                        self.stats.tt_cutoff(entry.flag);
+                        return tt_score;
+                    }
+                    EntryType::Alpha => {
+                        if tt_score <= alpha {
+                            self.stats.tt_cutoff(entry.flag);
+                            return alpha;
+                        }
+                    }
+                    EntryType::Beta => {
+                        if tt_score >= beta {
+                            self.stats.tt_cutoff(entry.flag);
+                            return beta;
+                        }
+                    }
+                }
+            }
         }
 
-        if eval > alpha {
-            alpha = eval;
+        let in_check = board.is_check(gen);
+        let old_alpha = alpha;
+        let mut eval = Self::MIN;
+
+        // no stand pat when in check, every evasion has to be searched
+        if !in_check {
+            eval = self.eval.evaluate(board, gen);
+
+            if eval >= beta {
+                self.store_quiescence(board, EntryType::Beta, beta, None, ply);
+                return beta;
+            }
+
+            if eval > alpha {
+                alpha = eval;
+            }
         }
 
+        let gen_checks = self.qsearch_checks && qply == 0 && !in_check;
 
-        gen.generate_moves(board);
-        let mut moves_list = gen.move_list.clone();
+        // evasions and checks need the quiets too
+        let mut picker = if in_check || gen_checks {
+            MovePicker::new(None, [None; 2])
+        } else {
+            MovePicker::captures()
+        };
+        let mut legal_moves = 0;
+        let mut best_move = None;
 
-        moves_list.0.sort_by(|a, b| {
-            self.score_move(b, ply as usize)
-                .cmp(&self.score_move(a, ply as usize))
-        });
+        while let Some(m) = picker.next(board, gen, &self.history_moves) {
+            let is_quiet = !m.is_capture() && !m.is_promotion();
 
-        for m in moves_list.iter() {
-            self.draw_table.push(board.hash);
-            let a = board.make_move(*m, true);
-            if a.is_none() {
-                self.draw_table.pop();
-                continue;
+            if !in_check {
+                if is_quiet && !gen_checks {
+                    continue;
+                }
+
+                // Delta Pruning
+                if !is_quiet
+                    && !m.is_promotion()
+                    && eval + m.capture(board).unwrap().get_value() + Self::DELTA_MARGIN <= alpha
+                {
+                    continue;
+                }
             }
-            if board.is_check_opp(gen) {
-                board.unmake_move(a.unwrap());
+
+            self.draw_table.push(board.hash);
+            let a = board.make_move(m, false).unwrap();
+
+            legal_moves += 1;
+
+            // quiet moves are only searched at the first ply if they give check
+            if is_quiet && !in_check && !board.is_check(gen) {
+                board.unmake_move(a);
                 self.draw_table.pop();
                 continue;
             }
 
-            let score = -self.quiescence(-beta, -alpha, board, gen, ply + 1);
+            let score = -self.quiescence(-beta, -alpha, board, gen, ply + 1, qply + 1);
 
             self.draw_table.pop();
-            board.unmake_move(a.unwrap());
+            board.unmake_move(a);
 
+            if self.stopped() {
+                return 0;
+            }
 
             if score > alpha {
                 alpha = score;
+                best_move = Some(m);
 
                 if score >= beta {
+                    self.store_quiescence(board, EntryType::Beta, beta, best_move, ply);
                     return beta;
                 }
             }
         }
+
+        if in_check && legal_moves == 0 {
+            return Score::mated_in(ply).0;
+        }
+
+        let flag = if alpha > old_alpha {
+            EntryType::Exact
+        } else {
+            EntryType::Alpha
+        };
+        self.store_quiescence(board, flag, alpha, best_move, ply);
+
         alpha
     }
 
+    #[inline(always)]
+    fn store_quiescence(
+        &mut self,
+        board: &Board,
+        flag: EntryType,
+        score: i32,
+        best_move: Option<Move>,
+        ply: usize,
+    ) {
+        // an entry from the main search is worth more than a quiescence result
+        if self.tt.get(board.hash).is_some_and(|e| e.depth > 0) {
+            return;
+        }
+
+        let entry = Entry::new(board.hash, 0, flag, Score(score).to_tt(ply).0, best_move);
+        self.tt.insert(board.hash, entry);
+    }
+
     pub fn add_draw(&mut self, key: u64) {
         self.draw_table.push(key);
     }
@@ -393,10 +918,16 @@ impl Negamax {
     pub fn reset(&mut self) {
         // for iterative deepening
         self.nodes = 0;
+        self.total_nodes = 0;
+        self.tt_hits = 0;
+        self.tt_misses = 0;
+        self.tb_hits = 0;
+        self.stats.reset();
+        self.root_moves.clear();
         self.killer_moves = [[None; Self::MAX_DEPTH]; 2];
         self.history_moves = [[0; 64]; 12];
         self.pv_length = [0; Self::MAX_DEPTH];
-        self.pv_table = [[None; Self::MAX_DEPTH]; Self::MAX_DEPTH];
+        self.pv_table.fill([None; Self::MAX_DEPTH]);
         self.follow_pv = false;
         self.score_pv = false;
     }
@@ -407,19 +938,15 @@ impl Negamax {
         self.draw_table.clear();
     }
 
+    pub fn get_pv(&self) -> Vec<Move> {
+        self.pv_table[0][..self.pv_length[0]]
+            .iter()
+            .filter_map(|m| *m)
+            .collect()
+    }
+
     pub fn get_pv_str(&self) -> String {
-        let mut pv = String::new();
-        for i in 0..self.pv_length[0] {
-            let pv_str = {
-                if self.pv_table[0][i].is_none() {
-                    continue;
-                }
-                self.pv_table[0][i].unwrap().to_string()
-            };
-            pv.push_str(&pv_str);
-            pv.push(' ');
-        }
-        pv
+        pv_to_string(&self.get_pv())
     }
 
     pub fn get_pv_table(&self, x: usize, y: usize) -> Option<Move> {
@@ -432,11 +959,163 @@ impl Negamax {
     }
 
     #[allow(dead_code)]
-    pub fn print_move_scores(&mut self, gen: &mut MovGen, ply: usize) {
-        let moves_list = gen.move_list.clone();
-
+    pub fn print_move_scores(&mut self, moves_list: &MoveList, ply: usize, board: &Board) {
         for moves in moves_list.iter() {
-            println!("{}: {}", moves, self.score_move(moves, ply));
+            println!("{}: {}", moves, self.score_move(moves, ply, board));
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kelp::board::fen::{Fen, FenParse};
+    use crate::kelp::board::piece::{BoardPiece, Color};
+    use crate::kelp::observer::NoObserver;
+
+    fn qsearch(fen: &str, checks: bool) -> i32 {
+        let mut gen = MovGen::new();
+        let mut board = Board::parse(Fen(fen.to_string())).unwrap();
+        let mut search = Negamax {
+            qsearch_checks: checks,
+            ..Default::default()
+        };
+        search.quiescence(Negamax::MIN, Negamax::MAX, &mut board, &mut gen, 0, 0)
+    }
+
+    #[test]
+    fn quiescence_in_check_test() {
+        // white is checkmated, standing pat would return the even material balance
+        let fools_mate = "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3";
+        assert_eq!(qsearch(fools_mate, false), Score::mated_in(0).0);
+
+        // only evasion is to capture the checking queen
+        let evasion = "4k3/8/8/8/8/8/3q4/R3K3 w - - 0 1";
+        assert!(qsearch(evasion, false) > 0);
+    }
+
+    #[test]
+    fn quiescence_keeps_deeper_entries_test() {
+        let mut gen = MovGen::new();
+        let mut board = Board::parse(Fen(
+            "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4".to_string(),
+        ))
+        .unwrap();
+        let mut search = Negamax::default();
+        // an upper bound that can't cut off the full window
+        let entry = Entry::new(board.hash, 5, EntryType::Alpha, 30, None);
+        search.tt.insert(board.hash, entry);
+
+        search.quiescence(Negamax::MIN, Negamax::MAX, &mut board, &mut gen, 0, 0);
+        assert_eq!(search.tt.get(board.hash), Some(entry));
+    }
+
+    #[test]
+    fn quiescence_checks_test() {
+        let back_rank = "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1";
+        assert_eq!(qsearch(back_rank, true), Score::mate_in(1).0);
+        assert!(!Score(qsearch(back_rank, false)).is_mate());
+    }
+
+    #[test]
+    fn search_stats_test() {
+        let mut gen = MovGen::new();
+        let mut board = Board::parse(Fen(
+            "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4".to_string(),
+        ))
+        .unwrap();
+        let mut search = Negamax::default();
+
+        let mut prev_score = None;
+        for depth in 1..=5 {
+            prev_score = Some(search.aspiration(
+                depth,
+                prev_score,
+                &mut board,
+                &mut gen,
+                &mut NoObserver,
+            ));
+        }
+
+        let stats = &search.stats;
+        assert_eq!(stats.iteration_nodes.len(), 5);
+        assert_eq!(stats.branching_factors().len(), 4);
+        assert!(stats.qnodes > 0 && stats.qnodes < search.nodes);
+        assert!(stats.beta_cutoffs > 0);
+        assert!(stats.first_move_cutoffs <= stats.beta_cutoffs);
+        assert!(stats.lmr_researches <= stats.lmr_tries);
+        assert!(stats.null_move_cutoffs <= stats.null_move_tries);
+
+        search.reset();
+        assert_eq!(search.stats, SearchStats::default());
+    }
+
+    /// the side with the only queen wins, anything else is a draw
+    struct QueenTablebase;
+
+    impl Tablebase for QueenTablebase {
+        fn max_pieces(&self) -> usize {
+            4
+        }
+
+        fn probe_wdl(&self, board: &Board, _gen: &MovGen) -> Option<Wdl> {
+            let white = board.get_bitboard(BoardPiece::WhiteQueen).count_bits() > 0;
+            let black = board.get_bitboard(BoardPiece::BlackQueen).count_bits() > 0;
+            let (ours, theirs) = if board.info.turn == Color::White {
+                (white, black)
+            } else {
+                (black, white)
+            };
+            Some(match (ours, theirs) {
+                (true, false) => Wdl::Win,
+                (false, true) => Wdl::Loss,
+                _ => Wdl::Draw,
+            })
+        }
+
+        fn probe_dtz(&self, board: &Board, gen: &MovGen) -> Option<i32> {
+            self.probe_wdl(board, gen).map(|wdl| wdl as i32 * 5)
+        }
+    }
+
+    fn tb_search(fen: &str, depth: usize, limit: usize) -> (Negamax, i32) {
+        let mut gen = MovGen::new();
+        let mut board = Board::parse(Fen(fen.to_string())).unwrap();
+        let mut search = Negamax {
+            tablebase: Some(Arc::new(QueenTablebase)),
+            tb_probe_limit: limit,
+            ..Default::default()
+        };
+        let score = search.aspiration(depth, None, &mut board, &mut gen, &mut NoObserver);
+        (search, score)
+    }
+
+    #[test]
+    fn tablebase_root_test() {
+        // every move but taking the queen loses
+        let mut gen = MovGen::new();
+        let mut board = Board::parse(Fen("7k/8/8/3q4/8/8/8/K2R4 w - - 0 1".to_string())).unwrap();
+        let mut search = Negamax {
+            tablebase: Some(Arc::new(QueenTablebase)),
+            ..Default::default()
+        };
+        search.init_root_moves(&mut board, &mut gen);
+
+        assert!(search.tb_root);
+        assert_eq!(search.root_moves.len(), 1);
+        assert_eq!(search.root_moves[0].mov.to_string(), "d1d5");
+    }
+
+    #[test]
+    fn tablebase_search_test() {
+        // taking the rook leaves a won tablebase position
+        let fen = "7k/p7/8/3r4/8/8/8/3Q2K1 w - - 0 1";
+        let (search, score) = tb_search(fen, 3, 4);
+        assert!(score >= Score::TB_WIN_SCORE);
+        assert!(search.tb_hits > 0);
+
+        let (search, score) = tb_search(fen, 3, 0);
+        assert!(score < Score::TB_WIN_SCORE);
+        assert_eq!(search.tb_hits, 0);
+    }
+}
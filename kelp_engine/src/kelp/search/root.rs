@@ -0,0 +1,67 @@
+use super::score::Score;
+use crate::kelp::board::moves::Move;
+use crate::kelp::pv_to_string;
+use std::fmt::Display;
+
+/// What the score of a root move is known to be
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default)]
+pub enum Bound {
+    Exact,
+    /// failed low, the real score is at most `score`
+    #[default]
+    Upper,
+    /// failed high, the real score is at least `score`
+    Lower,
+}
+
+/// A legal move at the root, kept across iterations of iterative deepening
+#[derive(Debug, Clone, PartialEq)]
+pub struct RootMove {
+    pub mov: Move,
+    /// score from the last iteration this move was searched in
+    pub score: Score,
+    pub bound: Bound,
+    /// depth of the last iteration this move was searched in, 0 if it never was
+    pub depth: usize,
+    /// principal variation starting with `mov`
+    pub pv: Vec<Move>,
+    /// nodes spent on this move over all iterations
+    pub nodes: u64,
+}
+
+impl RootMove {
+    pub fn new(mov: Move) -> Self {
+        RootMove {
+            mov,
+            score: Score(-Score::INFINITY),
+            bound: Bound::Upper,
+            depth: 0,
+            pv: vec![mov],
+            nodes: 0,
+        }
+    }
+
+    pub fn get_pv_str(&self) -> String {
+        pv_to_string(&self.pv)
+    }
+}
+
+impl Display for RootMove {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let bound = match self.bound {
+            Bound::Exact => "",
+            Bound::Upper => " upperbound",
+            Bound::Lower => " lowerbound",
+        };
+        write!(
+            f,
+            "{} depth {} score {}{} nodes {} pv {}",
+            self.mov,
+            self.depth,
+            self.score,
+            bound,
+            self.nodes,
+            self.get_pv_str()
+        )
+    }
+}
@@ -0,0 +1,149 @@
+use std::fmt::Display;
+
+/// Search score from the side to move's point of view, in centipawns.
+/// Mate scores are encoded as `MATE_VALUE - ply` where ply is the distance from the root to the mate.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Default)]
+pub struct Score(pub i32);
+
+impl Score {
+    pub const INFINITY: i32 = 50000;
+    pub const MATE_VALUE: i32 = 49000;
+    /// any score above this (or below its negation) is a mate score
+    pub const MATE_SCORE: i32 = 48000;
+    /// a win known from the tablebases, scored below every mate
+    pub const TB_WIN: i32 = 47000;
+    /// any score above this (or below its negation) is a tablebase win or a mate
+    pub const TB_WIN_SCORE: i32 = 46000;
+
+    #[inline(always)]
+    pub const fn cp(score: i32) -> Score {
+        Score(score)
+    }
+
+    /// score of delivering mate `ply` plies away from the root
+    #[inline(always)]
+    pub const fn mate_in(ply: usize) -> Score {
+        Score(Self::MATE_VALUE - ply as i32)
+    }
+
+    /// score of being mated `ply` plies away from the root
+    #[inline(always)]
+    pub const fn mated_in(ply: usize) -> Score {
+        Score(-Self::MATE_VALUE + ply as i32)
+    }
+
+    /// score of a tablebase win reached `ply` plies away from the root
+    #[inline(always)]
+    pub const fn tb_win_in(ply: usize) -> Score {
+        Score(Self::TB_WIN - ply as i32)
+    }
+
+    /// score of a tablebase loss reached `ply` plies away from the root
+    #[inline(always)]
+    pub const fn tb_loss_in(ply: usize) -> Score {
+        Score(-Self::TB_WIN + ply as i32)
+    }
+
+    #[inline(always)]
+    pub fn is_mate(&self) -> bool {
+        self.0.abs() > Self::MATE_SCORE && self.0.abs() <= Self::MATE_VALUE
+    }
+
+    /// number of moves (not plies) until mate, negative if the side to move is getting mated
+    pub fn mate_moves(&self) -> Option<i32> {
+        if !self.is_mate() {
+            return None;
+        }
+
+        if self.0 > 0 {
+            Some((Self::MATE_VALUE - self.0 + 1) / 2)
+        } else {
+            Some(-(Self::MATE_VALUE + self.0) / 2)
+        }
+    }
+
+    /// converts a root relative mate or tablebase score to one relative to the node at `ply` for storing in the TT
+    #[inline(always)]
+    pub fn to_tt(self, ply: usize) -> Score {
+        if self.0 > Self::TB_WIN_SCORE {
+            Score(self.0 + ply as i32)
+        } else if self.0 < -Self::TB_WIN_SCORE {
+            Score(self.0 - ply as i32)
+        } else {
+            self
+        }
+    }
+
+    /// converts a node relative mate or tablebase score read from the TT back to a root relative one
+    #[inline(always)]
+    pub fn from_tt(score: i32, ply: usize) -> Score {
+        if score > Self::TB_WIN_SCORE {
+            Score(score - ply as i32)
+        } else if score < -Self::TB_WIN_SCORE {
+            Score(score + ply as i32)
+        } else {
+            Score(score)
+        }
+    }
+}
+
+impl From<i32> for Score {
+    fn from(value: i32) -> Self {
+        Score(value)
+    }
+}
+
+impl From<Score> for i32 {
+    fn from(value: Score) -> Self {
+        value.0
+    }
+}
+
+/// UCI representation, `cp <x>` or `mate <n>`
+impl Display for Score {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.mate_moves() {
+            Some(moves) => write!(f, "mate {}", moves),
+            None => write!(f, "cp {}", self.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mate_moves_test() {
+        assert_eq!(Score::mate_in(1).mate_moves(), Some(1));
+        assert_eq!(Score::mate_in(3).mate_moves(), Some(2));
+        assert_eq!(Score::mate_in(5).mate_moves(), Some(3));
+        assert_eq!(Score::mated_in(2).mate_moves(), Some(-1));
+        assert_eq!(Score::mated_in(4).mate_moves(), Some(-2));
+        assert_eq!(Score::cp(150).mate_moves(), None);
+
+        assert_eq!(Score::mate_in(3).to_string(), "mate 2");
+        assert_eq!(Score::mated_in(2).to_string(), "mate -1");
+        assert_eq!(Score::cp(-35).to_string(), "cp -35");
+    }
+
+    #[test]
+    fn tt_adjust_test() {
+        // mate found 7 plies from the root, stored at a node 4 plies deep
+        let score = Score::mate_in(7);
+        let stored = score.to_tt(4);
+        assert_eq!(stored, Score::mate_in(3));
+        assert_eq!(Score::from_tt(stored.0, 4), score);
+        // same node reached at a different ply
+        assert_eq!(Score::from_tt(stored.0, 2), Score::mate_in(5));
+
+        let score = Score::mated_in(6);
+        assert_eq!(Score::from_tt(score.to_tt(6).0, 6), score);
+        assert_eq!(Score::cp(20).to_tt(10), Score::cp(20));
+
+        let score = Score::tb_loss_in(9);
+        assert_eq!(score.to_tt(5), Score::tb_loss_in(4));
+        assert_eq!(Score::from_tt(score.to_tt(5).0, 3), Score::tb_loss_in(7));
+        assert!(!score.is_mate());
+    }
+}
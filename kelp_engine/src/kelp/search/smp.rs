@@ -0,0 +1,81 @@
+use super::negamax::Negamax;
+use crate::kelp::board::board::Board;
+use crate::kelp::mov_gen::generator::MovGen;
+use crate::kelp::observer::NoObserver;
+use std::sync::atomic::AtomicU64;
+use std::sync::Arc;
+use std::thread::{self, Scope};
+
+// same as the main thread's stack on most platforms, search recursion can get deep
+const HELPER_STACK_SIZE: usize = 8 * 1024 * 1024;
+
+/// Lazy SMP helper, searches the same position as the main thread with its own board,
+/// move generator and heuristics, sharing only the transposition table
+pub struct Helper {
+    id: usize,
+    board: Board,
+    search: Box<Negamax>,
+}
+
+impl Helper {
+    pub fn new(
+        id: usize,
+        board: &Board,
+        main: &Negamax,
+        shared_nodes: Arc<AtomicU64>,
+        shared_tb_hits: Arc<AtomicU64>,
+    ) -> Self {
+        Helper {
+            id,
+            board: *board,
+            search: Box::new(main.new_helper(shared_nodes, shared_tb_hits)),
+        }
+    }
+
+    /// iterative deepening until `max_depth` or until the search is stopped,
+    /// odd helpers search one ply deeper than even ones so threads don't all walk the same tree
+    pub fn run(mut self, max_depth: usize) {
+        let mut gen = MovGen::new();
+        let mut prev_score = None;
+        let mut depth = 1 + self.id % 2;
+
+        while depth <= max_depth && !self.search.stopped() {
+            let score = self.search.aspiration(
+                depth,
+                prev_score,
+                &mut self.board,
+                &mut gen,
+                &mut NoObserver,
+            );
+            prev_score = Some(score);
+            depth += 1;
+        }
+    }
+}
+
+/// spawns `threads - 1` helpers searching the current position inside `scope`,
+/// they run until the stop flag of `main` or the global STOP is set
+pub fn spawn_helpers<'scope>(
+    scope: &'scope Scope<'scope, '_>,
+    threads: usize,
+    board: &Board,
+    main: &Negamax,
+    max_depth: usize,
+    shared_nodes: &Arc<AtomicU64>,
+    shared_tb_hits: &Arc<AtomicU64>,
+) {
+    for id in 1..threads {
+        let helper = Helper::new(
+            id,
+            board,
+            main,
+            Arc::clone(shared_nodes),
+            Arc::clone(shared_tb_hits),
+        );
+        thread::Builder::new()
+            .name(format!("kelp-helper-{}", id))
+            .stack_size(HELPER_STACK_SIZE)
+            .spawn_scoped(scope, move || helper.run(max_depth))
+            .expect("failed to spawn search thread");
+    }
+}
@@ -0,0 +1,171 @@
+use super::transposition::EntryType;
+use std::fmt::Display;
+
+/// Counters collected by the search, reset at the start of every search.
+/// Only the main thread's counters are reported when searching with helpers.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SearchStats {
+    /// quiescence nodes, these are also counted in `Negamax::nodes`
+    pub qnodes: u64,
+    /// tt cutoffs indexed by `EntryType` (exact, alpha/upper, beta/lower)
+    pub tt_cutoffs: [u64; 3],
+    /// interior nodes in which at least one move was searched
+    pub searched_nodes: u64,
+    pub beta_cutoffs: u64,
+    /// beta cutoffs produced by the first move searched
+    pub first_move_cutoffs: u64,
+    /// sum of the index of the move producing each beta cutoff
+    pub cutoff_index_sum: u64,
+    pub null_move_tries: u64,
+    pub null_move_cutoffs: u64,
+    pub lmr_tries: u64,
+    /// reduced searches that beat alpha and had to be searched again at full depth
+    pub lmr_researches: u64,
+    /// null window searches that landed inside the window and had to be searched again
+    pub pvs_researches: u64,
+    /// nodes spent on each completed iteration, index 0 is depth 1
+    pub iteration_nodes: Vec<u64>,
+}
+
+// as a percentage, 0 when nothing was counted
+fn pct(part: u64, total: u64) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        part as f64 * 100.0 / total as f64
+    }
+}
+
+impl SearchStats {
+    pub fn reset(&mut self) {
+        *self = SearchStats::default();
+    }
+
+    #[inline(always)]
+    pub(crate) fn tt_cutoff(&mut self, flag: EntryType) {
+        self.tt_cutoffs[flag as usize] += 1;
+    }
+
+    /// records a beta cutoff by the move at `index` in the ordered move list
+    #[inline(always)]
+    pub fn beta_cutoff(&mut self, index: usize) {
+        self.beta_cutoffs += 1;
+        self.cutoff_index_sum += index as u64;
+        if index == 0 {
+            self.first_move_cutoffs += 1;
+        }
+    }
+
+    /// percentage of searched nodes that failed high
+    pub fn beta_cutoff_rate(&self) -> f64 {
+        pct(self.beta_cutoffs, self.searched_nodes)
+    }
+
+    /// percentage of beta cutoffs caused by the first move, a measure of move ordering
+    pub fn first_move_cutoff_rate(&self) -> f64 {
+        pct(self.first_move_cutoffs, self.beta_cutoffs)
+    }
+
+    /// average index of the move producing a beta cutoff
+    pub fn avg_cutoff_index(&self) -> f64 {
+        if self.beta_cutoffs == 0 {
+            0.0
+        } else {
+            self.cutoff_index_sum as f64 / self.beta_cutoffs as f64
+        }
+    }
+
+    pub fn null_move_success_rate(&self) -> f64 {
+        pct(self.null_move_cutoffs, self.null_move_tries)
+    }
+
+    /// percentage of reduced searches that were not re-searched
+    pub fn lmr_success_rate(&self) -> f64 {
+        pct(self.lmr_tries - self.lmr_researches, self.lmr_tries)
+    }
+
+    pub fn lmr_research_rate(&self) -> f64 {
+        pct(self.lmr_researches, self.lmr_tries)
+    }
+
+    /// effective branching factor of every iteration after the first,
+    /// nodes of the iteration divided by nodes of the previous one
+    pub fn branching_factors(&self) -> Vec<f64> {
+        self.iteration_nodes
+            .windows(2)
+            .map(|w| {
+                if w[0] == 0 {
+                    0.0
+                } else {
+                    w[1] as f64 / w[0] as f64
+                }
+            })
+            .collect()
+    }
+}
+
+/// single line summary, meant to be sent as an `info string`
+impl Display for SearchStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "qnodes {} ttcut exact {} upper {} lower {} betacut {} ({:.1}%) firstcut {:.1}% \
+             cutidx {:.2} null {}/{} ({:.1}%) lmr {} success {:.1}% research {:.1}% pvsresearch {} ebf",
+            self.qnodes,
+            self.tt_cutoffs[EntryType::Exact as usize],
+            self.tt_cutoffs[EntryType::Alpha as usize],
+            self.tt_cutoffs[EntryType::Beta as usize],
+            self.beta_cutoffs,
+            self.beta_cutoff_rate(),
+            self.first_move_cutoff_rate(),
+            self.avg_cutoff_index(),
+            self.null_move_cutoffs,
+            self.null_move_tries,
+            self.null_move_success_rate(),
+            self.lmr_tries,
+            self.lmr_success_rate(),
+            self.lmr_research_rate(),
+            self.pvs_researches,
+        )?;
+
+        for ebf in self.branching_factors() {
+            write!(f, " {:.2}", ebf)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rates_test() {
+        let mut stats = SearchStats::default();
+        assert_eq!(stats.first_move_cutoff_rate(), 0.0);
+        assert_eq!(stats.avg_cutoff_index(), 0.0);
+
+        stats.searched_nodes = 10;
+        stats.beta_cutoff(0);
+        stats.beta_cutoff(0);
+        stats.beta_cutoff(0);
+        stats.beta_cutoff(3);
+        assert_eq!(stats.beta_cutoff_rate(), 40.0);
+        assert_eq!(stats.first_move_cutoff_rate(), 75.0);
+        assert_eq!(stats.avg_cutoff_index(), 0.75);
+
+        stats.lmr_tries = 8;
+        stats.lmr_researches = 2;
+        assert_eq!(stats.lmr_success_rate(), 75.0);
+        assert_eq!(stats.lmr_research_rate(), 25.0);
+
+        stats.iteration_nodes = vec![20, 100, 400];
+        assert_eq!(stats.branching_factors(), vec![5.0, 4.0]);
+
+        stats.tt_cutoff(EntryType::Beta);
+        assert_eq!(stats.tt_cutoffs, [0, 0, 1]);
+
+        stats.reset();
+        assert_eq!(stats, SearchStats::default());
+    }
+}
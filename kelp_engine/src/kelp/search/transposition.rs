@@ -1,11 +1,14 @@
+use crate::kelp::board::board::Board;
 use crate::kelp::board::moves::Move;
-use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
 
 const SIZE_MB: usize = 64;
 const SIZE_BYTES: usize = SIZE_MB * 1024 * 1024;
-const BYTES_PER_ENTRY: usize = std::mem::size_of::<Entry>();
+const BYTES_PER_ENTRY: usize = std::mem::size_of::<AtomicEntry>();
 const BYTES_PER_KB: usize = 1024;
 const BYTES_PER_MB: usize = BYTES_PER_KB * 1024;
+const HASH_FULL_SAMPLE: usize = 1000;
+const USED_BIT: u64 = 1 << 42;
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Default)]
 pub enum EntryType {
@@ -15,87 +18,173 @@ pub enum EntryType {
     Beta,
 }
 
+impl EntryType {
+    fn from_bits(bits: u64) -> EntryType {
+        match bits {
+            0 => EntryType::Exact,
+            1 => EntryType::Alpha,
+            _ => EntryType::Beta,
+        }
+    }
+}
+
 #[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
 pub struct Entry {
     pub hash: u64,
     pub depth: u8,
     pub flag: EntryType,
     pub score: i32,
-    pub best_move: Option<Move>,
+    /// best move packed by `Move::pack`, 0 if there is none
+    pub best_move: u16,
 }
+
+impl Entry {
+    pub fn new(hash: u64, depth: u8, flag: EntryType, score: i32, best_move: Option<Move>) -> Self {
+        Entry {
+            hash,
+            depth,
+            flag,
+            score,
+            best_move: best_move.map_or(0, |m| m.pack()),
+        }
+    }
+
+    /// unpacks the stored best move for the position it was stored for
+    pub fn best_move(&self, board: &Board) -> Option<Move> {
+        if self.best_move == 0 {
+            return None;
+        }
+        Move::unpack(self.best_move, board)
+    }
+
+    // score: 0..32, depth: 32..40, flag: 40..42, used: 42, move: 48..64
+    fn to_data(self) -> u64 {
+        (self.score as u32 as u64)
+            | (self.depth as u64) << 32
+            | (self.flag as u64) << 40
+            | USED_BIT
+            | (self.best_move as u64) << 48
+    }
+
+    fn from_data(hash: u64, data: u64) -> Entry {
+        Entry {
+            hash,
+            score: data as u32 as i32,
+            depth: (data >> 32) as u8,
+            flag: EntryType::from_bits((data >> 40) & 0b11),
+            best_move: (data >> 48) as u16,
+        }
+    }
+}
+
+/// Lock free slot, the key is stored xor'd with the data so a torn write from another
+/// thread fails verification instead of returning a mismatched entry
 #[derive(Debug, Default)]
+struct AtomicEntry {
+    key: AtomicU64,
+    data: AtomicU64,
+}
+
+/// Transposition table shared between search threads
+#[derive(Debug)]
 pub struct TranspositionTable {
-    table: HashMap<u64, Entry>,
+    table: Vec<AtomicEntry>,
     size: usize,
-    hits: u64,
-    misses: u64,
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl TranspositionTable {
     pub fn new() -> Self {
         log::info!("Initializing transposition table");
-        let num_entries = SIZE_BYTES / std::mem::size_of::<Entry>();
+        let num_entries = SIZE_BYTES / BYTES_PER_ENTRY;
         TranspositionTable {
-            table: HashMap::with_capacity(num_entries),
+            table: std::iter::repeat_with(AtomicEntry::default)
+                .take(num_entries)
+                .collect(),
             size: num_entries,
-            hits: 0,
-            misses: 0,
         }
     }
 
-    pub fn clear(&mut self) {
-        self.table.clear();
+    pub fn clear(&self) {
+        for entry in self.table.iter() {
+            entry.key.store(0, Ordering::Relaxed);
+            entry.data.store(0, Ordering::Relaxed);
+        }
     }
 
     #[inline(always)]
-    pub fn get(&mut self, hash: u64) -> Option<&Entry> {
-        let entry = self.table.get(&hash);
-        if entry.is_some() {
-            if entry.unwrap().hash == hash {
-                self.hits += 1;
-                return entry;
-            }
-        } else {
-            self.misses += 1;
-        }
-        None
+    fn index(&self, hash: u64) -> usize {
+        (hash % self.size as u64) as usize
     }
 
     #[inline(always)]
-    pub fn insert(&mut self, hash: u64, entry: Entry) {
-        if self.table.len() >= self.size {
-            self.table.clear(); // idk if this is the best way to do this
+    pub fn get(&self, hash: u64) -> Option<Entry> {
+        let slot = &self.table[self.index(hash)];
+        let data = slot.data.load(Ordering::Relaxed);
+        let key = slot.key.load(Ordering::Relaxed);
+
+        if data != 0 && key ^ data == hash {
+            return Some(Entry::from_data(hash, data));
         }
-        self.table.insert(hash, entry);
+        None
     }
 
-    pub fn get_size(&self) -> usize {
-        self.table.len()
+    #[inline(always)]
+    pub fn insert(&self, hash: u64, entry: Entry) {
+        let slot = &self.table[self.index(hash)];
+        let data = entry.to_data();
+        slot.key.store(hash ^ data, Ordering::Relaxed);
+        slot.data.store(data, Ordering::Relaxed);
     }
 
-    pub fn get_hits(&self) -> u64 {
-        self.hits
+    pub fn get_size(&self) -> usize {
+        self.size
     }
 
-    pub fn get_misses(&self) -> u64 {
-        self.misses
+    /// estimated used size in MB
+    pub fn get_hashmap_size_mb(&self) -> f64 {
+        self.get_hash_full_percentage() / 100.0 * (self.size * BYTES_PER_ENTRY) as f64
+            / BYTES_PER_MB as f64
     }
 
-    pub fn reset_hits_and_misses(&mut self) {
-        self.hits = 0;
-        self.misses = 0;
+    /// estimated fill percentage, sampled from the first entries of the table
+    pub fn get_hash_full_percentage(&self) -> f64 {
+        let sample = HASH_FULL_SAMPLE.min(self.size);
+        let used = self.table[..sample]
+            .iter()
+            .filter(|e| e.data.load(Ordering::Relaxed) != 0)
+            .count();
+        used as f64 / sample as f64 * 100.0
     }
+}
 
-    pub fn get_hashmap_size_mb(&self) -> f64 {
-        self.table.len() as f64 * BYTES_PER_ENTRY as f64 / BYTES_PER_MB as f64
-    }
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    // TODO: remove this function
-    pub fn get_total_entries(&self) -> usize {
-        self.table.len()
-    }
+    #[test]
+    fn pack_entry_test() {
+        let entry = Entry {
+            hash: 0xDEAD_BEEF_1234_5678,
+            depth: 12,
+            flag: EntryType::Beta,
+            score: -48990,
+            best_move: 0xA5C3,
+        };
+        let data = entry.to_data();
+        assert_eq!(Entry::from_data(entry.hash, data), entry);
 
-    pub fn get_hash_full_percentage(&self) -> f64 {
-        self.table.len() as f64 / self.size as f64 * 100.0
+        let tt = TranspositionTable::new();
+        tt.insert(entry.hash, entry);
+        assert_eq!(tt.get(entry.hash), Some(entry));
+        // same slot, different key
+        assert_eq!(tt.get(entry.hash + tt.get_size() as u64), None);
+        tt.clear();
+        assert_eq!(tt.get(entry.hash), None);
     }
 }
@@ -0,0 +1,548 @@
+mod table;
+
+use crate::kelp::board::board::Board;
+use crate::kelp::board::moves::{Move, MoveList};
+use crate::kelp::board::piece::BoardPiece;
+use crate::kelp::mov_gen::generator::MovGen;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::ops::Neg;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use table::{board_material, material_name, parse_material, Kind, Lookup, Table};
+
+pub use table::MAX_PIECES;
+
+/// Result of a tablebase position for the side to move. cursed wins and blessed losses are
+/// wins and losses the fifty move rule turns into draws
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum Wdl {
+    Loss = -2,
+    BlessedLoss = -1,
+    Draw = 0,
+    CursedWin = 1,
+    Win = 2,
+}
+
+impl Wdl {
+    fn from_value(value: i32) -> Option<Wdl> {
+        Some(match value {
+            -2 => Wdl::Loss,
+            -1 => Wdl::BlessedLoss,
+            0 => Wdl::Draw,
+            1 => Wdl::CursedWin,
+            2 => Wdl::Win,
+            _ => return None,
+        })
+    }
+
+    /// DTZ of a position whose best move resets the fifty move counter
+    pub fn dtz_before_zeroing(self) -> i32 {
+        match self {
+            Wdl::Loss => -1,
+            Wdl::BlessedLoss => -101,
+            Wdl::Draw => 0,
+            Wdl::CursedWin => 101,
+            Wdl::Win => 1,
+        }
+    }
+}
+
+impl Neg for Wdl {
+    type Output = Wdl;
+
+    fn neg(self) -> Wdl {
+        Wdl::from_value(-(self as i32)).unwrap()
+    }
+}
+
+/// Perfect endgame knowledge the search can probe, shared by every search thread
+pub trait Tablebase: Send + Sync {
+    /// most pieces, kings included, of a position that can be probed
+    fn max_pieces(&self) -> usize;
+
+    /// win/draw/loss of `board`, `None` if it isn't in the tablebase or has castling rights
+    fn probe_wdl(&self, board: &Board, gen: &MovGen) -> Option<Wdl>;
+
+    /// plies until a win or loss resets the fifty move counter, positive if the side to move
+    /// wins and 0 for draws. may be one ply more than the exact distance when the tablebase
+    /// only stores moves
+    fn probe_dtz(&self, board: &Board, gen: &MovGen) -> Option<i32>;
+}
+
+/// DTZ of the position after `mov`, counted from before the move and from the point of view of
+/// the side playing it. `None` if a probe failed
+pub fn move_dtz(tablebase: &dyn Tablebase, board: &Board, mov: Move, gen: &MovGen) -> Option<i32> {
+    let mut next = *board;
+    next.make_move(mov, false);
+
+    let dtz = if next.info.get_halfmove_clock() == 0 {
+        (-tablebase.probe_wdl(&next, gen)?).dtz_before_zeroing()
+    } else {
+        let dtz = -tablebase.probe_dtz(&next, gen)?;
+        dtz + dtz.signum()
+    };
+
+    // mate ends the game right away
+    if dtz == 2 && next.is_check(gen) && !has_legal_moves(&next, gen) {
+        return Some(1);
+    }
+    Some(dtz)
+}
+
+/// how good a root move with `dtz` is when the fifty move counter is at `halfmove_clock`. wins
+/// that can be converted in time rank highest and all the same, wins the fifty move rule
+/// turns into draws rank better the closer they get, losses the other way round
+pub fn root_rank(dtz: i32, halfmove_clock: u16) -> i32 {
+    const MAX_DTZ: i32 = 1 << 18;
+    let clock = halfmove_clock as i32;
+
+    if dtz > 0 {
+        if dtz + clock <= 99 {
+            MAX_DTZ
+        } else {
+            MAX_DTZ / 2 - (dtz + clock)
+        }
+    } else if dtz < 0 {
+        if -dtz * 2 + clock < 100 {
+            -MAX_DTZ
+        } else {
+            -MAX_DTZ / 2 + (-dtz + clock)
+        }
+    } else {
+        0
+    }
+}
+
+fn has_legal_moves(board: &Board, gen: &MovGen) -> bool {
+    let mut moves = MoveList::new();
+    gen.generate_legal_moves(board, &mut moves);
+    !moves.is_empty()
+}
+
+fn is_zeroing(board: &Board, mov: Move) -> bool {
+    mov.is_capture()
+        || matches!(
+            mov.piece(board),
+            BoardPiece::WhitePawn | BoardPiece::BlackPawn
+        )
+}
+
+// the files of one material, read the first time they are probed
+struct TableFiles {
+    material: table::Material,
+    wdl: PathBuf,
+    dtz: Option<PathBuf>,
+    // `None` once a file turned out to be unreadable or corrupt
+    wdl_table: OnceLock<Option<Table>>,
+    dtz_table: OnceLock<Option<Table>>,
+}
+
+fn load(path: &Path, kind: Kind, material: table::Material) -> Option<Table> {
+    match fs::read(path).and_then(|bytes| Table::new(bytes, kind, material)) {
+        Ok(table) => Some(table),
+        Err(e) => {
+            log::warn!("can't read tablebase {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// Syzygy endgame tablebases, `.rtbw` files for win/draw/loss and `.rtbz` files for the
+/// distance to zeroing. tables are read into memory the first time they are probed
+#[derive(Default)]
+pub struct Syzygy {
+    tables: HashMap<String, TableFiles>,
+    max_pieces: usize,
+}
+
+impl Syzygy {
+    /// finds the tables in `paths`, directories separated by `:` (`;` on windows)
+    pub fn open(paths: &str) -> io::Result<Syzygy> {
+        let separator = if cfg!(windows) { ';' } else { ':' };
+        let mut files: HashMap<String, (Option<PathBuf>, Option<PathBuf>)> = HashMap::new();
+
+        for dir in paths.split(separator).filter(|dir| !dir.is_empty()) {
+            for entry in fs::read_dir(dir)? {
+                let path = entry?.path();
+                let stem = path.file_stem().and_then(|s| s.to_str());
+                let Some(material) = stem.and_then(parse_material) else {
+                    continue;
+                };
+
+                let found = files.entry(material_name(&material, false)).or_default();
+                match path.extension().and_then(|e| e.to_str()) {
+                    Some("rtbw") => found.0 = Some(path),
+                    Some("rtbz") => found.1 = Some(path),
+                    _ => {}
+                }
+            }
+        }
+
+        let mut syzygy = Syzygy::default();
+        for (name, (wdl, dtz)) in files {
+            // a DTZ table alone can't be probed
+            let Some(wdl) = wdl else {
+                continue;
+            };
+            let material = parse_material(&name).unwrap();
+            let pieces = material.iter().flatten().sum::<u8>() as usize;
+            syzygy.max_pieces = syzygy.max_pieces.max(pieces);
+            syzygy.tables.insert(
+                name,
+                TableFiles {
+                    material,
+                    wdl,
+                    dtz,
+                    wdl_table: OnceLock::new(),
+                    dtz_table: OnceLock::new(),
+                },
+            );
+        }
+        Ok(syzygy)
+    }
+
+    /// number of WDL tables found
+    pub fn len(&self) -> usize {
+        self.tables.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tables.is_empty()
+    }
+
+    fn table(&self, board: &Board, kind: Kind) -> Option<&Table> {
+        let material = board_material(board);
+        let files = self
+            .tables
+            .get(&material_name(&material, false))
+            .or_else(|| self.tables.get(&material_name(&material, true)))?;
+
+        match kind {
+            Kind::Wdl => files
+                .wdl_table
+                .get_or_init(|| load(&files.wdl, kind, files.material)),
+            Kind::Dtz => files
+                .dtz_table
+                .get_or_init(|| load(files.dtz.as_ref()?, kind, files.material)),
+        }
+        .as_ref()
+    }
+
+    fn probe_wdl_table(&self, board: &Board) -> Option<Wdl> {
+        // bare kings have no table
+        if board.get_occ().count_bits() == 2 {
+            return Some(Wdl::Draw);
+        }
+        match self.table(board, Kind::Wdl)?.probe(board, 0)? {
+            Lookup::Value(value) => Wdl::from_value(value),
+            Lookup::OtherSide => None,
+        }
+    }
+
+    // tables don't know en passant and store the position before a capture as if it couldn't
+    // be made, so captures (and pawn moves for DTZ) are searched. the flag is set if the best
+    // result comes from such a move
+    fn search(&self, board: &Board, gen: &MovGen, pawn_moves: bool) -> Option<(Wdl, bool)> {
+        let mut moves = MoveList::new();
+        gen.generate_legal_moves(board, &mut moves);
+
+        let mut best = Wdl::Loss;
+        let mut searched = 0;
+        for &mov in moves.iter() {
+            let probed = mov.is_capture() || (pawn_moves && is_zeroing(board, mov));
+            if !probed {
+                continue;
+            }
+            searched += 1;
+
+            let mut next = *board;
+            next.make_move(mov, false);
+            let value = -self.search(&next, gen, false)?.0;
+            if value > best {
+                best = value;
+                if value == Wdl::Win {
+                    return Some((value, true));
+                }
+            }
+        }
+
+        // with every move searched the table isn't needed, it may even be wrong for en passant
+        let no_more_moves = searched > 0 && searched == moves.len();
+        let value = if no_more_moves {
+            best
+        } else {
+            self.probe_wdl_table(board)?
+        };
+
+        if best >= value {
+            Some((best, best > Wdl::Draw || no_more_moves))
+        } else {
+            Some((value, false))
+        }
+    }
+
+    fn can_probe(&self, board: &Board) -> bool {
+        board.info.castle.0 == 0 && board.get_occ().count_bits() as usize <= self.max_pieces
+    }
+}
+
+impl Tablebase for Syzygy {
+    fn max_pieces(&self) -> usize {
+        self.max_pieces
+    }
+
+    fn probe_wdl(&self, board: &Board, gen: &MovGen) -> Option<Wdl> {
+        if !self.can_probe(board) {
+            return None;
+        }
+        self.search(board, gen, false).map(|(wdl, _)| wdl)
+    }
+
+    fn probe_dtz(&self, board: &Board, gen: &MovGen) -> Option<i32> {
+        if !self.can_probe(board) {
+            return None;
+        }
+
+        let (wdl, zeroing) = self.search(board, gen, true)?;
+        if wdl == Wdl::Draw {
+            return Some(0);
+        }
+        if zeroing {
+            return Some(wdl.dtz_before_zeroing());
+        }
+
+        let table = self.table(board, Kind::Dtz)?;
+        let wdl_value = wdl as i32;
+        match table.probe(board, wdl_value)? {
+            Lookup::Value(dtz) => {
+                let fifty = matches!(wdl, Wdl::CursedWin | Wdl::BlessedLoss);
+                return Some((dtz + 100 * fifty as i32) * wdl_value.signum());
+            }
+            Lookup::OtherSide => {}
+        }
+
+        // the table stores the other side to move, the best of the moves' distances it is
+        let mut moves = MoveList::new();
+        gen.generate_legal_moves(board, &mut moves);
+        let mut best = None;
+        for &mov in moves.iter() {
+            let zeroing = is_zeroing(board, mov);
+            let mut next = *board;
+            next.make_move(mov, false);
+
+            // a zeroing move counts from before it, the sign still comes from after it
+            let mut dtz = if zeroing {
+                -self.search(&next, gen, false)?.0.dtz_before_zeroing()
+            } else {
+                -self.probe_dtz(&next, gen)?
+            };
+            if dtz == 1 && next.is_check(gen) && !has_legal_moves(&next, gen) {
+                best = Some(1);
+            }
+            if !zeroing {
+                dtz += dtz.signum();
+            }
+            if dtz.signum() == wdl_value.signum() && best.is_none_or(|best| dtz < best) {
+                best = Some(dtz);
+            }
+        }
+
+        // no legal moves, mated
+        Some(best.unwrap_or(-1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kelp::bitbase;
+    use crate::kelp::board::fen::{Fen, FenParse};
+    use crate::kelp::Squares;
+
+    // KQvK, KRvK, KPvK and KRPvK with the tables their promotions lead to
+    const TABLES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/syzygy");
+
+    // positions with their WDL and DTZ from the test suite of the shakmaty-syzygy crate, then
+    // mates in one for either color
+    const REFERENCE: [(&str, i32, i32); 12] = [
+        ("8/8/8/2R5/1K6/8/5k2/8 w - - 0 1", 2, 21),
+        ("8/3k4/8/8/8/8/4P3/3K4 w - - 0 1", 0, 0),
+        ("8/5p2/6k1/K7/8/8/8/8 w - - 0 1", -2, -2),
+        ("8/8/8/2K5/5kp1/8/8/8 b - - 0 1", 2, 1),
+        ("6k1/8/8/8/8/4n3/8/K7 b - - 0 1", 0, 0),
+        ("8/4P3/1k2K3/8/8/8/8/6R1 b - - 0 1", -2, -2),
+        ("1k6/1R3P2/8/8/8/8/8/6K1 b - - 0 1", -2, -2),
+        ("K7/8/5r2/8/3k4/5p2/8/8 w - - 0 1", -2, -2),
+        ("1r4k1/8/8/p7/8/8/8/K7 b - - 0 1", 2, 1),
+        ("8/8/8/8/7K/p4k2/8/1r6 w - - 0 1", -2, -2),
+        ("k7/8/1K6/8/8/8/8/6Q1 w - - 0 1", 2, 1),
+        ("6q1/8/8/8/8/1k6/8/K7 b - - 0 1", 2, 1),
+    ];
+
+    fn board(fen: &str) -> Board {
+        Board::parse(Fen(fen.to_string())).unwrap()
+    }
+
+    // every `step`th legal position of the two kings and `piece`, either side to move
+    fn positions(piece: BoardPiece, step: usize, gen: &MovGen) -> Vec<Board> {
+        let empty = board("8/8/8/8/8/8/8/8 w - - 0 1");
+        let mut boards = Vec::new();
+        for idx in (0..2 << 18).step_by(step) {
+            let squares = [(idx >> 1) & 63, (idx >> 7) & 63, idx >> 13];
+            if squares[0] == squares[1] || squares[2] == squares[0] || squares[2] == squares[1] {
+                continue;
+            }
+            if matches!(piece, BoardPiece::WhitePawn | BoardPiece::BlackPawn)
+                && !(8..56).contains(&squares[2])
+            {
+                continue;
+            }
+
+            let mut board = empty;
+            for (piece, sq) in [BoardPiece::WhiteKing, BoardPiece::BlackKing, piece]
+                .into_iter()
+                .zip(squares)
+            {
+                board.add_to_bb(piece, Squares::from_repr(sq as u8).unwrap());
+            }
+            if idx & 1 == 1 {
+                board.toggle_turn();
+            }
+            if !board.is_check_opp(gen) {
+                boards.push(board);
+            }
+        }
+        boards
+    }
+
+    #[test]
+    fn wdl_test() {
+        assert_eq!(-Wdl::Win, Wdl::Loss);
+        assert_eq!(-Wdl::BlessedLoss, Wdl::CursedWin);
+        assert!(Wdl::CursedWin > Wdl::Draw && Wdl::Draw > Wdl::BlessedLoss);
+        assert_eq!(Wdl::CursedWin.dtz_before_zeroing(), 101);
+    }
+
+    #[test]
+    fn root_rank_test() {
+        // a win in time is as good as any other, losses are best late
+        assert_eq!(root_rank(5, 0), root_rank(41, 30));
+        assert!(root_rank(41, 60) < root_rank(5, 60));
+        assert!(root_rank(41, 60) > root_rank(0, 60));
+        assert_eq!(root_rank(-3, 0), root_rank(-9, 0));
+        assert!(root_rank(-30, 50) > root_rank(-3, 50));
+        assert!(root_rank(-30, 50) < root_rank(0, 50));
+    }
+
+    #[test]
+    fn syzygy_open_test() {
+        let dir = std::env::temp_dir().join(format!("kelp_syzygy_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for name in [
+            "KQvK.rtbw",
+            "KQvK.rtbz",
+            "KRPvKR.rtbz",
+            "KvKN.rtbw",
+            "notes.txt",
+        ] {
+            fs::write(dir.join(name), b"not a table").unwrap();
+        }
+
+        let syzygy = Syzygy::open(dir.to_str().unwrap()).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        // KRPvKR has no WDL table, the stronger side doesn't have to come first
+        assert_eq!(syzygy.len(), 2);
+        assert_eq!(syzygy.max_pieces(), 3);
+
+        // the file isn't a table, probes fail instead of guessing
+        let gen = MovGen::new();
+        assert_eq!(
+            syzygy.probe_wdl(&board("8/8/8/8/8/2k5/8/KQ6 w - - 0 1"), &gen),
+            None
+        );
+        assert_eq!(
+            syzygy.probe_wdl(&board("8/8/8/8/8/2k5/8/K7 w - - 0 1"), &gen),
+            Some(Wdl::Draw)
+        );
+        // too many pieces, castling rights
+        assert_eq!(
+            syzygy.probe_wdl(&board("8/8/8/8/8/2k5/8/KQR5 w - - 0 1"), &gen),
+            None
+        );
+        assert_eq!(
+            syzygy.probe_wdl(&board("4k3/8/8/8/8/8/8/4K2R w K - 0 1"), &gen),
+            None
+        );
+
+        assert!(Syzygy::open("/nonexistent/kelp").is_err());
+        assert!(Syzygy::open("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn syzygy_probe_test() {
+        let gen = MovGen::new();
+        let syzygy = Syzygy::open(TABLES).unwrap();
+        assert_eq!(syzygy.max_pieces(), 4);
+
+        for (fen, wdl, dtz) in REFERENCE {
+            let board = board(fen);
+            let probed = syzygy.probe_wdl(&board, &gen).map(|wdl| wdl as i32);
+            assert_eq!(probed, Some(wdl), "{}", fen);
+            assert_eq!(syzygy.probe_dtz(&board, &gen), Some(dtz), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn syzygy_bitbase_test() {
+        // the generated bitbases are an independent solution of the three men endings, the
+        // black pieces are probed through the color swapped tables
+        let gen = MovGen::new();
+        let syzygy = Syzygy::open(TABLES).unwrap();
+        for piece in [
+            BoardPiece::WhiteQueen,
+            BoardPiece::BlackQueen,
+            BoardPiece::WhiteRook,
+            BoardPiece::BlackRook,
+            BoardPiece::WhitePawn,
+            BoardPiece::BlackPawn,
+        ] {
+            for board in positions(piece, 13, &gen) {
+                let wdl = syzygy.probe_wdl(&board, &gen);
+                assert_eq!(wdl, bitbase::probe(&board), "{}", board.to_fen());
+            }
+        }
+    }
+
+    #[test]
+    fn syzygy_dtz_test() {
+        // the DTZ of a position is the one of its best move
+        let gen = MovGen::new();
+        let syzygy = Syzygy::open(TABLES).unwrap();
+        for piece in [
+            BoardPiece::WhiteQueen,
+            BoardPiece::BlackRook,
+            BoardPiece::WhitePawn,
+        ] {
+            for board in positions(piece, 397, &gen) {
+                let dtz = syzygy.probe_dtz(&board, &gen).unwrap();
+                let mut moves = MoveList::new();
+                gen.generate_legal_moves(&board, &mut moves);
+                if dtz == 0 || moves.is_empty() {
+                    continue;
+                }
+
+                let dtzs = moves
+                    .iter()
+                    .map(|&mov| move_dtz(&syzygy, &board, mov, &gen).unwrap());
+                let best = if dtz > 0 {
+                    dtzs.filter(|&dtz| dtz > 0).min()
+                } else {
+                    dtzs.min()
+                };
+                assert_eq!(Some(dtz), best, "{}", board.to_fen());
+            }
+        }
+    }
+}
@@ -0,0 +1,918 @@
+// A single Syzygy file, `.rtbw` (win/draw/loss) or `.rtbz` (distance to zeroing). Positions are
+// mapped to an index using the symmetries of the board, the value of an index is stored
+// Huffman coded in blocks of pairs of symbols. This follows the probing code the format was
+// published with, every offset and table has to match it exactly.
+
+use crate::kelp::board::board::Board;
+use crate::kelp::board::piece::{BoardPiece, Color};
+use std::io;
+use strum::IntoEnumIterator;
+
+/// most pieces, kings included, any Syzygy table has
+pub const MAX_PIECES: usize = 7;
+
+const WDL_MAGIC: [u8; 4] = [0x71, 0xe8, 0x23, 0x5d];
+const DTZ_MAGIC: [u8; 4] = [0xd7, 0x66, 0x0c, 0xa5];
+
+// flags of the file
+const SPLIT: u8 = 1;
+const HAS_PAWNS: u8 = 2;
+
+// flags of every subtable
+const STM: u8 = 1;
+const MAPPED: u8 = 2;
+const WIN_PLIES: u8 = 4;
+const LOSS_PLIES: u8 = 8;
+const WIDE: u8 = 16;
+const SINGLE_VALUE: u8 = 128;
+
+/// Number of pieces of each kind by color, pawn, knight, bishop, rook, queen, king
+pub type Material = [[u8; 6]; 2];
+
+/// the material of a table name like `KRPvKR`, `None` if it isn't one
+pub fn parse_material(name: &str) -> Option<Material> {
+    let (white, black) = name.split_once('v')?;
+    let mut material = [[0; 6]; 2];
+    for (side, pieces) in [white, black].into_iter().enumerate() {
+        for c in pieces.chars() {
+            let kind = "PNBRQK".find(c)?;
+            material[side][kind] += 1;
+        }
+        if material[side][5] != 1 {
+            return None;
+        }
+    }
+
+    let count: u8 = material.iter().flatten().sum();
+    (count as usize <= MAX_PIECES).then_some(material)
+}
+
+/// the table name of `material`, black's pieces first if `flipped`
+pub fn material_name(material: &Material, flipped: bool) -> String {
+    let side = |color: usize| {
+        let mut name = String::new();
+        for (kind, c) in "PNBRQK".char_indices().rev() {
+            for _ in 0..material[color][kind] {
+                name.push(c);
+            }
+        }
+        name
+    };
+    let (first, second) = if flipped { (1, 0) } else { (0, 1) };
+    format!("{}v{}", side(first), side(second))
+}
+
+pub fn board_material(board: &Board) -> Material {
+    let mut material = [[0; 6]; 2];
+    for piece in BoardPiece::iter() {
+        let color = piece.get_color() as usize;
+        material[color][piece as usize % 6] = board.get_bitboard(piece).count_bits();
+    }
+    material
+}
+
+// pieces as the files store them, 1 to 6 for white pawn to king and 9 to 14 for black
+fn piece_code(piece: BoardPiece) -> u8 {
+    piece as u8 % 6 + 1 + 8 * (piece.get_color() == Color::Black) as u8
+}
+
+// how far above the a1-h8 diagonal a square is, negative below it
+const fn off_diagonal(sq: usize) -> i32 {
+    (sq / 8) as i32 - (sq % 8) as i32
+}
+
+const fn flip_diagonal(sq: usize) -> usize {
+    ((sq >> 3) | (sq << 3)) & 63
+}
+
+/// Tables mapping piece placements to indices
+pub(super) struct Encoding {
+    /// `binomial[k][n]` ways to pick k of n squares
+    pub binomial: [[u64; 64]; MAX_PIECES],
+    /// squares of the a1-d1-d4 triangle to 0..10, the diagonal last
+    pub map_a1d1d4: [u64; 64],
+    /// squares below the a1-h8 diagonal to 0..28
+    pub map_b1h1h7: [u64; 64],
+    /// the 462 placements of two kings, the first in the a1-d1-d4 triangle. `u64::MAX` if illegal
+    pub map_kk: [[u64; 64]; 10],
+    /// a2 to h7 to 0..48, the leading pawn is the one with the highest value
+    pub map_pawns: [u64; 64],
+    pub lead_pawn_idx: [[u64; 64]; 6],
+    pub lead_pawns_size: [[u64; 4]; 6],
+}
+
+pub(super) static ENCODING: Encoding = Encoding::new();
+
+impl Encoding {
+    const fn new() -> Encoding {
+        let mut binomial = [[0; 64]; MAX_PIECES];
+        binomial[0][0] = 1;
+        let mut n = 1;
+        while n < 64 {
+            let mut k = 0;
+            while k < MAX_PIECES && k <= n {
+                let with = if k > 0 { binomial[k - 1][n - 1] } else { 0 };
+                let without = if k < n { binomial[k][n - 1] } else { 0 };
+                binomial[k][n] = with + without;
+                k += 1;
+            }
+            n += 1;
+        }
+
+        let mut map_b1h1h7 = [0; 64];
+        let mut code = 0;
+        let mut sq = 0;
+        while sq < 64 {
+            if off_diagonal(sq) < 0 {
+                map_b1h1h7[sq] = code;
+                code += 1;
+            }
+            sq += 1;
+        }
+
+        let mut map_a1d1d4 = [0; 64];
+        let mut diagonal = [0; 4];
+        let mut diagonal_len = 0;
+        code = 0;
+        sq = 0;
+        while sq <= 27 {
+            if off_diagonal(sq) < 0 && sq % 8 <= 3 {
+                map_a1d1d4[sq] = code;
+                code += 1;
+            } else if off_diagonal(sq) == 0 && sq % 8 <= 3 {
+                diagonal[diagonal_len] = sq;
+                diagonal_len += 1;
+            }
+            sq += 1;
+        }
+        let mut i = 0;
+        while i < diagonal_len {
+            map_a1d1d4[diagonal[i]] = code;
+            code += 1;
+            i += 1;
+        }
+
+        // both kings on the diagonal are encoded last
+        let mut map_kk = [[u64::MAX; 64]; 10];
+        let mut both_on_diagonal = [(0, 0); 32];
+        let mut both_len = 0;
+        code = 0;
+        let mut idx = 0;
+        while idx < 10 {
+            let mut s1 = 0;
+            while s1 <= 27 {
+                // b1 is the only square of the triangle mapped to 0
+                if map_a1d1d4[s1] == idx as u64 && (idx != 0 || s1 == 1) {
+                    let mut s2 = 0;
+                    while s2 < 64 {
+                        let touching =
+                            (s1 % 8).abs_diff(s2 % 8) <= 1 && (s1 / 8).abs_diff(s2 / 8) <= 1;
+                        if touching || (off_diagonal(s1) == 0 && off_diagonal(s2) > 0) {
+                            // illegal, or the second king above the diagonal
+                        } else if off_diagonal(s1) == 0 && off_diagonal(s2) == 0 {
+                            both_on_diagonal[both_len] = (idx, s2);
+                            both_len += 1;
+                        } else {
+                            map_kk[idx][s2] = code;
+                            code += 1;
+                        }
+                        s2 += 1;
+                    }
+                }
+                s1 += 1;
+            }
+            idx += 1;
+        }
+        i = 0;
+        while i < both_len {
+            map_kk[both_on_diagonal[i].0][both_on_diagonal[i].1] = code;
+            code += 1;
+            i += 1;
+        }
+
+        let mut map_pawns = [0; 64];
+        let mut lead_pawn_idx = [[0; 64]; 6];
+        let mut lead_pawns_size = [[0; 4]; 6];
+        let mut available: u64 = 47;
+        let mut count = 1;
+        while count <= 5 {
+            let mut file = 0;
+            while file < 4 {
+                let mut idx = 0;
+                let mut rank = 1;
+                while rank <= 6 {
+                    let sq = 8 * rank + file;
+                    if count == 1 {
+                        map_pawns[sq] = available;
+                        map_pawns[sq ^ 7] = available - 1;
+                        available = available.saturating_sub(2);
+                    }
+                    lead_pawn_idx[count][sq] = idx;
+                    idx += binomial[count - 1][map_pawns[sq] as usize];
+                    rank += 1;
+                }
+                lead_pawns_size[count][file] = idx;
+                file += 1;
+            }
+            count += 1;
+        }
+
+        Encoding {
+            binomial,
+            map_a1d1d4,
+            map_b1h1h7,
+            map_kk,
+            map_pawns,
+            lead_pawn_idx,
+            lead_pawns_size,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Kind {
+    Wdl,
+    Dtz,
+}
+
+/// What a probe of a table found
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Lookup {
+    /// -2 to 2 for win/draw/loss tables, plies to zeroing for DTZ tables
+    Value(i32),
+    /// DTZ tables only store one side to move, the other side has to be probed a ply deeper
+    OtherSide,
+}
+
+fn read<const N: usize>(bytes: &[u8], at: usize) -> Option<[u8; N]> {
+    bytes.get(at..at.checked_add(N)?)?.try_into().ok()
+}
+
+fn u16_at(bytes: &[u8], at: usize) -> Option<u16> {
+    read(bytes, at).map(u16::from_le_bytes)
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> Option<u32> {
+    read(bytes, at).map(u32::from_le_bytes)
+}
+
+// a node of the pair tree, 12 bits each. a leaf has 0xfff on the right and its value on the left
+fn pair(bytes: &[u8], btree: usize, sym: usize) -> Option<(usize, usize)> {
+    let [a, b, c] = read(bytes, btree + 3 * sym)?;
+    let left = (b as usize & 0xf) << 8 | a as usize;
+    let right = (c as usize) << 4 | (b as usize) >> 4;
+    Some((left, right))
+}
+
+/// How one side to move of one leading pawn file is encoded and compressed
+#[derive(Debug, Default)]
+struct PairsData {
+    flags: u8,
+    /// piece codes in the order they are encoded
+    pieces: Vec<u8>,
+    /// number of pieces in each group, identical pieces are encoded together
+    group_len: Vec<usize>,
+    /// factor of every group in the index, the last one is the size of the table
+    group_idx: Vec<u64>,
+    block_size: usize,
+    // about every `span` values there is an entry in the sparse index
+    span: u64,
+    blocks: usize,
+    // the value itself for single value tables
+    min_sym_len: u8,
+    lowest_sym: usize,
+    // lowest symbol of every length, left aligned
+    base: Vec<u64>,
+    // number of values after the first one a symbol stands for
+    sym_len: Vec<u8>,
+    btree: usize,
+    sparse_index: usize,
+    sparse_index_size: usize,
+    block_lengths: usize,
+    block_length_size: usize,
+    data: usize,
+    // DTZ tables, start of the values of wins, losses, cursed wins and blessed losses
+    map_idx: [usize; 4],
+}
+
+impl PairsData {
+    fn new(pieces: Vec<u8>, order: [u8; 2], file: usize, table: &Table) -> Option<PairsData> {
+        // the leading group, 3 unique pieces or the kings, then runs of identical pieces
+        let mut first_len = if table.has_pawns {
+            0
+        } else if table.has_unique {
+            3
+        } else {
+            2
+        };
+        let mut group_len = vec![1];
+        for i in 1..pieces.len() {
+            first_len -= 1;
+            if first_len > 0 || pieces[i] == pieces[i - 1] {
+                *group_len.last_mut()? += 1;
+            } else {
+                group_len.push(1);
+            }
+        }
+
+        // groups are multiplied in the order the file asks for, the leading group at
+        // `order[0]` and the other side's pawns at `order[1]`
+        let n = group_len.len();
+        if group_len[0] > 5 || (table.both_pawns && n < 2) {
+            return None;
+        }
+        let mut group_idx = vec![0; n + 1];
+        let mut next = if table.both_pawns { 2 } else { 1 };
+        let mut free_squares = 64 - group_len[0] - if table.both_pawns { group_len[1] } else { 0 };
+        let mut idx: u64 = 1;
+        let mut k = 0;
+        while next < n || k == order[0] as usize || k == order[1] as usize {
+            if k == order[0] as usize {
+                group_idx[0] = idx;
+                idx *= if table.has_pawns {
+                    ENCODING.lead_pawns_size[group_len[0]][file]
+                } else if table.has_unique {
+                    31332
+                } else {
+                    462
+                };
+            } else if k == order[1] as usize {
+                group_idx[1] = idx;
+                idx *= ENCODING.binomial[group_len[1]][48 - group_len[0]];
+            } else {
+                group_idx[next] = idx;
+                idx *= ENCODING.binomial[group_len[next]][free_squares];
+                free_squares -= group_len[next];
+                next += 1;
+            }
+            k += 1;
+        }
+        group_idx[n] = idx;
+
+        Some(PairsData {
+            pieces,
+            group_len,
+            group_idx,
+            ..Default::default()
+        })
+    }
+
+    // reads the compression header at `p`, returns where the next one starts
+    fn set_sizes(&mut self, bytes: &[u8], mut p: usize, kind: Kind) -> Option<usize> {
+        self.flags = *bytes.get(p)?;
+        if self.flags & SINGLE_VALUE != 0 {
+            // the byte isn't meaningful in DTZ tables, every position there is a zeroing move away
+            self.min_sym_len = match kind {
+                Kind::Wdl => *bytes.get(p + 1)?,
+                Kind::Dtz => 0,
+            };
+            return Some(p + 2);
+        }
+
+        let header: [u8; 10] = read(bytes, p)?;
+        let size = *self.group_idx.last()?;
+        self.block_size = 1usize.checked_shl(header[1] as u32)?;
+        self.span = 1u64.checked_shl(header[2] as u32)?;
+        self.sparse_index_size = size.div_ceil(self.span) as usize;
+        self.blocks = u32::from_le_bytes(header[4..8].try_into().ok()?) as usize;
+        // padded so the sparse index never points past the end
+        self.block_length_size = self.blocks + header[3] as usize;
+        let max_sym_len = header[8];
+        self.min_sym_len = header[9];
+        if self.min_sym_len == 0 || max_sym_len < self.min_sym_len || max_sym_len > 32 {
+            return None;
+        }
+
+        // canonical Huffman code, the lowest code of every length
+        self.lowest_sym = p + 10;
+        let lengths = (max_sym_len - self.min_sym_len + 1) as usize;
+        self.base = vec![0; lengths];
+        for i in (0..lengths - 1).rev() {
+            let lowest = u16_at(bytes, self.lowest_sym + 2 * i)? as u64;
+            let next = u16_at(bytes, self.lowest_sym + 2 * (i + 1))? as u64;
+            self.base[i] = (self.base[i + 1] + lowest).checked_sub(next)? / 2;
+        }
+        for (i, base) in self.base.iter_mut().enumerate() {
+            *base <<= 64 - i - self.min_sym_len as usize;
+        }
+
+        p = self.lowest_sym + 2 * lengths;
+        let symbols = u16_at(bytes, p)? as usize;
+        self.btree = p + 2;
+        self.sym_len = vec![0; symbols];
+        let mut visited = vec![false; symbols];
+        for sym in 0..symbols {
+            if !visited[sym] {
+                self.sym_len[sym] = self.set_sym_len(bytes, sym, &mut visited)?;
+            }
+        }
+
+        Some(self.btree + 3 * symbols + (symbols & 1))
+    }
+
+    fn set_sym_len(&mut self, bytes: &[u8], sym: usize, visited: &mut [bool]) -> Option<u8> {
+        // set before the children, a corrupt tree with a cycle can't recurse forever
+        visited[sym] = true;
+        let (left, right) = pair(bytes, self.btree, sym)?;
+        if right == 0xfff {
+            return Some(0);
+        }
+
+        for child in [left, right] {
+            if !*visited.get(child)? {
+                self.sym_len[child] = self.set_sym_len(bytes, child, visited)?;
+            }
+        }
+        Some(
+            self.sym_len[left]
+                .wrapping_add(self.sym_len[right])
+                .wrapping_add(1),
+        )
+    }
+}
+
+/// A table read into memory
+pub struct Table {
+    kind: Kind,
+    bytes: Vec<u8>,
+    /// material of the side the table calls white
+    material: Material,
+    symmetric: bool,
+    has_pawns: bool,
+    // a piece other than a king that is the only one of its kind
+    has_unique: bool,
+    both_pawns: bool,
+    /// by leading pawn file, then by side to move
+    subtables: Vec<Vec<PairsData>>,
+    // start of the DTZ value maps
+    map: usize,
+}
+
+impl Table {
+    /// parses the file of the table for `material`, the material it was named after
+    pub fn new(bytes: Vec<u8>, kind: Kind, material: Material) -> io::Result<Table> {
+        let invalid = |message| io::Error::new(io::ErrorKind::InvalidData, message);
+        let magic = match kind {
+            Kind::Wdl => WDL_MAGIC,
+            Kind::Dtz => DTZ_MAGIC,
+        };
+        if bytes.get(0..4) != Some(&magic[..]) {
+            return Err(invalid("not a syzygy table"));
+        }
+
+        let pawns = [material[0][0], material[1][0]];
+        let mut table = Table {
+            kind,
+            bytes: Vec::new(),
+            material,
+            symmetric: material[0] == material[1],
+            has_pawns: pawns[0] + pawns[1] > 0,
+            has_unique: material.iter().any(|side| side[..5].contains(&1)),
+            // the side with fewer pawns leads, if the other one has any both do
+            both_pawns: pawns[0] > 0 && pawns[1] > 0,
+            subtables: Vec::new(),
+            map: 0,
+        };
+        table
+            .parse(&bytes)
+            .ok_or_else(|| invalid("corrupt syzygy table"))?;
+        table.bytes = bytes;
+        Ok(table)
+    }
+
+    fn parse(&mut self, bytes: &[u8]) -> Option<()> {
+        let flags = *bytes.get(4)?;
+        if (flags & HAS_PAWNS != 0) != self.has_pawns || (flags & SPLIT != 0) == self.symmetric {
+            return None;
+        }
+
+        let piece_count = self.material.iter().flatten().sum::<u8>() as usize;
+        let sides = if self.kind == Kind::Wdl && !self.symmetric {
+            2
+        } else {
+            1
+        };
+        let files = if self.has_pawns { 4 } else { 1 };
+
+        let mut p = 5;
+        for file in 0..files {
+            let first = *bytes.get(p)?;
+            let second = if self.both_pawns {
+                *bytes.get(p + 1)?
+            } else {
+                0xff
+            };
+            let order = [[first & 0xf, second & 0xf], [first >> 4, second >> 4]];
+            p += 1 + self.both_pawns as usize;
+
+            let mut subtable = Vec::with_capacity(sides);
+            for (side, order) in order.into_iter().enumerate().take(sides) {
+                let pieces: Vec<u8> = bytes
+                    .get(p..p + piece_count)?
+                    .iter()
+                    .map(|b| if side == 0 { b & 0xf } else { b >> 4 })
+                    .collect();
+                if !self.same_material(&pieces) || (pieces[0] & 7 == 1) != self.has_pawns {
+                    return None;
+                }
+                subtable.push(PairsData::new(pieces, order, file, self)?);
+            }
+            self.subtables.push(subtable);
+            p += piece_count;
+        }
+        p += p & 1;
+
+        for d in self.subtables.iter_mut().flatten() {
+            p = d.set_sizes(bytes, p, self.kind)?;
+        }
+
+        if self.kind == Kind::Dtz {
+            self.map = p;
+            for subtable in self.subtables.iter_mut() {
+                let d = &mut subtable[0];
+                if d.flags & MAPPED == 0 {
+                    continue;
+                }
+                if d.flags & WIDE != 0 {
+                    p += p & 1;
+                    for i in 0..4 {
+                        d.map_idx[i] = (p - self.map) / 2 + 1;
+                        p += 2 + 2 * u16_at(bytes, p)? as usize;
+                    }
+                } else {
+                    for i in 0..4 {
+                        d.map_idx[i] = p - self.map + 1;
+                        p += 1 + *bytes.get(p)? as usize;
+                    }
+                }
+            }
+            p += p & 1;
+        }
+
+        for d in self.subtables.iter_mut().flatten() {
+            d.sparse_index = p;
+            p += 6 * d.sparse_index_size;
+        }
+        for d in self.subtables.iter_mut().flatten() {
+            d.block_lengths = p;
+            p += 2 * d.block_length_size;
+        }
+        for d in self.subtables.iter_mut().flatten() {
+            p = (p + 0x3f) & !0x3f;
+            d.data = p;
+            p += d.blocks.checked_mul(d.block_size)?;
+        }
+
+        (p <= bytes.len()).then_some(())
+    }
+
+    fn same_material(&self, pieces: &[u8]) -> bool {
+        let mut material = [[0; 6]; 2];
+        for &code in pieces {
+            match code & 7 {
+                kind @ 1..=6 => material[(code >> 3) as usize & 1][kind as usize - 1] += 1,
+                _ => return false,
+            }
+        }
+        material == self.material || material == [self.material[1], self.material[0]]
+    }
+
+    /// the value of `board`, which must have the material of the table in either color. `wdl`
+    /// is the win/draw/loss of the position when probing a DTZ table. `None` if the table is
+    /// corrupt
+    pub fn probe(&self, board: &Board, wdl: i32) -> Option<Lookup> {
+        let encoding = &ENCODING;
+        let black_to_move = board.get_side_to_move() == Color::Black;
+
+        // tables are stored with white as the stronger side and, if both sides have the same
+        // pieces, with white to move. otherwise colors are swapped and the board mirrored
+        let flip =
+            board_material(board)[0] != self.material[0] || (self.symmetric && black_to_move);
+        let flip_color = if flip { 8 } else { 0 };
+        let flip_squares = if flip { 56 } else { 0 };
+        let stm = (flip ^ black_to_move) as usize;
+
+        let mut squares = [0; MAX_PIECES];
+        let mut pieces = [0; MAX_PIECES];
+        let mut size = 0;
+        let mut file = 0;
+        let mut lead_pawns = 0;
+
+        // tables with pawns have a subtable for every file of the leading pawn
+        if self.has_pawns {
+            let pawn = match self.subtables[0][0].pieces[0] ^ flip_color {
+                1 => BoardPiece::WhitePawn,
+                _ => BoardPiece::BlackPawn,
+            };
+            let bitboard = board.get_bitboard(pawn);
+            lead_pawns = bitboard.0;
+            for sq in bitboard {
+                squares[size] = sq as usize ^ flip_squares;
+                size += 1;
+            }
+            let lead = (0..size).max_by_key(|&i| encoding.map_pawns[squares[i]])?;
+            squares.swap(0, lead);
+            file = (squares[0] % 8).min(7 - squares[0] % 8);
+        }
+        let lead_count = size;
+
+        let subtable = &self.subtables[file];
+        let d = &subtable[stm % subtable.len()];
+        if self.kind == Kind::Dtz
+            && (d.flags & STM != 0) != (stm == 1)
+            && (!self.symmetric || self.has_pawns)
+        {
+            return Some(Lookup::OtherSide);
+        }
+
+        for piece in BoardPiece::iter() {
+            for sq in board.get_bitboard(piece) {
+                if lead_pawns >> sq & 1 == 0 && size < MAX_PIECES {
+                    squares[size] = sq as usize ^ flip_squares;
+                    pieces[size] = piece_code(piece) ^ flip_color;
+                    size += 1;
+                }
+            }
+        }
+        if size != d.pieces.len() {
+            return None;
+        }
+
+        // same order as the pieces of the table
+        for i in lead_count..size.saturating_sub(1) {
+            for j in i + 1..size {
+                if d.pieces[i] == pieces[j] {
+                    pieces.swap(i, j);
+                    squares.swap(i, j);
+                    break;
+                }
+            }
+        }
+
+        // the leading piece goes to files a to d
+        if squares[0] % 8 > 3 {
+            for sq in squares[..size].iter_mut() {
+                *sq ^= 7;
+            }
+        }
+
+        let mut idx;
+        if self.has_pawns {
+            idx = encoding.lead_pawn_idx[lead_count][squares[0]];
+            squares[1..lead_count].sort_by_key(|&sq| encoding.map_pawns[sq]);
+            for (i, &sq) in squares.iter().enumerate().take(lead_count).skip(1) {
+                idx += encoding.binomial[i][encoding.map_pawns[sq] as usize];
+            }
+        } else {
+            // ranks 1 to 4, then the first piece of the leading group off the diagonal below it
+            if squares[0] / 8 > 3 {
+                for sq in squares[..size].iter_mut() {
+                    *sq ^= 56;
+                }
+            }
+            for i in 0..d.group_len[0] {
+                let off = off_diagonal(squares[i]);
+                if off == 0 {
+                    continue;
+                }
+                if off > 0 {
+                    for sq in squares[i..size].iter_mut() {
+                        *sq = flip_diagonal(*sq);
+                    }
+                }
+                break;
+            }
+
+            let [s0, s1, s2] = [squares[0], squares[1], squares[2]];
+            let rank = |sq: usize| (sq / 8) as u64;
+            if self.has_unique {
+                let adjust1 = (s1 > s0) as usize;
+                let adjust2 = (s2 > s0) as usize + (s2 > s1) as usize;
+                idx = if off_diagonal(s0) != 0 {
+                    (encoding.map_a1d1d4[s0] * 63 + (s1 - adjust1) as u64) * 62
+                        + (s2 - adjust2) as u64
+                } else if off_diagonal(s1) != 0 {
+                    (6 * 63 + rank(s0) * 28 + encoding.map_b1h1h7[s1]) * 62 + (s2 - adjust2) as u64
+                } else if off_diagonal(s2) != 0 {
+                    6 * 63 * 62
+                        + 4 * 28 * 62
+                        + rank(s0) * 7 * 28
+                        + (rank(s1) - adjust1 as u64) * 28
+                        + encoding.map_b1h1h7[s2]
+                } else {
+                    6 * 63 * 62
+                        + 4 * 28 * 62
+                        + 4 * 7 * 28
+                        + rank(s0) * 7 * 6
+                        + (rank(s1) - adjust1 as u64) * 6
+                        + (rank(s2) - adjust2 as u64)
+                };
+            } else {
+                idx = encoding.map_kk[encoding.map_a1d1d4[s0] as usize][s1];
+                if idx == u64::MAX {
+                    return None;
+                }
+            }
+        }
+
+        // the remaining groups, squares taken by earlier groups are skipped
+        idx *= d.group_idx[0];
+        let mut start = d.group_len[0];
+        let mut remaining_pawns = self.both_pawns;
+        for next in 1..d.group_len.len() {
+            let len = d.group_len[next];
+            squares[start..start + len].sort_unstable();
+            let mut n = 0;
+            for i in 0..len {
+                let sq = squares[start + i];
+                let adjust = squares[..start].iter().filter(|&&s| sq > s).count();
+                let pawn_rank = if remaining_pawns { 8 } else { 0 };
+                n += encoding.binomial[i + 1][sq - adjust - pawn_rank];
+            }
+            remaining_pawns = false;
+            idx += n * d.group_idx[next];
+            start += len;
+        }
+
+        let value = self.decompress(d, idx)?;
+        match self.kind {
+            Kind::Wdl => Some(Lookup::Value(value as i32 - 2)),
+            Kind::Dtz => self.map_dtz(d, value, wdl).map(Lookup::Value),
+        }
+    }
+
+    // DTZ tables store moves instead of plies when that is exact enough, and may store an
+    // index into a map of the values
+    fn map_dtz(&self, d: &PairsData, value: usize, wdl: i32) -> Option<i32> {
+        let mut value = value;
+        if d.flags & MAPPED != 0 {
+            // wins, losses, cursed wins and blessed losses
+            let map = [1, 3, 0, 2, 0][(wdl + 2) as usize];
+            let i = d.map_idx[map] + value;
+            value = if d.flags & WIDE != 0 {
+                u16_at(&self.bytes, self.map + 2 * i)? as usize
+            } else {
+                *self.bytes.get(self.map + i)? as usize
+            };
+        }
+
+        let plies =
+            (wdl == 2 && d.flags & WIN_PLIES != 0) || (wdl == -2 && d.flags & LOSS_PLIES != 0);
+        if !plies {
+            value *= 2;
+        }
+        Some(value as i32 + 1)
+    }
+
+    // the value at `idx`, found through the sparse index and decoded symbol by symbol
+    fn decompress(&self, d: &PairsData, idx: u64) -> Option<usize> {
+        if d.flags & SINGLE_VALUE != 0 {
+            return Some(d.min_sym_len as usize);
+        }
+        let bytes = &self.bytes[..];
+
+        let entry = d.sparse_index + 6 * (idx / d.span) as usize;
+        let mut block = u32_at(bytes, entry)? as usize;
+        let mut offset = u16_at(bytes, entry + 4)? as i64;
+        offset += (idx % d.span) as i64 - (d.span / 2) as i64;
+
+        let block_length = |block: usize| u16_at(bytes, d.block_lengths + 2 * block).map(i64::from);
+        while offset < 0 {
+            block = block.checked_sub(1)?;
+            offset += block_length(block)? + 1;
+        }
+        while offset > block_length(block)? {
+            offset -= block_length(block)? + 1;
+            block += 1;
+        }
+
+        let mut ptr = d.data + block * d.block_size;
+        let mut buf = u64::from_be_bytes(read(bytes, ptr)?);
+        ptr += 8;
+        let mut buf_size = 64;
+
+        let mut sym;
+        loop {
+            let mut len = 0;
+            while buf < *d.base.get(len)? {
+                len += 1;
+            }
+            sym = ((buf - d.base[len]) >> (64 - len - d.min_sym_len as usize)) as usize;
+            sym += u16_at(bytes, d.lowest_sym + 2 * len)? as usize;
+
+            let sym_len = *d.sym_len.get(sym)? as i64;
+            if offset < sym_len + 1 {
+                break;
+            }
+            offset -= sym_len + 1;
+            len += d.min_sym_len as usize;
+            buf <<= len;
+            buf_size -= len;
+
+            if buf_size <= 32 {
+                buf_size += 32;
+                buf |= (u32::from_be_bytes(read(bytes, ptr)?) as u64) << (64 - buf_size);
+                ptr += 4;
+            }
+        }
+
+        // the symbol stands for a sequence of values, walk down to the one at `offset`
+        while d.sym_len[sym] != 0 {
+            let (left, right) = pair(bytes, d.btree, sym)?;
+            let left_len = *d.sym_len.get(left)? as i64;
+            if offset < left_len + 1 {
+                sym = left;
+            } else {
+                offset -= left_len + 1;
+                sym = right;
+            }
+        }
+        pair(bytes, d.btree, sym).map(|(value, _)| value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encoding_test() {
+        let e = &ENCODING;
+        assert_eq!(e.binomial[2][62], 62 * 61 / 2);
+        assert_eq!(e.binomial[3][5], 10);
+        assert_eq!(e.binomial[4][3], 0);
+
+        // b1, c1, d1, c2, d2, d3, then the diagonal a1, b2, c3, d4
+        let triangle = [1, 2, 3, 10, 11, 19, 0, 9, 18, 27];
+        for (code, sq) in triangle.into_iter().enumerate() {
+            assert_eq!(e.map_a1d1d4[sq], code as u64);
+        }
+        assert_eq!(e.map_b1h1h7[1], 0);
+        assert_eq!(e.map_b1h1h7[55], 27);
+
+        // every legal placement of two kings has a code, 462 of them
+        let mut codes: Vec<u64> = e
+            .map_kk
+            .iter()
+            .flatten()
+            .copied()
+            .filter(|&c| c != u64::MAX)
+            .collect();
+        codes.sort_unstable();
+        assert_eq!(codes, (0..462).collect::<Vec<_>>());
+        assert_eq!(e.map_kk[0][3], 0);
+        assert_eq!(e.map_kk[1][0], 58);
+        assert_eq!(e.map_kk[6][2], 339);
+        assert_eq!(e.map_kk[6][18], 441);
+        assert_eq!(e.map_kk[9][63], 461);
+        assert_eq!(e.map_kk[0][1], u64::MAX);
+
+        // a2, h2, a3, then the b file, ... e7 is the last square a pawn can't lead from
+        assert_eq!(
+            (e.map_pawns[8], e.map_pawns[15], e.map_pawns[16]),
+            (47, 46, 45)
+        );
+        assert_eq!(
+            (e.map_pawns[9], e.map_pawns[11], e.map_pawns[52]),
+            (35, 11, 0)
+        );
+        assert_eq!(e.lead_pawns_size[1], [6, 6, 6, 6]);
+        // a second pawn can stand on any of the 47 squares after a2
+        assert_eq!(e.lead_pawn_idx[2][16], 47);
+    }
+
+    #[test]
+    fn material_test() {
+        let material = parse_material("KRPvKR").unwrap();
+        assert_eq!(material, [[1, 0, 0, 1, 0, 1], [0, 0, 0, 1, 0, 1]]);
+        assert_eq!(material_name(&material, false), "KRPvKR");
+        assert_eq!(material_name(&material, true), "KRvKRP");
+
+        assert_eq!(
+            parse_material("KQvK"),
+            Some([[0, 0, 0, 0, 1, 1], [0, 0, 0, 0, 0, 1]])
+        );
+        assert_eq!(parse_material("KQK"), None);
+        assert_eq!(parse_material("QvK"), None);
+        assert_eq!(parse_material("KXvK"), None);
+        assert_eq!(parse_material("KQQQQvKQQ"), None);
+    }
+
+    #[test]
+    fn table_header_test() {
+        let material = parse_material("KQvK").unwrap();
+        let err = Table::new(vec![0; 64], Kind::Wdl, material).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // the DTZ magic doesn't open a WDL table, a truncated header is corrupt
+        let mut bytes = DTZ_MAGIC.to_vec();
+        bytes.push(SPLIT);
+        assert!(Table::new(bytes.clone(), Kind::Wdl, material).is_err());
+        assert!(Table::new(bytes, Kind::Dtz, material).is_err());
+
+        // a pawn table named after material without pawns
+        let mut bytes = WDL_MAGIC.to_vec();
+        bytes.extend([SPLIT | HAS_PAWNS, 0, 0x96, 0x6e]);
+        assert!(Table::new(bytes, Kind::Wdl, material).is_err());
+    }
+}
@@ -7,6 +7,10 @@ pub trait UCI {
 
     fn handle_uci(&self, arg: &[&str]);
 
+    fn handle_setoption(&mut self, arg: &[&str]);
+
+    fn handle_bench(&mut self, arg: &[&str]);
+
     fn handle_quit(&self);
 
     fn handle_stop(&self);
@@ -20,7 +24,15 @@ pub trait UCI {
     fn is_keyword(&self, arg: &str) -> bool {
         matches!(
             arg,
-            "position" | "go" | "uci" | "quit" | "stop" | "ponderhit" | "debug" | "isready"
+            "position"
+                | "go"
+                | "uci"
+                | "setoption"
+                | "quit"
+                | "stop"
+                | "ponderhit"
+                | "debug"
+                | "isready"
         )
     }
 
@@ -57,6 +69,8 @@ pub trait UCI {
             "position" => self.handle_position(&args),
             "go" => self.handle_go(&args),
             "uci" => self.handle_uci(&args),
+            "setoption" => self.handle_setoption(&args),
+            "bench" => self.handle_bench(&args),
             "quit" => self.handle_quit(),
             "stop" => self.handle_stop(),
             "isready" => self.handle_ready(),
@@ -79,10 +93,6 @@ pub trait UCI {
         self.send(format!("bestmove {}", bestmove).as_str());
     }
 
-    fn send_info(&self, info: &str) {
-        self.send(format!("info {}", info).as_str());
-    }
-
     // a very basic uci loop, can be overriden
     fn uci_loop(&mut self) {
         loop {
@@ -1,9 +1,9 @@
 mod kelp;
 extern crate log;
 
+use crate::kelp::bitbase::Bitbases;
 use crate::kelp::kelp::Kelp;
 use crate::kelp::uci_trait::UCI;
-use kelp::kelp_core::lookup_table::LookupTable;
 
 
 
@@ -26,7 +26,18 @@ fn main() {
     let _tricky = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1 ";
     let _empty = "8/8/8/8/8/8/8/8 w KQ - 0 1";
 
-    let mut table = LookupTable::default();
-    let mut kelp = Kelp::new(&mut table);
+    // the four men bitbases take a while to generate, the evaluation uses them once they are ready
+    std::thread::spawn(|| Bitbases::global().fill());
+
+    let mut kelp = Kelp::new();
+
+    // kelp bench [depth], runs the bench and exits
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+    if args.first().map(String::as_str) == Some("bench") {
+        let args = args[1..].iter().map(String::as_str).collect::<Vec<&str>>();
+        kelp.handle_bench(&args);
+        return;
+    }
+
     kelp.uci_loop();
 }
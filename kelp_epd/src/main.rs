@@ -0,0 +1,92 @@
+use kelp_engine::kelp::engine::{Engine, SearchLimits};
+use kelp_engine::kelp::epd::{read_epd, run_suite};
+use std::fs::File;
+use std::io::BufReader;
+use std::time::Duration;
+
+fn print_usage() {
+    println!(
+        "Usage: kelp_epd <file> [--movetime <ms>] [--nodes <n>] [--depth <d>] [--threads <n>]"
+    );
+    println!("searches every position of an epd suite and scores the best move against");
+    println!("bm/am, or the STS points in c0 or c8/c9 when present. defaults to --movetime 1000");
+}
+
+struct Args {
+    file: String,
+    limits: SearchLimits,
+    threads: usize,
+}
+
+fn parse_args() -> Option<Args> {
+    let mut iter = std::env::args().skip(1);
+    let mut args = Args {
+        file: iter.next()?,
+        limits: SearchLimits::default(),
+        threads: 1,
+    };
+
+    while let Some(flag) = iter.next() {
+        let value = iter.next()?;
+        match flag.as_str() {
+            "--movetime" => args.limits.movetime = Some(Duration::from_millis(value.parse().ok()?)),
+            "--nodes" => args.limits.nodes = Some(value.parse().ok()?),
+            "--depth" => args.limits.depth = Some(value.parse().ok()?),
+            "--threads" => args.threads = value.parse().ok()?,
+            _ => return None,
+        }
+    }
+
+    let limits = &mut args.limits;
+    if limits.movetime.is_none() && limits.nodes.is_none() && limits.depth.is_none() {
+        limits.movetime = Some(Duration::from_millis(1000));
+    }
+
+    Some(args)
+}
+
+fn main() {
+    let Some(args) = parse_args() else {
+        print_usage();
+        std::process::exit(1);
+    };
+
+    let file = match File::open(&args.file) {
+        Ok(file) => file,
+        Err(e) => {
+            eprintln!("{}: {}", args.file, e);
+            std::process::exit(1);
+        }
+    };
+
+    let mut positions = Vec::new();
+    for (line, epd) in read_epd(BufReader::new(file)) {
+        match epd {
+            Ok(epd) => positions.push(epd),
+            Err(e) => eprintln!("{}:{}: {}", args.file, line, e),
+        }
+    }
+
+    let mut engine = Engine::new();
+    engine.set_threads(args.threads);
+
+    let mut index = 0;
+    let report = run_suite(&mut engine, &positions, args.limits, |epd, res| {
+        index += 1;
+        let best = res.best_move.map_or("none".to_string(), |m| m.to_string());
+        println!(
+            "{:>4} {:<20} {:<6} {:>2}/{:<2} {:>6}ms {:>10} nodes {}",
+            index,
+            epd.id().unwrap_or("-"),
+            best,
+            res.points,
+            res.max_points,
+            res.time.as_millis(),
+            res.nodes,
+            if res.solved() { "ok" } else { "failed" }
+        );
+    });
+
+    println!();
+    println!("{}", report);
+}
@@ -0,0 +1,74 @@
+use kelp_engine::kelp::kelp_core::magic_search::{find_magics, magics_to_rust};
+
+fn print_usage() {
+    println!("Usage: kelp_magics [--seed <n>] [--reduce <bits>] [--tries <n>] [--out <file>]");
+    println!("--reduce tries magics with up to <bits> fewer index bits per square, these are rare");
+    println!("and every square that has none costs --tries candidates");
+}
+
+struct Args {
+    seed: u64,
+    reduce: u8,
+    tries: usize,
+    out: Option<String>,
+}
+
+fn parse_args() -> Option<Args> {
+    let mut args = Args {
+        seed: 1947,
+        reduce: 0,
+        tries: 100_000_000,
+        out: None,
+    };
+
+    let mut iter = std::env::args().skip(1);
+    while let Some(flag) = iter.next() {
+        let value = iter.next()?;
+        match flag.as_str() {
+            "--seed" => args.seed = value.parse().ok()?,
+            "--reduce" => args.reduce = value.parse().ok()?,
+            "--tries" => args.tries = value.parse().ok()?,
+            "--out" => args.out = Some(value),
+            _ => return None,
+        }
+    }
+
+    Some(args)
+}
+
+fn main() {
+    let Some(args) = parse_args() else {
+        print_usage();
+        std::process::exit(1);
+    };
+
+    let Some(rook) = find_magics(true, args.reduce, args.tries, args.seed) else {
+        eprintln!("no rook magics found, try more --tries");
+        std::process::exit(1);
+    };
+    let Some(bishop) = find_magics(false, args.reduce, args.tries, args.seed) else {
+        eprintln!("no bishop magics found, try more --tries");
+        std::process::exit(1);
+    };
+
+    eprintln!("rook table entries   : {}", rook.table_size());
+    eprintln!("bishop table entries : {}", bishop.table_size());
+
+    let source = format!(
+        "// generated by kelp_magics --seed {} --reduce {} --tries {}\n\n{}",
+        args.seed,
+        args.reduce,
+        args.tries,
+        magics_to_rust(&rook, &bishop)
+    );
+
+    match args.out {
+        Some(path) => {
+            if let Err(e) = std::fs::write(&path, source) {
+                eprintln!("could not write {}: {}", path, e);
+                std::process::exit(1);
+            }
+        }
+        None => print!("{}", source),
+    }
+}
@@ -2,15 +2,12 @@ use kelp_engine::kelp::board::{
     board::Board,
     fen::{Fen, FenParse},
 };
-use kelp_engine::kelp::kelp_core::lookup_table::LookupTable;
 use kelp_engine::kelp::mov_gen::{generator::MovGen, perft::*};
 fn print_usage() {
     println!("Usage: kelp_perft <depth> <fen>");
 }
 fn main() {
-    let mut table = LookupTable::new();
-    table.populate();
-    let mut movgen = MovGen::new(&table);
+    let mut movgen = MovGen::new();
     let starring_fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
     let args = std::env::args().collect::<Vec<String>>();
     let depth = args.get(1);